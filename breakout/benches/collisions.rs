@@ -0,0 +1,143 @@
+//! How long one fixed timestep of the simulation takes in a dense level:
+//! 2,000 bricks, with 20 balls bouncing around between them.
+//!
+//! Run with `cargo bench --bench collisions`.
+
+use std::time::Instant;
+
+use bevy::{prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+use breakout::{
+    Ball, BreakoutSimPlugin, Brick, Collider, Velocity,
+    arena::ArenaConfig,
+    level::{BrickKind, CustomLevel, Level},
+    state::GameState,
+};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const COLUMNS: usize = 50;
+const ROWS: usize = 40;
+const BALLS: usize = 20;
+const BRICK_SIZE: Vec2 = Vec2::new(100.0, 30.0);
+// The bricks are spread out, leaving room for the balls to travel between them
+const BRICK_SPACING: Vec2 = Vec2::new(140.0, 70.0);
+
+// The center of the brick at `column` and `row`, with the bricks centered on the origin
+fn brick_position(column: usize, row: usize) -> Vec2 {
+    let offset = Vec2::new(COLUMNS as f32 - 1.0, ROWS as f32 - 1.0) / 2.0;
+    (Vec2::new(column as f32, row as f32) - offset) * BRICK_SPACING
+}
+
+fn dense_level() -> App {
+    // Big enough for the bricks, and for the paddle below them
+    let arena = ArenaConfig {
+        left: -3600.0,
+        right: 3600.0,
+        bottom: -1600.0,
+        top: 1600.0,
+        ..default()
+    };
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ))
+        .insert_resource(arena)
+        .add_plugins(BreakoutSimPlugin);
+
+    // Run the startup systems before starting a level
+    app.update();
+
+    // Start a level of its own, so that no level files need loading
+    let level = Level {
+        name: "Dense".to_string(),
+        ball_speed: 400.0,
+        gap: 5.0,
+        difficulty: None,
+        stars: Vec::new(),
+        cells: vec![vec![Some(BrickKind::Normal)]],
+        obstacles: Vec::new(),
+    };
+    let level = app.world_mut().resource_mut::<Assets<Level>>().add(level);
+    app.world_mut().insert_resource(CustomLevel(level));
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app.update();
+
+    // The level's own brick is its only breakable one. Without a collider no ball can reach it,
+    // so the level is never cleared and the simulation keeps stepping.
+    let level_brick = app
+        .world_mut()
+        .query_filtered::<Entity, With<Brick>>()
+        .single(app.world())
+        .unwrap();
+    app.world_mut().entity_mut(level_brick).remove::<Collider>();
+
+    // The bricks can't be broken, so the level stays as dense however long it runs
+    for row in 0..ROWS {
+        for column in 0..COLUMNS {
+            app.world_mut().spawn((
+                Transform::from_translation(brick_position(column, row).extend(0.0))
+                    .with_scale(BRICK_SIZE.extend(1.0)),
+                Brick {
+                    kind: BrickKind::Unbreakable,
+                    hit_points: 1,
+                    score: 0,
+                },
+                Collider,
+            ));
+        }
+    }
+
+    // A floor, so that no balls are lost
+    app.world_mut().spawn((
+        Transform::from_xyz(0.0, arena.bottom + 20.0, 0.0).with_scale(Vec3::new(
+            arena.width(),
+            20.0,
+            1.0,
+        )),
+        Collider,
+    ));
+
+    // Each ball starts where four bricks meet, heading off in its own direction
+    for i in 0..BALLS {
+        let position =
+            brick_position((i * 11) % (COLUMNS - 1), (i * 7) % (ROWS - 1)) + BRICK_SPACING / 2.0;
+        app.world_mut().spawn((
+            Ball,
+            Velocity(Vec2::from_angle(i as f32) * 400.0),
+            Transform::from_translation(position.extend(1.0)),
+        ));
+    }
+
+    app
+}
+
+fn fixed_timestep(c: &mut Criterion) {
+    let mut app = dense_level();
+
+    c.bench_function("fixed timestep with 2,000 bricks and 20 balls", |b| {
+        b.iter_custom(|iterations| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                app.update();
+            }
+            let elapsed = start.elapsed();
+
+            // Otherwise the simulation has stopped, and this timed idle updates
+            assert_eq!(
+                *app.world().resource::<State<GameState>>().get(),
+                GameState::Playing
+            );
+            elapsed
+        });
+    });
+
+    // Nothing should have been lost along the way
+    let mut balls = app.world_mut().query_filtered::<(), With<Ball>>();
+    assert_eq!(balls.iter(app.world()).count(), BALLS + 1);
+}
+
+criterion_group!(benches, fixed_timestep);
+criterion_main!(benches);
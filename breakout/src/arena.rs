@@ -0,0 +1,120 @@
+//! The size of the playing field, which the walls, the level layouts and the camera follow.
+
+use bevy::prelude::*;
+
+use crate::{BALL_DIAMETER, GAP_BETWEEN_PADDLE_AND_FLOOR, PADDLE_SIZE};
+
+/// Where the walls of the arena are, in world units.
+///
+/// There is no wall at the bottom: balls that get past the paddle fall into a kill zone.
+/// Levels are validated against this when they load, so insert a custom one
+/// before adding [`BreakoutSimPlugin`](crate::BreakoutSimPlugin).
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct ArenaConfig {
+    /// The x coordinate of the center of the left wall
+    pub left: f32,
+    pub right: f32,
+    /// The y coordinate of the kill zone
+    pub bottom: f32,
+    pub top: f32,
+    pub wall_thickness: f32,
+    /// How much of the world the camera shows, centered on the origin.
+    /// It is scaled to fit the window, leaving bars at the sides or top and bottom if the
+    /// window's shape doesn't match.
+    pub view_size: Vec2,
+}
+
+impl Default for ArenaConfig {
+    fn default() -> Self {
+        ArenaConfig {
+            left: -450.0,
+            right: 450.0,
+            bottom: -300.0,
+            top: 300.0,
+            wall_thickness: 10.0,
+            view_size: Vec2::new(1280.0, 720.0),
+        }
+    }
+}
+
+impl ArenaConfig {
+    /// The distance between the centers of the side walls
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    /// The distance between the kill zone and the center of the top wall
+    pub fn height(&self) -> f32 {
+        self.top - self.bottom
+    }
+
+    pub fn center_x(&self) -> f32 {
+        (self.left + self.right) / 2.0
+    }
+
+    /// Halfway between the side walls, and between the kill zone and the top wall
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.center_x(), (self.bottom + self.top) / 2.0)
+    }
+
+    /// Whether `point` is between the walls and above the kill zone
+    pub fn contains(&self, point: Vec2) -> bool {
+        Rect::new(self.left, self.bottom, self.right, self.top).contains(point)
+    }
+
+    /// Where a ball's center is when its edge touches the inside of each wall,
+    /// or at the bottom, the kill zone
+    pub fn ball_bounds(&self) -> Rect {
+        let inset = self.wall_thickness / 2.0 + BALL_DIAMETER / 2.0;
+        Rect::new(
+            self.left + inset,
+            self.bottom,
+            self.right - inset,
+            self.top - inset,
+        )
+    }
+
+    /// The height of the center of the paddle
+    pub fn paddle_y(&self) -> f32 {
+        self.bottom + GAP_BETWEEN_PADDLE_AND_FLOOR
+    }
+
+    /// The ball starts each life resting on top of the paddle.
+    /// Its z-value is 1 so it renders on top in the case of overlapping sprites.
+    pub fn ball_starting_position(&self) -> Vec3 {
+        Vec3::new(
+            self.center_x(),
+            self.paddle_y() + PADDLE_SIZE.y / 2.0 + BALL_DIAMETER / 2.0,
+            1.0,
+        )
+    }
+
+    /// The largest rectangle with the shape of [`view_size`](Self::view_size)
+    /// that fits in a window of `window_size`, centered in it
+    pub fn letterbox(&self, window_size: Vec2) -> Rect {
+        let scale = (window_size / self.view_size).min_element();
+        Rect::from_center_size(window_size / 2.0, self.view_size * scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterbox_keeps_the_view_shape() {
+        let arena = ArenaConfig::default();
+
+        // Too wide: bars at the sides
+        let rect = arena.letterbox(Vec2::new(1920.0, 720.0));
+        assert_eq!(rect, Rect::new(320.0, 0.0, 1600.0, 720.0));
+
+        // Too tall: bars at the top and bottom
+        let rect = arena.letterbox(Vec2::new(640.0, 720.0));
+        assert_eq!(rect, Rect::new(0.0, 180.0, 640.0, 540.0));
+
+        // Just right, but bigger
+        let rect = arena.letterbox(Vec2::new(2560.0, 1440.0));
+        assert_eq!(rect, Rect::new(0.0, 0.0, 2560.0, 1440.0));
+    }
+}
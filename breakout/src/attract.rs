@@ -0,0 +1,131 @@
+//! The attract-mode demo: after a while on the title screen with no input,
+//! the autopilot plays a random level until the player presses something.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    CurrentLevel, Levels, Lives, STARTING_LIVES, Score,
+    autopilot::Autopilot,
+    check_for_level_cleared,
+    input::MenuInput,
+    replay::Replay,
+    state::{GameState, InGame, prompt, toggle_pause},
+    versus::GameMode,
+};
+
+// Seconds on the title screen without any input before the demo starts
+const ATTRACT_DELAY: f32 = 15.0;
+
+/// Present while the demo is playing
+#[derive(Resource)]
+pub(crate) struct AttractMode;
+
+#[derive(Resource, Deref, DerefMut)]
+struct IdleTimer(Timer);
+
+pub struct AttractModePlugin;
+
+impl Plugin for AttractModePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(IdleTimer(Timer::from_seconds(
+            ATTRACT_DELAY,
+            TimerMode::Once,
+        )))
+        .add_systems(OnEnter(GameState::Title), stop_demo)
+        .add_systems(
+            Update,
+            start_demo
+                .run_if(in_state(GameState::Title))
+                // Replays start from the title screen too
+                .run_if(not(resource_exists::<Replay>)),
+        )
+        .add_systems(
+            OnEnter(InGame),
+            spawn_demo_banner.run_if(resource_exists::<AttractMode>),
+        )
+        .add_systems(
+            Update,
+            // Any key leaves the demo, including the ones that would otherwise pause it
+            leave_demo
+                .after(toggle_pause)
+                .run_if(in_state(InGame))
+                .run_if(resource_exists::<AttractMode>),
+        )
+        .add_systems(
+            FixedUpdate,
+            end_demo_with_level
+                .after(check_for_level_cleared)
+                .run_if(in_state(GameState::Playing))
+                .run_if(resource_exists::<AttractMode>),
+        );
+    }
+}
+
+fn stop_demo(mut commands: Commands, mut idle_timer: ResMut<IdleTimer>) {
+    commands.remove_resource::<AttractMode>();
+    commands.remove_resource::<Autopilot>();
+    idle_timer.reset();
+}
+
+fn start_demo(
+    mut commands: Commands,
+    menu_input: MenuInput,
+    time: Res<Time>,
+    mut idle_timer: ResMut<IdleTimer>,
+    asset_server: Res<AssetServer>,
+    levels: Res<Levels>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if menu_input.any() {
+        idle_timer.reset();
+        return;
+    }
+
+    if !idle_timer.tick(time.delta()).finished()
+        || !levels
+            .0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    {
+        return;
+    }
+
+    commands.insert_resource(AttractMode);
+    commands.insert_resource(Autopilot);
+    commands.insert_resource(GameMode::Solo);
+    commands.insert_resource(CurrentLevel(
+        rand::thread_rng().gen_range(0..levels.0.len()),
+    ));
+    commands.insert_resource(Score(0));
+    commands.insert_resource(Lives(STARTING_LIVES));
+    next_state.set(GameState::Playing);
+}
+
+fn spawn_demo_banner(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        StateScoped(InGame),
+        children![prompt("Demo - press any key to play")],
+    ));
+}
+
+fn leave_demo(menu_input: MenuInput, mut next_state: ResMut<NextState<GameState>>) {
+    if menu_input.any() {
+        next_state.set(GameState::Title);
+    }
+}
+
+// The demo goes back to the title screen when its level ends, however it ends,
+// rather than showing the screens between levels or asking for a high score name
+fn end_demo_with_level(mut next_state: ResMut<NextState<GameState>>) {
+    if matches!(*next_state, NextState::Pending(_)) {
+        next_state.set(GameState::Title);
+    }
+}
@@ -0,0 +1,302 @@
+//! A computer player, for the attract-mode demo and for playing levels unattended in tests.
+//!
+//! It steers the paddle through [`PaddleIntent`], just like the player's input does,
+//! so everything it plays can be recorded and replayed.
+
+use bevy::{
+    math::bounding::{BoundingCircle, BoundingVolume},
+    prelude::*,
+};
+
+use crate::{
+    BALL_DIAMETER, Ball, Brick, Collider, MIN_BALL_VERTICAL_SPEED_FRACTION, Paddle, StuckToPaddle,
+    Velocity,
+    arena::ArenaConfig,
+    collision::{ColliderShape, Volume},
+    input::PaddleIntent,
+    paddle_bounce,
+    state::GameState,
+};
+
+// How far from the center of the paddle the autopilot is willing to hit the ball,
+// as a fraction of the paddle's half width, to aim it at the remaining bricks
+const MAX_AIM: f32 = 0.8;
+// How many different shots the autopilot considers, spread evenly across that part of the paddle
+const AIM_STEPS: usize = 32;
+// How many bounces off walls and unbreakable bricks the autopilot looks ahead for each shot
+const MAX_SHOT_BOUNCES: usize = 6;
+
+/// Plays the game by itself while this resource exists
+#[derive(Resource, Default)]
+pub struct Autopilot;
+
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        // Input is read before the fixed timestep runs, so steer the paddle at the same point
+        app.add_systems(
+            FixedPreUpdate,
+            drive_paddle
+                .run_if(resource_exists::<Autopilot>)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Where and when a ball will next reach the height of the paddle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Landing {
+    pub x: f32,
+    /// Seconds from now
+    pub time: f32,
+}
+
+/// Predicts where a ball at `position` will cross `landing_y` on its way down,
+/// bouncing off the side and top walls of `arena` but ignoring everything else in its way.
+///
+/// Returns `None` if the ball is moving sideways, or has already fallen below `landing_y`.
+pub fn predict_landing(
+    arena: &ArenaConfig,
+    position: Vec2,
+    velocity: Vec2,
+    landing_y: f32,
+) -> Option<Landing> {
+    let bounds = arena.ball_bounds();
+    let (left, right, top) = (bounds.min.x, bounds.max.x, bounds.max.y);
+
+    if position.y < landing_y {
+        return None;
+    }
+
+    let distance = if velocity.y < 0.0 {
+        position.y - landing_y
+    } else if velocity.y > 0.0 {
+        (top - position.y).max(0.0) + (top - landing_y)
+    } else {
+        return None;
+    };
+    let time = distance / velocity.y.abs();
+
+    // Unfold the bounces off the side walls: the ball travels in a straight line
+    // through mirrored copies of the arena, so fold that line back into the real one
+    let width = right - left;
+    let unfolded = (position.x + velocity.x * time - left).rem_euclid(2.0 * width);
+    let x = left + width - (unfolded - width).abs();
+
+    Some(Landing { x, time })
+}
+
+// Launch any ball waiting on the paddle, then get under whichever ball will land first,
+// hitting it off center to send it towards a brick that it can break
+#[allow(clippy::type_complexity)]
+fn drive_paddle(
+    mut intent: ResMut<PaddleIntent>,
+    paddle_transform: Single<&Transform, With<Paddle>>,
+    ball_query: Query<(&Transform, &Velocity, Has<StuckToPaddle>), With<Ball>>,
+    collider_query: Query<
+        (&Transform, &ColliderShape, Option<&Brick>),
+        (With<Collider>, Without<Paddle>),
+    >,
+    arena: Res<ArenaConfig>,
+) {
+    let paddle_top = paddle_transform.translation.y + paddle_transform.scale.y / 2.0;
+    let landing_y = paddle_top + BALL_DIAMETER / 2.0;
+
+    let mut waiting = false;
+    let mut first_landing: Option<Landing> = None;
+    for (transform, velocity, stuck) in &ball_query {
+        if stuck {
+            waiting = true;
+            continue;
+        }
+
+        let landing = predict_landing(
+            &arena,
+            transform.translation.truncate(),
+            **velocity,
+            landing_y,
+        );
+        if let Some(landing) = landing
+            && first_landing.is_none_or(|first| landing.time < first.time)
+        {
+            first_landing = Some(landing);
+        }
+    }
+
+    let target_x = match first_landing {
+        Some(landing) => {
+            let landing_point = Vec2::new(landing.x, landing_y);
+            let obstacles: Vec<(Volume, bool)> = collider_query
+                .iter()
+                .map(|(transform, shape, brick)| {
+                    let breakable = brick.is_some_and(|brick| brick.kind.is_breakable());
+                    (Volume::new(transform, *shape), breakable)
+                })
+                .collect();
+
+            // Try hitting the ball with different parts of the paddle,
+            // and pick the shot that reaches a brick that can still be broken soonest
+            let best_shot = (0..=AIM_STEPS)
+                .map(|step| MAX_AIM * (2.0 * step as f32 / AIM_STEPS as f32 - 1.0))
+                .filter_map(|offset| {
+                    trace_shot(
+                        &arena,
+                        landing_point,
+                        paddle_bounce(offset, 1.0),
+                        &obstacles,
+                    )
+                    .map(|distance| (offset, distance))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            // Otherwise, aim for the nearest brick that can still be broken and hope for the best
+            let offset = best_shot.map(|(offset, _)| offset).unwrap_or_else(|| {
+                obstacles
+                    .iter()
+                    .filter(|(_, breakable)| *breakable)
+                    .map(|(volume, _)| volume.aabb().center())
+                    .min_by(|a, b| {
+                        a.distance_squared(landing_point)
+                            .total_cmp(&b.distance_squared(landing_point))
+                    })
+                    .map_or(0.0, |brick| {
+                        // This undoes `paddle_bounce`, finding where on the paddle
+                        // to hit the ball to send it in that direction
+                        let direction = (brick - landing_point).normalize_or_zero();
+                        let max_horizontal =
+                            (1.0 - MIN_BALL_VERTICAL_SPEED_FRACTION.powi(2)).sqrt();
+                        (direction.x / max_horizontal).clamp(-MAX_AIM, MAX_AIM)
+                    })
+            });
+
+            landing.x - offset * paddle_transform.scale.x / 2.0
+        }
+        None => paddle_transform.translation.x,
+    };
+
+    *intent = PaddleIntent {
+        direction: 0.0,
+        target_x: Some(target_x),
+        launch: waiting,
+    };
+}
+
+// Follows a ball from `start` in `direction`, bouncing off walls and unbreakable bricks,
+// and returns how far it travels before hitting a brick that can be broken, if it does
+fn trace_shot(
+    arena: &ArenaConfig,
+    mut start: Vec2,
+    mut direction: Vec2,
+    obstacles: &[(Volume, bool)],
+) -> Option<f32> {
+    // Further than the ball can go without hitting anything
+    let range = arena.width() + arena.height();
+    let mut distance = 0.0;
+
+    for _ in 0..=MAX_SHOT_BOUNCES {
+        let ball = BoundingCircle::new(start, BALL_DIAMETER / 2.0);
+        let motion = direction * range;
+        let (hit, breakable) = obstacles
+            .iter()
+            .filter_map(|(volume, breakable)| {
+                volume.sweep(ball, motion).map(|hit| (hit, *breakable))
+            })
+            .min_by(|(a, _), (b, _)| a.time.total_cmp(&b.time))?;
+
+        distance += hit.time * range;
+        if breakable {
+            return Some(distance);
+        }
+
+        start += motion * hit.time;
+        direction = direction.reflect(hit.normal);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LANDING_Y: f32 = -200.0;
+
+    #[test]
+    fn falling_straight_down() {
+        let arena = ArenaConfig::default();
+        let landing = predict_landing(
+            &arena,
+            Vec2::new(50.0, 100.0),
+            Vec2::new(0.0, -300.0),
+            LANDING_Y,
+        );
+        assert_eq!(landing, Some(Landing { x: 50.0, time: 1.0 }));
+    }
+
+    #[test]
+    fn bounces_off_side_walls() {
+        let arena = ArenaConfig::default();
+        let bounds = arena.ball_bounds();
+        let right = bounds.max.x;
+
+        // Reaches the right wall halfway down, then comes back the same distance
+        let landing = predict_landing(
+            &arena,
+            Vec2::new(right - 150.0, 100.0),
+            Vec2::new(300.0, -300.0),
+            LANDING_Y,
+        )
+        .unwrap();
+        assert!((landing.x - (right - 150.0)).abs() < 1e-3, "{landing:?}");
+
+        // Far enough to bounce off both walls
+        let width = bounds.width();
+        let landing = predict_landing(
+            &arena,
+            Vec2::new(0.0, 100.0),
+            Vec2::new(width * 20.0 / 3.0, -100.0),
+            LANDING_Y,
+        )
+        .unwrap();
+        assert!(landing.x.abs() < 1e-2, "{landing:?}");
+    }
+
+    #[test]
+    fn rising_ball_comes_back_down_from_the_top_wall() {
+        let arena = ArenaConfig::default();
+        let top = arena.ball_bounds().max.y;
+        let landing = predict_landing(
+            &arena,
+            Vec2::new(0.0, top - 100.0),
+            Vec2::new(0.0, 100.0),
+            LANDING_Y,
+        )
+        .unwrap();
+        assert_eq!(landing.x, 0.0);
+        assert!((landing.time - (1.0 + (top - LANDING_Y) / 100.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn no_landing_for_sideways_or_lost_balls() {
+        let arena = ArenaConfig::default();
+        assert_eq!(
+            predict_landing(
+                &arena,
+                Vec2::new(0.0, 0.0),
+                Vec2::new(300.0, 0.0),
+                LANDING_Y
+            ),
+            None
+        );
+        assert_eq!(
+            predict_landing(
+                &arena,
+                Vec2::new(0.0, -250.0),
+                Vec2::new(0.0, -300.0),
+                LANDING_Y
+            ),
+            None
+        );
+    }
+}
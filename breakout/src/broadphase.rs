@@ -0,0 +1,196 @@
+//! A uniform grid of the colliders in the arena, so that each ball is only swept against the
+//! colliders near its path, rather than every brick in the level.
+//!
+//! Colliders are added and removed by observers as they are spawned and despawned,
+//! and moved to the cells they now cover each step if they have moved.
+
+use bevy::{
+    math::bounding::{Aabb2d, BoundingCircle},
+    platform::collections::HashMap,
+    prelude::*,
+};
+
+use crate::{
+    Collider,
+    collision::{ColliderShape, Volume},
+};
+
+// About the size of a brick, so most bricks only cover a cell or two
+const CELL_SIZE: f32 = 128.0;
+
+/// Which colliders cover each cell of a uniform grid
+#[derive(Resource, Debug, Default)]
+pub struct Broadphase {
+    cells: HashMap<IVec2, Vec<Entity>>,
+    // The range of cells each collider covers, so it can be taken out of them again
+    colliders: HashMap<Entity, (IVec2, IVec2)>,
+}
+
+impl Broadphase {
+    /// Puts `entity` in every cell that `aabb` covers, taking it out of any it no longer does
+    pub fn insert(&mut self, entity: Entity, aabb: Aabb2d) {
+        let range = cell_range(aabb);
+        if self.colliders.get(&entity) == Some(&range) {
+            return;
+        }
+
+        self.remove(entity);
+        for cell in cells(range) {
+            self.cells.entry(cell).or_default().push(entity);
+        }
+        self.colliders.insert(entity, range);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(range) = self.colliders.remove(&entity) else {
+            return;
+        };
+
+        for cell in cells(range) {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|other| *other != entity);
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Every collider in the cells that `aabb` covers, each once, in order.
+    ///
+    /// These might not touch `aabb`, but every collider that does is included.
+    pub fn query(&self, aabb: Aabb2d) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = cells(cell_range(aabb))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+
+        // Sorted, so that colliders hit at the same time are picked the same way every run
+        entities.sort_unstable();
+        entities.dedup();
+        entities
+    }
+
+    pub fn len(&self) -> usize {
+        self.colliders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colliders.is_empty()
+    }
+}
+
+// The lowest and highest cells that `aabb` covers
+fn cell_range(aabb: Aabb2d) -> (IVec2, IVec2) {
+    (
+        (aabb.min / CELL_SIZE).floor().as_ivec2(),
+        (aabb.max / CELL_SIZE).floor().as_ivec2(),
+    )
+}
+
+fn cells((min, max): (IVec2, IVec2)) -> impl Iterator<Item = IVec2> {
+    (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+}
+
+// The box that `ball` covers while moving by `motion`
+pub(crate) fn swept_aabb(ball: BoundingCircle, motion: Vec2) -> Aabb2d {
+    let start = ball.aabb_2d();
+    Aabb2d {
+        min: start.min + motion.min(Vec2::ZERO),
+        max: start.max + motion.max(Vec2::ZERO),
+    }
+}
+
+// Colliders are added when they are spawned, or when their `Collider` is inserted again
+pub(crate) fn add_to_broadphase(
+    trigger: Trigger<OnInsert, Collider>,
+    colliders: Query<(&Transform, &ColliderShape)>,
+    mut broadphase: ResMut<Broadphase>,
+) {
+    if let Ok((transform, shape)) = colliders.get(trigger.target()) {
+        broadphase.insert(trigger.target(), Volume::new(transform, *shape).aabb());
+    }
+}
+
+pub(crate) fn remove_from_broadphase(
+    trigger: Trigger<OnRemove, Collider>,
+    mut broadphase: ResMut<Broadphase>,
+) {
+    broadphase.remove(trigger.target());
+}
+
+// Move colliders that have moved, turned or changed size, such as paddles and obstacles
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_broadphase(
+    colliders: Query<
+        (Entity, &Transform, &ColliderShape),
+        (
+            With<Collider>,
+            Or<(Changed<Transform>, Changed<ColliderShape>)>,
+        ),
+    >,
+    mut broadphase: ResMut<Broadphase>,
+) {
+    for (entity, transform, shape) in &colliders {
+        broadphase.insert(entity, Volume::new(transform, *shape).aabb());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aabb(min: Vec2, max: Vec2) -> Aabb2d {
+        Aabb2d { min, max }
+    }
+
+    #[test]
+    fn finds_colliders_in_overlapping_cells() {
+        let mut world = World::new();
+        let [small, wide, far] = [(); 3].map(|_| world.spawn_empty().id());
+
+        let mut broadphase = Broadphase::default();
+        broadphase.insert(small, aabb(Vec2::new(10.0, 10.0), Vec2::new(20.0, 20.0)));
+        broadphase.insert(wide, aabb(Vec2::new(-300.0, 0.0), Vec2::new(300.0, 10.0)));
+        broadphase.insert(
+            far,
+            aabb(Vec2::new(1000.0, 1000.0), Vec2::new(1010.0, 1010.0)),
+        );
+
+        // Each collider is only returned once, however many cells it shares with the query
+        let near_origin = broadphase.query(aabb(Vec2::splat(-50.0), Vec2::splat(50.0)));
+        assert_eq!(near_origin, vec![small, wide]);
+        assert_eq!(
+            broadphase.query(aabb(Vec2::new(200.0, 0.0), Vec2::new(210.0, 5.0))),
+            vec![wide]
+        );
+        assert_eq!(
+            broadphase.query(aabb(Vec2::new(500.0, 500.0), Vec2::new(510.0, 510.0))),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn moves_and_removes_colliders() {
+        let mut world = World::new();
+        let entity = world.spawn_empty().id();
+        let mut broadphase = Broadphase::default();
+
+        broadphase.insert(entity, aabb(Vec2::ZERO, Vec2::splat(10.0)));
+        broadphase.insert(entity, aabb(Vec2::splat(500.0), Vec2::splat(510.0)));
+        assert_eq!(broadphase.len(), 1);
+        assert_eq!(
+            broadphase.query(aabb(Vec2::ZERO, Vec2::splat(10.0))),
+            vec![]
+        );
+        assert_eq!(
+            broadphase.query(aabb(Vec2::splat(500.0), Vec2::splat(510.0))),
+            vec![entity]
+        );
+
+        broadphase.remove(entity);
+        assert!(broadphase.is_empty());
+        assert!(broadphase.cells.is_empty());
+    }
+}
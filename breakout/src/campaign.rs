@@ -0,0 +1,350 @@
+//! The campaign: the bundled [`LEVELS`], played in order, and the player's progress through them.
+//!
+//! Clearing a level in a solo game unlocks the next one, and records the level's best score,
+//! fastest time and most stars, rated against the star scores in its level file.
+//! Unlocked levels can be played again from the level-select screen.
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CurrentLevel, Level, Levels, Lives, STARTING_LIVES, Score,
+    input::MenuInput,
+    level::{CustomLevel, LEVELS},
+    menu::{MenuAction, MenuChoice, menu},
+    persist,
+    replay::Replay,
+    state::{GameState, LevelClock, LevelStart, screen, spawn_level_cleared_screen},
+    versus::GameMode,
+};
+
+const PROGRESS_FILE: &str = "progress.ron";
+
+/// Loads the [`CampaignProgress`], records each cleared level in it,
+/// and adds the level-select screen, opened from the title screen
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        let path = persist::data_path(PROGRESS_FILE);
+        let progress = path
+            .as_deref()
+            .and_then(persist::load::<CampaignProgress>)
+            .unwrap_or_default();
+
+        app.insert_resource(progress)
+            .insert_resource(ProgressPath(path))
+            .add_systems(
+                OnEnter(GameState::LevelCleared),
+                record_level_result
+                    .before(spawn_level_cleared_screen)
+                    .run_if(in_campaign),
+            )
+            .add_systems(OnExit(GameState::LevelCleared), |mut commands: Commands| {
+                commands.remove_resource::<LevelResult>();
+            })
+            .add_systems(
+                Update,
+                save_progress.run_if(
+                    resource_changed::<CampaignProgress>
+                        .and(not(resource_added::<CampaignProgress>)),
+                ),
+            )
+            .add_systems(
+                Update,
+                open_level_select
+                    .run_if(in_state(GameState::Title).and(not(resource_exists::<CustomLevel>))),
+            )
+            .add_systems(OnEnter(GameState::LevelSelect), spawn_level_select)
+            .add_systems(
+                Update,
+                choose_level.run_if(in_state(GameState::LevelSelect)),
+            );
+    }
+}
+
+/// The best that a level has been played
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct LevelRecord {
+    pub best_score: usize,
+    /// The fastest clear, in seconds
+    pub best_time: f32,
+    pub stars: usize,
+}
+
+/// Which levels of the campaign have been cleared, and how well.
+///
+/// Saved as `progress.ron` in the user's data directory.
+/// Levels are recorded by their path in [`LEVELS`], so that adding levels keeps the progress.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CampaignProgress {
+    cleared: BTreeMap<String, LevelRecord>,
+}
+
+impl CampaignProgress {
+    /// The record of `level`, an index into [`LEVELS`], if it has been cleared
+    pub fn record(&self, level: usize) -> Option<&LevelRecord> {
+        LEVELS.get(level).and_then(|path| self.cleared.get(*path))
+    }
+
+    /// The first level is always unlocked, and clearing a level unlocks the one after it
+    pub fn is_unlocked(&self, level: usize) -> bool {
+        level < LEVELS.len() && (level == 0 || self.record(level - 1).is_some())
+    }
+
+    /// Record that `level` was cleared with `score` points in `time`, earning `stars`.
+    /// Returns whether that beat the level's best score.
+    pub fn clear(&mut self, level: usize, score: usize, time: Duration, stars: usize) -> bool {
+        let Some(path) = LEVELS.get(level) else {
+            return false;
+        };
+        let time = time.as_secs_f32();
+
+        match self.cleared.get_mut(*path) {
+            Some(record) => {
+                let new_best = score > record.best_score;
+                record.best_score = record.best_score.max(score);
+                record.best_time = record.best_time.min(time);
+                record.stars = record.stars.max(stars);
+                new_best
+            }
+            None => {
+                self.cleared.insert(
+                    path.to_string(),
+                    LevelRecord {
+                        best_score: score,
+                        best_time: time,
+                        stars,
+                    },
+                );
+                true
+            }
+        }
+    }
+}
+
+/// Where the campaign progress is saved, if the platform has a data directory
+#[derive(Resource)]
+struct ProgressPath(Option<PathBuf>);
+
+/// How the level that was just cleared went, for the level-cleared screen
+#[derive(Resource, Debug)]
+pub(crate) struct LevelResult {
+    pub(crate) score: usize,
+    pub(crate) time: Duration,
+    /// The stars earned, and out of how many, if the level is rated
+    pub(crate) stars: Option<(usize, usize)>,
+    pub(crate) new_best: bool,
+}
+
+/// Only solo games of the bundled levels count towards the campaign, and not replays of them
+fn in_campaign(
+    game_mode: Res<GameMode>,
+    custom_level: Option<Res<CustomLevel>>,
+    replay: Option<Res<Replay>>,
+) -> bool {
+    *game_mode == GameMode::Solo && custom_level.is_none() && replay.is_none()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_level_result(
+    mut commands: Commands,
+    score: Res<Score>,
+    level_start: Res<LevelStart>,
+    clock: Res<LevelClock>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    mut progress: ResMut<CampaignProgress>,
+) {
+    let Some(level) = levels
+        .0
+        .get(**current_level)
+        .and_then(|handle| level_assets.get(handle))
+    else {
+        return;
+    };
+
+    // The score carries on from level to level, so only count what this level added
+    let level_score = score.saturating_sub(level_start.score);
+    let stars = level.stars_for(level_score);
+    let new_best = progress.clear(**current_level, level_score, **clock, stars);
+
+    commands.insert_resource(LevelResult {
+        score: level_score,
+        time: **clock,
+        stars: (!level.stars.is_empty()).then_some((stars, level.stars.len())),
+        new_best,
+    });
+}
+
+fn save_progress(progress: Res<CampaignProgress>, path: Res<ProgressPath>) {
+    if let Some(path) = &path.0 {
+        persist::save(path, &*progress);
+    }
+}
+
+fn open_level_select(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    levels: Res<Levels>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyS)
+        && levels
+            .0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    {
+        next_state.set(GameState::LevelSelect);
+    }
+}
+
+/// `stars` out of `out_of`, as filled and empty stars
+pub(crate) fn star_text(stars: usize, out_of: usize) -> String {
+    format!(
+        "{}{}",
+        "*".repeat(stars.min(out_of)),
+        "-".repeat(out_of.saturating_sub(stars))
+    )
+}
+
+/// A time in minutes and seconds, such as `1:05`
+pub(crate) fn time_text(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// Each level's button shows how well it has been played, or that it is locked
+fn level_label(index: usize, level: &Level, progress: &CampaignProgress) -> String {
+    let number = index + 1;
+    if !progress.is_unlocked(index) {
+        return format!("{number}. Locked");
+    }
+
+    let mut parts = vec![format!("{number}. {}", level.name)];
+    if let Some(record) = progress.record(index) {
+        if !level.stars.is_empty() {
+            parts.push(star_text(record.stars, level.stars.len()));
+        }
+        parts.push(format!(
+            "{} in {}",
+            record.best_score,
+            time_text(Duration::try_from_secs_f32(record.best_time).unwrap_or_default())
+        ));
+    }
+    parts.join("  ")
+}
+
+fn spawn_level_select(
+    mut commands: Commands,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    progress: Res<CampaignProgress>,
+) {
+    let mut items: Vec<(MenuAction, String)> = levels
+        .0
+        .iter()
+        .enumerate()
+        .filter_map(|(index, handle)| {
+            let level = level_assets.get(handle)?;
+            Some((
+                MenuAction::PlayLevel(index),
+                level_label(index, level, &progress),
+            ))
+        })
+        .collect();
+    items.push((MenuAction::Back, "Back".to_string()));
+
+    commands.spawn(screen(
+        GameState::LevelSelect,
+        "Choose a level",
+        menu(items),
+    ));
+}
+
+// Play the chosen level and the ones after it, as a new game
+#[allow(clippy::too_many_arguments)]
+fn choose_level(
+    menu_input: MenuInput,
+    mut choices: EventReader<MenuChoice>,
+    progress: Res<CampaignProgress>,
+    mut game_mode: ResMut<GameMode>,
+    mut score: ResMut<Score>,
+    mut lives: ResMut<Lives>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if menu_input.back() {
+        next_state.set(GameState::Title);
+    }
+
+    for choice in choices.read().filter(|choice| choice.step == 0) {
+        match choice.action {
+            MenuAction::PlayLevel(level) if progress.is_unlocked(level) => {
+                *game_mode = GameMode::Solo;
+                **score = 0;
+                **lives = STARTING_LIVES;
+                **current_level = level;
+                next_state.set(GameState::Playing);
+            }
+            MenuAction::Back => next_state.set(GameState::Title),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clearing_a_level_unlocks_the_next() {
+        let mut progress = CampaignProgress::default();
+        assert!(progress.is_unlocked(0));
+        assert!(!progress.is_unlocked(1));
+
+        progress.clear(0, 100, Duration::from_secs(60), 1);
+        assert!(progress.is_unlocked(1));
+        assert!(!progress.is_unlocked(2));
+        assert!(!progress.is_unlocked(LEVELS.len()));
+    }
+
+    #[test]
+    fn keeps_the_best_of_each_record() {
+        let mut progress = CampaignProgress::default();
+        assert!(progress.clear(0, 100, Duration::from_secs(60), 1));
+        assert!(!progress.clear(0, 80, Duration::from_secs(30), 0));
+        assert!(progress.clear(0, 150, Duration::from_secs(90), 2));
+
+        assert_eq!(
+            progress.record(0),
+            Some(&LevelRecord {
+                best_score: 150,
+                best_time: 30.0,
+                stars: 2,
+            })
+        );
+        assert!(!progress.clear(LEVELS.len(), 1000, Duration::ZERO, 3));
+    }
+
+    #[test]
+    fn saved_progress_parses_back() {
+        let mut progress = CampaignProgress::default();
+        progress.clear(0, 100, Duration::from_secs(65), 2);
+        let ron = ron::to_string(&progress).unwrap();
+        assert_eq!(ron::from_str::<CampaignProgress>(&ron).unwrap(), progress);
+    }
+
+    #[test]
+    fn formats_stars_and_times() {
+        assert_eq!(star_text(2, 3), "**-");
+        assert_eq!(star_text(0, 3), "---");
+        assert_eq!(time_text(Duration::from_secs(65)), "1:05");
+        assert_eq!(time_text(Duration::from_millis(59_900)), "0:59");
+    }
+}
@@ -0,0 +1,610 @@
+use bevy::math::bounding::{Aabb2d, BoundingCircle, BoundingVolume, IntersectsVolume};
+use bevy::prelude::*;
+
+/// The shape of a collider, sized by its `Transform`'s scale and turned by its rotation.
+/// Colliders are boxes unless they say otherwise.
+#[derive(Component, Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum ColliderShape {
+    #[default]
+    Box,
+    /// A circle as wide as the scale's `x`
+    Circle,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Collision {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Collision {
+    /// Unit vector pointing out of the side of the box that was hit
+    pub fn normal(self) -> Vec2 {
+        match self {
+            Collision::Left => Vec2::NEG_X,
+            Collision::Right => Vec2::X,
+            Collision::Top => Vec2::Y,
+            Collision::Bottom => Vec2::NEG_Y,
+        }
+    }
+
+    // The side of a box that a ball at `offset` from its closest point on the box is touching
+    fn from_offset(offset: Vec2) -> Collision {
+        if offset.x.abs() > offset.y.abs() {
+            if offset.x < 0. {
+                Collision::Left
+            } else {
+                Collision::Right
+            }
+        } else if offset.y > 0. {
+            Collision::Top
+        } else {
+            Collision::Bottom
+        }
+    }
+}
+
+/// The box covered by a sprite-like entity, whose size is stored in its scale
+pub fn bounding_box(transform: &Transform) -> Aabb2d {
+    Aabb2d::new(
+        transform.translation.truncate(),
+        transform.scale.truncate() / 2.,
+    )
+}
+
+/// The space a collider takes up in the world
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Volume {
+    Box {
+        center: Vec2,
+        half_size: Vec2,
+        rotation: Rot2,
+    },
+    Circle(BoundingCircle),
+}
+
+/// The first contact of a moving ball with a [`Volume`]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Contact {
+    /// Fraction of the motion travelled before the hit, from 0.0 to 1.0
+    pub time: f32,
+    /// Unit vector pointing out of the volume where the ball touched it
+    pub normal: Vec2,
+}
+
+impl Volume {
+    /// The volume of a collider with this `transform` and `shape`
+    pub fn new(transform: &Transform, shape: ColliderShape) -> Volume {
+        let center = transform.translation.truncate();
+        match shape {
+            ColliderShape::Box => Volume::Box {
+                center,
+                half_size: transform.scale.truncate() / 2.,
+                rotation: Rot2::radians(transform.rotation.to_euler(EulerRot::XYZ).2),
+            },
+            ColliderShape::Circle => {
+                Volume::Circle(BoundingCircle::new(center, transform.scale.x / 2.))
+            }
+        }
+    }
+
+    /// The axis-aligned box around this volume
+    pub fn aabb(&self) -> Aabb2d {
+        match *self {
+            Volume::Box {
+                center,
+                half_size,
+                rotation,
+            } => {
+                let corner = rotation * half_size;
+                let other_corner = rotation * half_size.with_y(-half_size.y);
+                Aabb2d::new(center, corner.abs().max(other_corner.abs()))
+            }
+            Volume::Circle(circle) => circle.aabb_2d(),
+        }
+    }
+
+    /// Returns the first time `ball` touches this volume while moving by `motion`,
+    /// like [`sweep_ball`] does for boxes that aren't rotated
+    pub fn sweep(&self, ball: BoundingCircle, motion: Vec2) -> Option<Contact> {
+        match *self {
+            Volume::Box {
+                center,
+                half_size,
+                rotation,
+            } => {
+                // Boxes that aren't rotated are swept as they are, so that their hits are exact
+                if rotation == Rot2::IDENTITY {
+                    return sweep_ball(ball, motion, Aabb2d::new(center, half_size)).map(|hit| {
+                        Contact {
+                            time: hit.time,
+                            normal: hit.side.normal(),
+                        }
+                    });
+                }
+
+                // Otherwise, turn the world so the box is axis-aligned at the origin,
+                // and turn the side that was hit back again
+                let inverse = rotation.inverse();
+                let local_ball =
+                    BoundingCircle::new(inverse * (ball.center() - center), ball.radius());
+                sweep_ball(
+                    local_ball,
+                    inverse * motion,
+                    Aabb2d::new(Vec2::ZERO, half_size),
+                )
+                .map(|hit| Contact {
+                    time: hit.time,
+                    normal: rotation * hit.side.normal(),
+                })
+            }
+            Volume::Circle(circle) => sweep_ball_circle(ball, motion, circle),
+        }
+    }
+
+    /// Whether this volume overlaps `other`, such as a laser, at all
+    pub fn intersects(&self, other: Aabb2d) -> bool {
+        match *self {
+            Volume::Box {
+                center,
+                half_size,
+                rotation,
+            } => {
+                // The boxes overlap unless one of their sides separates them
+                let offset = center - other.center();
+                let other_half_size = other.half_size();
+                let sides = [rotation * Vec2::X, rotation * Vec2::Y];
+                [Vec2::X, Vec2::Y, sides[0], sides[1]].iter().all(|axis| {
+                    let reach = other_half_size.dot(axis.abs())
+                        + half_size.x * sides[0].dot(*axis).abs()
+                        + half_size.y * sides[1].dot(*axis).abs();
+                    offset.dot(*axis).abs() <= reach
+                })
+            }
+            Volume::Circle(circle) => circle.intersects(&other),
+        }
+    }
+
+    /// How far `ball` would have to move along the returned normal to stop overlapping this volume,
+    /// or `None` if they aren't overlapping.
+    ///
+    /// Moving colliders can run into a ball between timesteps, and this gets it back out.
+    pub fn penetration(&self, ball: BoundingCircle) -> Option<(Vec2, f32)> {
+        match *self {
+            Volume::Box {
+                center,
+                half_size,
+                rotation,
+            } => {
+                let local_center = rotation.inverse() * (ball.center() - center);
+                let closest = local_center.clamp(-half_size, half_size);
+                let offset = local_center - closest;
+                let distance = offset.length();
+
+                let (normal, depth) = if distance > 0.0 {
+                    if distance >= ball.radius() {
+                        return None;
+                    }
+                    (offset / distance, ball.radius() - distance)
+                } else {
+                    // The center is inside the box, so leave through the nearest side
+                    let to_side = half_size - local_center.abs();
+                    if to_side.x < to_side.y {
+                        (Vec2::X * local_center.x.signum(), to_side.x + ball.radius())
+                    } else {
+                        (Vec2::Y * local_center.y.signum(), to_side.y + ball.radius())
+                    }
+                };
+                Some((rotation * normal, depth))
+            }
+            Volume::Circle(circle) => {
+                let offset = ball.center() - circle.center();
+                let reach = ball.radius() + circle.radius();
+                let distance = offset.length();
+                if distance >= reach {
+                    return None;
+                }
+
+                let normal = offset.try_normalize().unwrap_or(Vec2::Y);
+                Some((normal, reach - distance))
+            }
+        }
+    }
+}
+
+// Sweeping a circle against a circle is the same as casting a ray from its center
+// against a circle as big as both of them put together
+fn sweep_ball_circle(
+    ball: BoundingCircle,
+    motion: Vec2,
+    circle: BoundingCircle,
+) -> Option<Contact> {
+    let reach = ball.radius() + circle.radius();
+    let to_start = ball.center() - circle.center();
+
+    // Touching or overlapping already only counts as a hit when moving further in
+    if to_start.length_squared() <= reach * reach {
+        let normal = to_start.try_normalize()?;
+        return (motion.dot(normal) < 0.0).then_some(Contact { time: 0.0, normal });
+    }
+
+    let a = motion.length_squared();
+    if a == 0.0 {
+        return None;
+    }
+    let b = to_start.dot(motion);
+    let c = to_start.length_squared() - reach * reach;
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let time = (-b - discriminant.sqrt()) / a;
+    if !(0.0..=1.0).contains(&time) {
+        return None;
+    }
+
+    Some(Contact {
+        time,
+        normal: (to_start + motion * time).normalize(),
+    })
+}
+
+// Returns `Some` if `ball` collides with `bounding_box`.
+// The returned `Collision` is the side of `bounding_box` that `ball` hit.
+pub fn ball_collision(ball: BoundingCircle, bounding_box: Aabb2d) -> Option<Collision> {
+    if !ball.intersects(&bounding_box) {
+        return None;
+    }
+
+    let closest = bounding_box.closest_point(ball.center());
+    Some(Collision::from_offset(ball.center() - closest))
+}
+
+/// The first contact of a moving ball with a box
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SweepHit {
+    /// Fraction of the motion travelled before the hit, from 0.0 to 1.0
+    pub time: f32,
+    /// The side of the box that was hit
+    pub side: Collision,
+}
+
+/// Returns the first time `ball` touches `bounding_box` while moving by `motion`.
+///
+/// This is a swept test, so unlike [`ball_collision`] it finds hits that happen partway through
+/// the motion, even if the ball would have ended up entirely on the other side of the box.
+/// A ball that already overlaps the box only hits it if it is moving further in.
+pub fn sweep_ball(ball: BoundingCircle, motion: Vec2, bounding_box: Aabb2d) -> Option<SweepHit> {
+    let radius = ball.radius();
+    let start = ball.center();
+
+    if let Some(side) = ball_collision(ball, bounding_box) {
+        return (motion.dot(side.normal()) < 0.0).then_some(SweepHit { time: 0.0, side });
+    }
+
+    // Sweeping a circle against a box is the same as casting a ray from the circle's center
+    // against the box grown by the radius, with rounded corners.
+    // Start by casting against the grown box with square corners.
+    let grown = Aabb2d {
+        min: bounding_box.min - radius,
+        max: bounding_box.max + radius,
+    };
+
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut entry_side = Collision::Top;
+
+    for axis in 0..2 {
+        let (start, motion, min, max) =
+            (start[axis], motion[axis], grown.min[axis], grown.max[axis]);
+
+        if motion == 0.0 {
+            // Moving parallel to this axis' slab, so we are either always or never inside it
+            if start < min || start > max {
+                return None;
+            }
+            continue;
+        }
+
+        let (near, far, near_side) = if motion > 0.0 {
+            let near_side = if axis == 0 {
+                Collision::Left
+            } else {
+                Collision::Bottom
+            };
+            ((min - start) / motion, (max - start) / motion, near_side)
+        } else {
+            let near_side = if axis == 0 {
+                Collision::Right
+            } else {
+                Collision::Top
+            };
+            ((max - start) / motion, (min - start) / motion, near_side)
+        };
+
+        if near > entry {
+            entry = near;
+            entry_side = near_side;
+        }
+        exit = exit.min(far);
+    }
+
+    if entry > exit || entry > 1.0 || exit < 0.0 {
+        return None;
+    }
+
+    // If the center is inside the grown box at the start, we must be in one of its corners
+    let entry = entry.max(0.0);
+    let contact = start + motion * entry;
+    let within_x = (bounding_box.min.x..=bounding_box.max.x).contains(&contact.x);
+    let within_y = (bounding_box.min.y..=bounding_box.max.y).contains(&contact.y);
+
+    if within_x || within_y {
+        return Some(SweepHit {
+            time: entry,
+            side: entry_side,
+        });
+    }
+
+    // The ray entered through a corner, so check it against the rounded corner
+    let corner = bounding_box.closest_point(contact);
+    let to_start = start - corner;
+    let a = motion.length_squared();
+    let b = to_start.dot(motion);
+    let c = to_start.length_squared() - radius * radius;
+    let discriminant = b * b - a * c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let time = (-b - discriminant.sqrt()) / a;
+    if !(0.0..=1.0).contains(&time) {
+        return None;
+    }
+
+    Some(SweepHit {
+        time,
+        side: Collision::from_offset(start + motion * time - corner),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb2d {
+        Aabb2d::new(Vec2::ZERO, Vec2::splat(1.0))
+    }
+
+    fn ball_at(x: f32, y: f32) -> BoundingCircle {
+        BoundingCircle::new(Vec2::new(x, y), 0.5)
+    }
+
+    #[test]
+    fn ball_collision_edges() {
+        assert_eq!(
+            ball_collision(ball_at(-1.4, 0.0), unit_box()),
+            Some(Collision::Left)
+        );
+        assert_eq!(
+            ball_collision(ball_at(1.4, 0.0), unit_box()),
+            Some(Collision::Right)
+        );
+        assert_eq!(
+            ball_collision(ball_at(0.0, 1.4), unit_box()),
+            Some(Collision::Top)
+        );
+        assert_eq!(
+            ball_collision(ball_at(0.0, -1.4), unit_box()),
+            Some(Collision::Bottom)
+        );
+    }
+
+    #[test]
+    fn ball_collision_grazing_edge() {
+        // Exactly touching counts as a hit, anything further away doesn't
+        assert_eq!(
+            ball_collision(ball_at(0.9, 1.5), unit_box()),
+            Some(Collision::Top)
+        );
+        assert_eq!(ball_collision(ball_at(0.9, 1.51), unit_box()), None);
+        assert_eq!(ball_collision(ball_at(1.51, -0.9), unit_box()), None);
+    }
+
+    #[test]
+    fn ball_collision_corners() {
+        // Near a corner, the side is picked by whichever axis the ball is further out on
+        assert_eq!(
+            ball_collision(ball_at(-1.3, 1.2), unit_box()),
+            Some(Collision::Left)
+        );
+        assert_eq!(
+            ball_collision(ball_at(-1.2, 1.3), unit_box()),
+            Some(Collision::Top)
+        );
+        assert_eq!(
+            ball_collision(ball_at(1.3, -1.2), unit_box()),
+            Some(Collision::Right)
+        );
+        assert_eq!(
+            ball_collision(ball_at(1.2, -1.3), unit_box()),
+            Some(Collision::Bottom)
+        );
+        // A perfectly diagonal hit counts as vertical
+        assert_eq!(
+            ball_collision(ball_at(1.3, 1.3), unit_box()),
+            Some(Collision::Top)
+        );
+    }
+
+    #[test]
+    fn ball_collision_misses_corner() {
+        // Within the radius on both axes, but not diagonally
+        assert_eq!(ball_collision(ball_at(1.4, 1.4), unit_box()), None);
+        assert_eq!(ball_collision(ball_at(-1.4, -1.4), unit_box()), None);
+    }
+
+    #[test]
+    fn sweep_finds_hit_partway_through_motion() {
+        // Starts 2.5 to the left of the box's left face, touching it after moving 2.0
+        let hit = sweep_ball(ball_at(-3.5, 0.0), Vec2::new(4.0, 0.0), unit_box()).unwrap();
+        assert_eq!(hit.side, Collision::Left);
+        assert!((hit.time - 0.5).abs() < 1e-5);
+
+        let hit = sweep_ball(ball_at(0.0, 3.5), Vec2::new(0.0, -8.0), unit_box()).unwrap();
+        assert_eq!(hit.side, Collision::Top);
+        assert!((hit.time - 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sweep_does_not_tunnel() {
+        // The motion ends well past the box, which a test at the end position alone would miss
+        let hit = sweep_ball(ball_at(-5.0, 0.2), Vec2::new(20.0, 0.0), unit_box()).unwrap();
+        assert_eq!(hit.side, Collision::Left);
+        assert!((hit.time - 0.175).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sweep_misses() {
+        // Falls short
+        assert_eq!(
+            sweep_ball(ball_at(-5.0, 0.0), Vec2::new(3.0, 0.0), unit_box()),
+            None
+        );
+        // Passes above
+        assert_eq!(
+            sweep_ball(ball_at(-5.0, 1.6), Vec2::new(10.0, 0.0), unit_box()),
+            None
+        );
+        // Moving away
+        assert_eq!(
+            sweep_ball(ball_at(-5.0, 0.0), Vec2::new(-3.0, 0.0), unit_box()),
+            None
+        );
+    }
+
+    #[test]
+    fn sweep_rounded_corner() {
+        // Passes through the square corner of the grown box, but misses the rounded corner
+        assert_eq!(
+            sweep_ball(ball_at(-3.0, 1.45), Vec2::new(1.6, 0.0), unit_box()),
+            None
+        );
+
+        // Hits the corner diagonally
+        let hit = sweep_ball(ball_at(-3.0, 3.0), Vec2::new(4.0, -4.0), unit_box()).unwrap();
+        let expected_time = (2.0 - 0.5 / 2f32.sqrt()) / 4.0;
+        assert!((hit.time - expected_time).abs() < 1e-5);
+        assert_eq!(hit.side, Collision::Top);
+    }
+
+    fn rotated_box(degrees: f32) -> Volume {
+        Volume::Box {
+            center: Vec2::ZERO,
+            half_size: Vec2::new(2.0, 0.5),
+            rotation: Rot2::degrees(degrees),
+        }
+    }
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-5),
+            "{actual} should be {expected}"
+        );
+    }
+
+    #[test]
+    fn unrotated_volumes_match_sweep_ball() {
+        let transform = Transform::from_scale(Vec3::new(2.0, 2.0, 1.0));
+        let volume = Volume::new(&transform, ColliderShape::Box);
+
+        let hit = volume
+            .sweep(ball_at(-3.5, 0.0), Vec2::new(4.0, 0.0))
+            .unwrap();
+        let expected = sweep_ball(ball_at(-3.5, 0.0), Vec2::new(4.0, 0.0), unit_box()).unwrap();
+        assert_eq!(hit.time, expected.time);
+        assert_eq!(hit.normal, expected.side.normal());
+    }
+
+    #[test]
+    fn sweep_rotated_box() {
+        // A bar turned a quarter turn is tall and thin
+        let bar = rotated_box(90.0);
+        assert_near(bar.aabb().half_size(), Vec2::new(0.5, 2.0));
+
+        // Its unrotated box would be missed by this ball, which hits what was its top face
+        let hit = bar.sweep(ball_at(-3.0, 1.5), Vec2::new(4.0, 0.0)).unwrap();
+        assert!((hit.time - 0.5).abs() < 1e-5);
+        assert_near(hit.normal, Vec2::NEG_X);
+
+        // Passes over its end
+        assert_eq!(bar.sweep(ball_at(-3.0, 2.6), Vec2::new(6.0, 0.0)), None);
+    }
+
+    #[test]
+    fn sweep_diagonal_box_reflects_along_its_face() {
+        // A bar at 45 degrees sends a falling ball sideways
+        let bar = rotated_box(45.0);
+        let hit = bar.sweep(ball_at(-0.5, 3.0), Vec2::new(0.0, -4.0)).unwrap();
+        let normal = Vec2::new(-1.0, 1.0).normalize();
+        assert_near(hit.normal, normal);
+
+        // The face is 0.5 from the center, and the ball's radius is 0.5
+        let contact = Vec2::new(-0.5, 3.0 - 4.0 * hit.time);
+        assert!((contact.dot(normal) - 1.0).abs() < 1e-5);
+        assert_near(Vec2::NEG_Y.reflect(hit.normal), Vec2::NEG_X);
+    }
+
+    #[test]
+    fn sweep_circle() {
+        let disc = Volume::new(
+            &Transform::from_scale(Vec3::new(2.0, 2.0, 1.0)),
+            ColliderShape::Circle,
+        );
+
+        let hit = disc.sweep(ball_at(-3.5, 0.0), Vec2::new(4.0, 0.0)).unwrap();
+        assert!((hit.time - 0.5).abs() < 1e-5);
+        assert_near(hit.normal, Vec2::NEG_X);
+
+        // Off-center hits bounce off at an angle, and near misses don't hit at all
+        let hit = disc
+            .sweep(ball_at(-3.0, 0.75), Vec2::new(6.0, 0.0))
+            .unwrap();
+        assert!(hit.normal.x < 0.0 && hit.normal.y > 0.0);
+        assert_eq!(disc.sweep(ball_at(-3.0, 1.51), Vec2::new(6.0, 0.0)), None);
+    }
+
+    #[test]
+    fn penetration_pushes_out_of_rotated_boxes() {
+        let bar = rotated_box(90.0);
+        assert_eq!(bar.penetration(ball_at(1.0, 0.0)), None);
+
+        // Overlapping the side
+        let (normal, depth) = bar.penetration(ball_at(0.75, 1.0)).unwrap();
+        assert_near(normal, Vec2::X);
+        assert!((depth - 0.25).abs() < 1e-5);
+
+        // Inside, nearest the top
+        let (normal, depth) = bar.penetration(ball_at(0.0, 1.75)).unwrap();
+        assert_near(normal, Vec2::Y);
+        assert!((depth - 0.75).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sweep_starting_in_contact() {
+        // Touching the top and moving down is an immediate hit
+        let hit = sweep_ball(ball_at(0.0, 1.5), Vec2::new(0.0, -1.0), unit_box()).unwrap();
+        assert_eq!(hit.time, 0.0);
+        assert_eq!(hit.side, Collision::Top);
+
+        // Touching the top and moving up is free to leave
+        assert_eq!(
+            sweep_ball(ball_at(0.0, 1.5), Vec2::new(0.0, 1.0), unit_box()),
+            None
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! How hard the game is: how fast the ball starts each life, and how quickly it speeds up.
+//!
+//! The ball speeds up a little with every brick it hits and every second it stays in play,
+//! and once more the first time it reaches the top wall, up to a cap.
+//! It goes back to its starting speed when a life is lost.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Ball, BrickHit, StuckToPaddle, Velocity, arena::ArenaConfig};
+
+/// The difficulty chosen by the player. Levels can override it in their level file.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    /// The next difficulty up, wrapping around to the easiest
+    pub fn next(self) -> Difficulty {
+        let index = Difficulty::ALL.iter().position(|&d| d == self).unwrap();
+        Difficulty::ALL[(index + 1) % Difficulty::ALL.len()]
+    }
+
+    /// The next difficulty down, wrapping around to the hardest
+    pub fn previous(self) -> Difficulty {
+        let index = Difficulty::ALL.iter().position(|&d| d == self).unwrap();
+        Difficulty::ALL[(index + Difficulty::ALL.len() - 1) % Difficulty::ALL.len()]
+    }
+
+    pub fn speed_ramp(self) -> SpeedRamp {
+        match self {
+            Difficulty::Easy => SpeedRamp {
+                start: 0.85,
+                per_hit: 0.004,
+                per_second: 0.002,
+                top_wall: 0.05,
+                max: 1.25,
+            },
+            Difficulty::Normal => SpeedRamp {
+                start: 1.0,
+                per_hit: 0.008,
+                per_second: 0.003,
+                top_wall: 0.1,
+                max: 1.5,
+            },
+            Difficulty::Hard => SpeedRamp {
+                start: 1.15,
+                per_hit: 0.012,
+                per_second: 0.005,
+                top_wall: 0.15,
+                max: 1.8,
+            },
+        }
+    }
+}
+
+/// How the ball's speed changes over a life, as multiples of the level's ball speed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedRamp {
+    /// Each life starts at this speed
+    pub start: f32,
+    /// Added for every brick hit
+    pub per_hit: f32,
+    /// Added for every second the ball is in play
+    pub per_second: f32,
+    /// Added the first time the ball reaches the top wall each life
+    pub top_wall: f32,
+    /// The ball never goes faster than this
+    pub max: f32,
+}
+
+/// How many times faster than the level's ball speed the ball is currently going
+#[derive(Resource, Debug)]
+pub struct BallSpeedMultiplier {
+    ramp: SpeedRamp,
+    multiplier: f32,
+    reached_top_wall: bool,
+}
+
+impl Default for BallSpeedMultiplier {
+    fn default() -> Self {
+        BallSpeedMultiplier::new(Difficulty::default().speed_ramp())
+    }
+}
+
+impl BallSpeedMultiplier {
+    pub fn new(ramp: SpeedRamp) -> BallSpeedMultiplier {
+        BallSpeedMultiplier {
+            ramp,
+            multiplier: ramp.start,
+            reached_top_wall: false,
+        }
+    }
+
+    pub fn get(&self) -> f32 {
+        self.multiplier
+    }
+
+    /// Back to the starting speed, for a new life
+    pub fn reset(&mut self) {
+        *self = BallSpeedMultiplier::new(self.ramp);
+    }
+
+    fn increase(&mut self, amount: f32) {
+        self.multiplier = (self.multiplier + amount).min(self.ramp.max);
+    }
+}
+
+// Speed up for each brick hit, for time passing while the ball is in play,
+// and for the first time each life that a ball reaches the top wall
+#[allow(clippy::type_complexity)]
+pub(crate) fn ramp_up_ball_speed(
+    mut speed_multiplier: ResMut<BallSpeedMultiplier>,
+    mut brick_hits: EventReader<BrickHit>,
+    ball_query: Query<(&Transform, &Velocity), (With<Ball>, Without<StuckToPaddle>)>,
+    arena: Res<ArenaConfig>,
+    time: Res<Time>,
+) {
+    let ramp = speed_multiplier.ramp;
+
+    let hits = brick_hits.read().count();
+    speed_multiplier.increase(hits as f32 * ramp.per_hit);
+
+    if ball_query.is_empty() {
+        return;
+    }
+    speed_multiplier.increase(ramp.per_second * time.delta_secs());
+
+    // A ball moving down that is less than one timestep's travel below the point where it
+    // touches the top wall must have bounced off it during this timestep
+    let contact_y = arena.ball_bounds().max.y;
+    if !speed_multiplier.reached_top_wall
+        && ball_query.iter().any(|(transform, velocity)| {
+            velocity.y < 0.0
+                && transform.translation.y >= contact_y + velocity.y * time.delta_secs() - 0.01
+        })
+    {
+        speed_multiplier.reached_top_wall = true;
+        speed_multiplier.increase(ramp.top_wall);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harder_difficulties_are_faster() {
+        for pair in Difficulty::ALL.windows(2) {
+            let (easier, harder) = (pair[0].speed_ramp(), pair[1].speed_ramp());
+            assert!(easier.start < harder.start);
+            assert!(easier.max < harder.max);
+        }
+    }
+
+    #[test]
+    fn multiplier_is_capped_and_resets() {
+        let ramp = Difficulty::Normal.speed_ramp();
+        let mut multiplier = BallSpeedMultiplier::new(ramp);
+        assert_eq!(multiplier.get(), ramp.start);
+
+        multiplier.increase(ramp.per_hit);
+        assert_eq!(multiplier.get(), ramp.start + ramp.per_hit);
+
+        multiplier.increase(100.0);
+        assert_eq!(multiplier.get(), ramp.max);
+
+        multiplier.reset();
+        assert_eq!(multiplier.get(), ramp.start);
+    }
+
+    #[test]
+    fn next_cycles_through_every_difficulty() {
+        assert_eq!(Difficulty::Easy.next(), Difficulty::Normal);
+        assert_eq!(Difficulty::Normal.next(), Difficulty::Hard);
+        assert_eq!(Difficulty::Hard.next(), Difficulty::Easy);
+        assert_eq!(Difficulty::Easy.previous(), Difficulty::Hard);
+        assert_eq!(Difficulty::Hard.previous(), Difficulty::Normal);
+    }
+}
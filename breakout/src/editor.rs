@@ -0,0 +1,543 @@
+//! The level editor: paint bricks onto the grid that level layouts are placed on,
+//! play the level straight away, and save it as a level file.
+//!
+//! Levels are saved to `custom.level.ron` in the user's data directory,
+//! or to the file given with `--level`, which the game then plays instead of the bundled levels.
+
+use std::path::PathBuf;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    BALL_SPEED, BRICK_SIZE, Brick, GAP_BETWEEN_BRICKS, Lives, STARTING_LIVES, Score,
+    arena::ArenaConfig,
+    level::{BrickGrid, BrickKind, CustomLevel, Level, LevelError, LevelFilePath},
+    persist,
+    state::{GameState, InGame, prompt},
+    versus::GameMode,
+};
+
+const EDITOR_LEVEL_FILE: &str = "custom.level.ron";
+const EDITOR_LEVEL_NAME: &str = "Custom";
+
+const GRID_CELL_COLOR: Color = Color::srgba(0.5, 0.5, 1.0, 0.15);
+const BRUSH_KEYS: [KeyCode; 4] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+];
+
+/// Adds the level editor, opened from the title screen
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, open_editor.run_if(in_state(GameState::Title)))
+            .add_systems(
+                OnEnter(GameState::Editor),
+                (
+                    end_test_play,
+                    start_editing,
+                    spawn_editor,
+                    spawn_editor_bricks,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    choose_brush,
+                    paint,
+                    save_level,
+                    open_level,
+                    test_play,
+                    close_editor,
+                    (spawn_editor_bricks, update_editor_help).run_if(resource_changed::<Editor>),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Editor)),
+            )
+            .add_systems(
+                OnEnter(InGame),
+                spawn_test_play_banner.run_if(is_test_playing),
+            )
+            .add_systems(
+                Update,
+                stop_test_play.run_if(in_state(InGame).and(is_test_playing)),
+            )
+            .add_systems(OnEnter(GameState::LevelCleared), return_to_editor)
+            .add_systems(OnEnter(GameState::GameOver), return_to_editor);
+    }
+}
+
+/// The level being edited. It is kept when leaving the editor, so work isn't lost.
+#[derive(Resource)]
+struct Editor {
+    /// Always has a cell for every cell of `grid`
+    level: Level,
+    /// The largest grid that fits in the arena and that the level lines up with,
+    /// or `None` if not even one brick fits
+    grid: Option<BrickGrid>,
+    brush: BrickKind,
+    path: Option<PathBuf>,
+    /// What happened last, such as saving or why the level can't be played
+    status: String,
+}
+
+impl Editor {
+    /// Show `level` on the editor's grid. Layouts narrower than the grid are centered,
+    /// as they are when played. An odd width on an even grid, or the other way around,
+    /// would be half a cell off, so the grid is a column narrower for those.
+    /// Anything that doesn't fit is dropped. Fails, leaving the editor as it was,
+    /// if there is no room for a grid.
+    fn set_level(&mut self, level: Level, arena: &ArenaConfig) -> Result<(), LevelError> {
+        let largest = BrickGrid::largest(level.gap, arena)?;
+        let width = level.cells.iter().map(Vec::len).max().unwrap_or(0);
+        let columns = largest.columns - largest.columns.saturating_sub(width) % 2;
+        let grid = BrickGrid::new(columns, largest.rows, level.gap, arena)?;
+        let left = grid.columns.saturating_sub(width) / 2;
+
+        let mut cells = vec![vec![None; grid.columns]; grid.rows];
+        for (row, kinds) in cells.iter_mut().zip(&level.cells) {
+            for (cell, kind) in row[left..].iter_mut().zip(kinds) {
+                *cell = *kind;
+            }
+        }
+
+        self.level = Level { cells, ..level };
+        self.grid = Some(grid);
+        Ok(())
+    }
+
+    /// The level as it is saved and played: the full width of the grid,
+    /// so it is placed where it was painted, without the empty rows at the bottom
+    fn to_level(&self) -> Level {
+        let rows = self
+            .level
+            .cells
+            .iter()
+            .rposition(|row| row.iter().any(Option::is_some))
+            .map_or(0, |last| last + 1);
+
+        Level {
+            cells: self.level.cells[..rows].to_vec(),
+            ..self.level.clone()
+        }
+    }
+}
+
+fn empty_level() -> Level {
+    Level {
+        name: EDITOR_LEVEL_NAME.to_string(),
+        ball_speed: BALL_SPEED,
+        gap: GAP_BETWEEN_BRICKS,
+        difficulty: None,
+        stars: Vec::new(),
+        cells: Vec::new(),
+        obstacles: Vec::new(),
+    }
+}
+
+/// Present while the edited level is being played.
+/// Holds any custom level from the command line, to put back afterwards.
+#[derive(Resource)]
+pub(crate) struct TestPlay {
+    previous: Option<CustomLevel>,
+}
+
+/// A run condition for skipping the usual screens between levels during a test play
+pub(crate) fn is_test_playing(test_play: Option<Res<TestPlay>>) -> bool {
+    test_play.is_some()
+}
+
+#[derive(Component)]
+struct EditorBrick;
+
+#[derive(Component)]
+struct EditorHelp;
+
+fn open_editor(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyE) {
+        next_state.set(GameState::Editor);
+    }
+}
+
+// The first time the editor is opened, open the level file, or start a new level
+fn start_editing(
+    mut commands: Commands,
+    editor: Option<Res<Editor>>,
+    level_file_path: Option<Res<LevelFilePath>>,
+    arena: Res<ArenaConfig>,
+) {
+    if editor.is_some() {
+        return;
+    }
+
+    let path = match level_file_path {
+        Some(path) => Some(path.0.clone()),
+        None => persist::data_path(EDITOR_LEVEL_FILE),
+    };
+    let mut editor = Editor {
+        level: empty_level(),
+        grid: None,
+        brush: BrickKind::Normal,
+        path,
+        status: String::new(),
+    };
+    if let Err(error) = editor.set_level(empty_level(), &arena) {
+        editor.status = format!("There is no room to paint bricks: {error}");
+    } else if editor.path.as_ref().is_some_and(|path| path.exists()) {
+        load(&mut editor, &arena);
+    }
+
+    commands.insert_resource(editor);
+}
+
+fn spawn_editor(mut commands: Commands, editor: Res<Editor>) {
+    if let Some(grid) = editor.grid {
+        for row in 0..grid.rows {
+            for column in 0..grid.columns {
+                commands.spawn((
+                    Sprite::from_color(GRID_CELL_COLOR, BRICK_SIZE),
+                    Transform::from_translation(grid.position(column, row).extend(-1.0)),
+                    StateScoped(GameState::Editor),
+                ));
+            }
+        }
+    }
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        StateScoped(GameState::Editor),
+        children![(prompt(help_text(&editor)), EditorHelp)],
+    ));
+}
+
+fn help_text(editor: &Editor) -> String {
+    let brushes: Vec<String> = BrickKind::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, kind)| {
+            let marker = if *kind == editor.brush { "*" } else { "" };
+            format!("{}: {marker}{}{marker}", index + 1, kind.name())
+        })
+        .collect();
+
+    format!(
+        "{}\nLeft click to paint, right click to erase\nEnter to play, S to save, O to open, Esc to leave\n{}",
+        brushes.join("  "),
+        editor.status
+    )
+}
+
+fn update_editor_help(editor: Res<Editor>, mut help: Single<&mut Text, With<EditorHelp>>) {
+    help.0 = help_text(&editor);
+}
+
+// Draw the painted cells like the bricks they will become
+fn spawn_editor_bricks(
+    mut commands: Commands,
+    editor: Res<Editor>,
+    bricks: Query<Entity, With<EditorBrick>>,
+) {
+    for brick in &bricks {
+        commands.entity(brick).despawn();
+    }
+
+    let Some(grid) = editor.grid else {
+        return;
+    };
+    for (row, kinds) in editor.level.cells.iter().enumerate() {
+        for (column, kind) in kinds.iter().enumerate() {
+            let Some(kind) = kind else {
+                continue;
+            };
+
+            commands.spawn((
+                Sprite::from_color(Brick::new(*kind).color(), BRICK_SIZE),
+                Transform::from_translation(grid.position(column, row).extend(0.0)),
+                EditorBrick,
+                StateScoped(GameState::Editor),
+            ));
+        }
+    }
+}
+
+fn choose_brush(keyboard_input: Res<ButtonInput<KeyCode>>, mut editor: ResMut<Editor>) {
+    for (key, kind) in BRUSH_KEYS.into_iter().zip(BrickKind::ALL) {
+        if keyboard_input.just_pressed(key) {
+            editor.brush = kind;
+        }
+    }
+}
+
+// Paint the cell under the cursor while the mouse is held, so strokes fill every cell they cross
+fn paint(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    mut editor: ResMut<Editor>,
+) {
+    let kind = if mouse_buttons.pressed(MouseButton::Left) {
+        Some(editor.brush)
+    } else if mouse_buttons.pressed(MouseButton::Right) {
+        None
+    } else {
+        return;
+    };
+
+    let (camera, camera_transform) = *camera;
+    let Some(point) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+    let Some((column, row)) = editor.grid.and_then(|grid| grid.cell_at(point)) else {
+        return;
+    };
+
+    // Only touch the editor when something changes, so the bricks aren't redrawn every frame
+    if editor.level.cells[row][column] != kind {
+        editor.level.cells[row][column] = kind;
+    }
+}
+
+fn load(editor: &mut Editor, arena: &ArenaConfig) {
+    let Some(path) = editor.path.clone() else {
+        editor.status = "There is nowhere to open levels from".to_string();
+        return;
+    };
+
+    match Level::load(&path, arena).and_then(|level| editor.set_level(level, arena)) {
+        Ok(()) => editor.status = format!("Opened {}", path.display()),
+        Err(error) => editor.status = format!("Could not open {}: {error}", path.display()),
+    }
+}
+
+fn open_level(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    arena: Res<ArenaConfig>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyO) {
+        load(&mut editor, &arena);
+    }
+}
+
+fn save_level(keyboard_input: Res<ButtonInput<KeyCode>>, mut editor: ResMut<Editor>) {
+    if !keyboard_input.just_pressed(KeyCode::KeyS) {
+        return;
+    }
+
+    let Some(path) = editor.path.clone() else {
+        editor.status = "There is nowhere to save levels".to_string();
+        return;
+    };
+
+    editor.status = match editor.to_level().save(&path) {
+        Ok(()) => format!("Saved to {}", path.display()),
+        Err(error) => format!("Could not save to {}: {error}", path.display()),
+    };
+}
+
+// Play the level as it is now, in place of any other levels
+#[allow(clippy::too_many_arguments)]
+fn test_play(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    custom_level: Option<Res<CustomLevel>>,
+    mut levels: ResMut<Assets<Level>>,
+    arena: Res<ArenaConfig>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Enter) {
+        return;
+    }
+
+    let level = editor.to_level();
+    if let Err(error) = level.bricks(&arena) {
+        editor.status = format!("This level can't be played: {error}");
+        return;
+    }
+
+    commands.insert_resource(TestPlay {
+        previous: custom_level.map(|custom_level| custom_level.clone()),
+    });
+    commands.insert_resource(CustomLevel(levels.add(level)));
+    commands.insert_resource(GameMode::Solo);
+    commands.insert_resource(Score(0));
+    commands.insert_resource(Lives(STARTING_LIVES));
+    next_state.set(GameState::Playing);
+}
+
+fn close_editor(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Title);
+    }
+}
+
+fn spawn_test_play_banner(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        StateScoped(InGame),
+        children![prompt("Testing - press E to go back to the editor")],
+    ));
+}
+
+fn stop_test_play(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyE) {
+        next_state.set(GameState::Editor);
+    }
+}
+
+// Clearing or losing the edited level goes back to editing it
+fn return_to_editor(
+    test_play: Option<Res<TestPlay>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if test_play.is_some() {
+        next_state.set(GameState::Editor);
+    }
+}
+
+fn end_test_play(mut commands: Commands, test_play: Option<Res<TestPlay>>) {
+    let Some(test_play) = test_play else {
+        return;
+    };
+
+    match &test_play.previous {
+        Some(previous) => commands.insert_resource(previous.clone()),
+        None => commands.remove_resource::<CustomLevel>(),
+    }
+    commands.remove_resource::<TestPlay>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor() -> Editor {
+        Editor {
+            level: empty_level(),
+            grid: None,
+            brush: BrickKind::Normal,
+            path: None,
+            status: String::new(),
+        }
+    }
+
+    #[test]
+    fn levels_are_centered_on_the_grid_and_saved_full_width() {
+        let arena = ArenaConfig::default();
+        let mut editor = editor();
+        let level = Level {
+            cells: vec![
+                vec![
+                    Some(BrickKind::Normal),
+                    None,
+                    None,
+                    Some(BrickKind::Explosive),
+                ],
+                vec![None, Some(BrickKind::MultiHit)],
+            ],
+            ..empty_level()
+        };
+
+        editor.set_level(level.clone(), &arena).unwrap();
+        let grid = editor.grid.unwrap();
+        assert_eq!(editor.level.cells.len(), grid.rows);
+        assert!(
+            editor
+                .level
+                .cells
+                .iter()
+                .all(|row| row.len() == grid.columns)
+        );
+
+        // The bricks are where they would be if the original level was played
+        let saved = editor.to_level();
+        assert_eq!(saved.cells.len(), 2);
+        assert_eq!(saved.cells[0].len(), grid.columns);
+        assert_eq!(saved.bricks(&arena).unwrap(), level.bricks(&arena).unwrap());
+    }
+
+    #[test]
+    fn reopened_levels_are_saved_where_they_were() {
+        let arena = ArenaConfig::default();
+        // One of these widths has the same parity as the largest grid, and one doesn't
+        for width in [3, 4] {
+            let mut row = vec![None; width];
+            row[0] = Some(BrickKind::Normal);
+            row[width - 1] = Some(BrickKind::MultiHit);
+            let level = Level {
+                cells: vec![row],
+                ..empty_level()
+            };
+
+            let mut editor = editor();
+            editor.set_level(level.clone(), &arena).unwrap();
+            let saved = editor.to_level();
+            assert_eq!(
+                saved.bricks(&arena).unwrap(),
+                level.bricks(&arena).unwrap(),
+                "{width}"
+            );
+
+            // Opening the saved level again doesn't move anything either
+            editor.set_level(saved.clone(), &arena).unwrap();
+            assert_eq!(editor.to_level(), saved, "{width}");
+        }
+    }
+
+    #[test]
+    fn levels_without_room_for_a_grid_are_not_opened() {
+        let tiny = ArenaConfig {
+            left: -100.0,
+            right: 100.0,
+            bottom: -100.0,
+            top: 100.0,
+            ..default()
+        };
+        let level = Level {
+            cells: vec![vec![Some(BrickKind::Normal)]],
+            ..empty_level()
+        };
+
+        let mut editor = editor();
+        assert!(editor.set_level(level.clone(), &tiny).is_err());
+        let negative_gap = Level {
+            gap: -30.0,
+            ..level
+        };
+        assert!(matches!(
+            editor.set_level(negative_gap, &ArenaConfig::default()),
+            Err(LevelError::Gap(_))
+        ));
+        assert_eq!(editor.grid, None);
+        assert!(editor.level.cells.is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+//! Purely visual feedback: shards flying off destroyed bricks, the camera shaking,
+//! and the ball squashing as it bounces.
+//!
+//! None of it touches the simulation, and all of it can be turned off with
+//! the reduce-motion [`Accessibility`] setting, which is saved with the other settings.
+
+use std::{f32::consts::TAU, path::PathBuf};
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BallLost, Brick, BrickDestroyed, CollisionEvent, check_for_collisions, damage_bricks,
+    level::BrickKind,
+    lose_life, persist,
+    state::{GameState, InGame},
+};
+
+const ACCESSIBILITY_CONFIG_FILE: &str = "accessibility.ron";
+
+const SHARDS_PER_BRICK: usize = 8;
+const SHARD_SIZE: Vec2 = Vec2::new(10.0, 6.0);
+const SHARD_MIN_SPEED: f32 = 80.0;
+const SHARD_MAX_SPEED: f32 = 260.0;
+const SHARD_GRAVITY: f32 = -900.0;
+const SHARD_MAX_SPIN: f32 = 12.0;
+const SHARD_LIFETIME: f32 = 0.6;
+
+// How much trauma, from 0.0 to 1.0, each event adds to the camera shake
+const BRICK_TRAUMA: f32 = 0.15;
+const EXPLOSION_TRAUMA: f32 = 0.35;
+const BALL_LOST_TRAUMA: f32 = 0.6;
+// Trauma fades by this much every second
+const TRAUMA_DECAY: f32 = 1.2;
+// The camera's offset and angle at full trauma. The shake grows with the square of the trauma,
+// so small knocks barely move it and big ones are obvious.
+const MAX_SHAKE_OFFSET: f32 = 16.0;
+const MAX_SHAKE_ANGLE: f32 = 0.03;
+
+// At the moment of impact, the ball is this much narrower along the bounce and wider across it.
+// It then wobbles back, briefly stretching the other way.
+const SQUASH_AMOUNT: f32 = 0.35;
+const SQUASH_DURATION: f32 = 0.2;
+
+/// Spawns brick shards, shakes the camera and squashes the ball, unless motion is reduced
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        let config_path = persist::config_path(ACCESSIBILITY_CONFIG_FILE);
+        let accessibility = config_path
+            .as_deref()
+            .and_then(persist::load)
+            .unwrap_or_default();
+
+        app.insert_resource::<Accessibility>(accessibility)
+            .insert_resource(AccessibilityConfigPath(config_path))
+            .add_systems(
+                Update,
+                toggle_reduce_motion.run_if(in_state(GameState::Title)),
+            )
+            .add_systems(
+                Update,
+                save_accessibility.run_if(
+                    resource_changed::<Accessibility>.and(not(resource_added::<Accessibility>)),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    spawn_shards.after(damage_bricks),
+                    add_trauma.after(lose_life),
+                    squash_balls.after(check_for_collisions),
+                )
+                    .run_if(|accessibility: Res<Accessibility>| !accessibility.reduce_motion),
+            )
+            .add_systems(Update, (move_shards, shake_camera, animate_squash));
+    }
+}
+
+/// Settings for players who find parts of the game hard to play
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accessibility {
+    /// Turns off the screen shake, the flying brick shards and the ball squashing
+    pub reduce_motion: bool,
+}
+
+/// Where the accessibility settings are saved, if the platform has a config directory
+#[derive(Resource)]
+struct AccessibilityConfigPath(Option<PathBuf>);
+
+/// How shaken up the camera is, from 0.0 to 1.0
+#[derive(Component, Default)]
+pub struct CameraShake {
+    trauma: f32,
+}
+
+impl CameraShake {
+    fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+}
+
+// A piece of a destroyed brick, falling until its timer finishes
+#[derive(Component)]
+struct Shard {
+    velocity: Vec2,
+    spin: f32,
+    lifetime: Timer,
+}
+
+// Added to a ball's visuals when it bounces, wobbling them along the bounce's normal
+#[derive(Component)]
+struct Squash {
+    normal: Vec2,
+    timer: Timer,
+}
+
+fn toggle_reduce_motion(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut accessibility: ResMut<Accessibility>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+
+    accessibility.reduce_motion = !accessibility.reduce_motion;
+}
+
+// Reduce motion can be changed on the title screen and in the settings
+fn save_accessibility(
+    accessibility: Res<Accessibility>,
+    config_path: Res<AccessibilityConfigPath>,
+) {
+    if let Some(path) = &config_path.0 {
+        persist::save(path, &*accessibility);
+    }
+}
+
+fn spawn_shards(mut commands: Commands, mut destroyed_events: EventReader<BrickDestroyed>) {
+    let mut rng = rand::thread_rng();
+
+    for destroyed in destroyed_events.read() {
+        // The color the brick was when it took its last hit
+        let color = Brick {
+            kind: destroyed.kind,
+            hit_points: 1,
+            score: 0,
+        }
+        .color();
+
+        for _ in 0..SHARDS_PER_BRICK {
+            let direction = Vec2::from_angle(rng.gen_range(0.0..TAU));
+            let speed = rng.gen_range(SHARD_MIN_SPEED..SHARD_MAX_SPEED);
+            commands.spawn((
+                Sprite::from_color(color, SHARD_SIZE),
+                Transform::from_translation(destroyed.position.extend(0.5)),
+                Shard {
+                    velocity: direction * speed,
+                    spin: rng.gen_range(-SHARD_MAX_SPIN..SHARD_MAX_SPIN),
+                    lifetime: Timer::from_seconds(SHARD_LIFETIME, TimerMode::Once),
+                },
+                StateScoped(InGame),
+            ));
+        }
+    }
+}
+
+// Shards keep flying while the game is paused, as they don't affect anything
+fn move_shards(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Shard, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut shard, mut transform, mut sprite) in &mut query {
+        shard.lifetime.tick(time.delta());
+        if shard.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        shard.velocity.y += SHARD_GRAVITY * dt;
+        transform.translation += (shard.velocity * dt).extend(0.0);
+        transform.rotate_z(shard.spin * dt);
+        sprite.color.set_alpha(shard.lifetime.fraction_remaining());
+    }
+}
+
+fn add_trauma(
+    mut destroyed_events: EventReader<BrickDestroyed>,
+    mut ball_lost_events: EventReader<BallLost>,
+    mut camera_shake: Single<&mut CameraShake>,
+) {
+    for destroyed in destroyed_events.read() {
+        camera_shake.add_trauma(match destroyed.kind {
+            BrickKind::Explosive => EXPLOSION_TRAUMA,
+            _ => BRICK_TRAUMA,
+        });
+    }
+
+    if ball_lost_events.read().count() > 0 {
+        camera_shake.add_trauma(BALL_LOST_TRAUMA);
+    }
+}
+
+fn shake_camera(
+    camera: Single<(&mut CameraShake, &mut Transform)>,
+    accessibility: Res<Accessibility>,
+    time: Res<Time>,
+) {
+    let (mut camera_shake, mut transform) = camera.into_inner();
+    if accessibility.reduce_motion {
+        camera_shake.trauma = 0.0;
+    }
+
+    camera_shake.trauma = (camera_shake.trauma - TRAUMA_DECAY * time.delta_secs()).max(0.0);
+    let shake = camera_shake.trauma * camera_shake.trauma;
+
+    let mut rng = rand::thread_rng();
+    let offset = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+    transform.translation = (offset * MAX_SHAKE_OFFSET * shake).extend(transform.translation.z);
+    transform.rotation = Quat::from_rotation_z(rng.gen_range(-1.0..=1.0) * MAX_SHAKE_ANGLE * shake);
+}
+
+// Balls are drawn by a child entity, so squashing them doesn't change their size in the simulation
+fn squash_balls(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    children_query: Query<&Children>,
+) {
+    for collision in collision_events.read() {
+        let Ok(children) = children_query.get(collision.ball) else {
+            continue;
+        };
+
+        for &child in children {
+            commands.entity(child).try_insert(Squash {
+                normal: collision.normal,
+                timer: Timer::from_seconds(SQUASH_DURATION, TimerMode::Once),
+            });
+        }
+    }
+}
+
+fn animate_squash(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Squash, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut squash, mut transform) in &mut query {
+        squash.timer.tick(time.delta());
+        if squash.timer.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<Squash>();
+            continue;
+        }
+
+        // A single damped wobble, from squashed through stretched and back to round
+        let t = squash.timer.fraction();
+        let amount = SQUASH_AMOUNT * (1.0 - t) * (t * TAU).cos();
+        transform.rotation = Quat::from_rotation_z(squash.normal.to_angle());
+        transform.scale = Vec3::new(1.0 - amount, 1.0 + amount, 1.0);
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Lives, Score,
+    editor::is_test_playing,
+    input::MenuInput,
+    persist,
+    state::{GameState, prompt, screen},
+    versus::is_versus,
+};
+
+const HIGH_SCORES_FILE: &str = "high_scores.ron";
+const MAX_HIGH_SCORES: usize = 10;
+const MAX_NAME_LENGTH: usize = 12;
+const DEFAULT_NAME: &str = "Player";
+
+/// Loads the high-score table, and adds the game-over screen where new high scores are entered
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        let path = persist::data_path(HIGH_SCORES_FILE);
+        let high_scores = path
+            .as_deref()
+            .and_then(persist::load::<HighScores>)
+            .map(HighScores::tidy)
+            .unwrap_or_default();
+
+        app.insert_resource(high_scores)
+            .insert_resource(HighScoresPath(path))
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                spawn_game_over_screen.run_if(not(is_versus).and(not(is_test_playing))),
+            )
+            .add_systems(
+                Update,
+                // Returning first means the key that finishes the name can't also leave the screen
+                (return_to_title, enter_name)
+                    .chain()
+                    .run_if(in_state(GameState::GameOver)),
+            )
+            .add_systems(OnExit(GameState::GameOver), |mut commands: Commands| {
+                commands.remove_resource::<NameEntry>();
+            });
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: usize,
+    /// The day the score was set, as `YYYY-MM-DD`
+    pub date: String,
+}
+
+/// The best scores, highest first.
+///
+/// Saved as `high_scores.ron` in the user's data directory.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct HighScores(Vec<HighScore>);
+
+impl HighScores {
+    /// Where `score` would be placed in the table, or `None` if it isn't good enough.
+    ///
+    /// A new score goes below any existing entries with the same score.
+    pub fn rank(&self, score: usize) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+
+        let rank = self.0.partition_point(|entry| entry.score >= score);
+        (rank < MAX_HIGH_SCORES).then_some(rank)
+    }
+
+    /// Add `entry` to the table, returning where it was placed.
+    /// The lowest score drops off the table if it is full.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let rank = self.rank(entry.score)?;
+        self.0.insert(rank, entry);
+        self.0.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    pub fn entries(&self) -> &[HighScore] {
+        &self.0
+    }
+
+    // The file may have been edited by hand, so make sure it is in order and not too long
+    fn tidy(mut self) -> HighScores {
+        self.0.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        self.0.truncate(MAX_HIGH_SCORES);
+        self
+    }
+}
+
+/// Where the high scores are saved, if the platform has a data directory
+#[derive(Resource)]
+struct HighScoresPath(Option<PathBuf>);
+
+/// The name being typed for a new high score on the game-over screen
+#[derive(Resource)]
+struct NameEntry {
+    name: String,
+    score: usize,
+}
+
+impl NameEntry {
+    fn high_score(&self) -> HighScore {
+        let name = match self.name.trim() {
+            "" => DEFAULT_NAME,
+            name => name,
+        };
+
+        HighScore {
+            name: name.to_string(),
+            score: self.score,
+            date: today(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct HighScoreTable;
+
+#[derive(Component)]
+struct GameOverPrompt;
+
+// Today's date in UTC, as `YYYY-MM-DD`
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Converts days since 1970-01-01 to a (year, month, day) date in the Gregorian calendar.
+// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn table_text(high_scores: &HighScores, highlight: Option<usize>) -> String {
+    if high_scores.entries().is_empty() {
+        return "No high scores yet".to_string();
+    }
+
+    high_scores
+        .entries()
+        .iter()
+        .enumerate()
+        .map(|(rank, entry)| {
+            let marker = if Some(rank) == highlight { ">" } else { " " };
+            format!(
+                "{marker} {:>2}. {:<MAX_NAME_LENGTH$}  {:>6}  {}",
+                rank + 1,
+                entry.name,
+                entry.score,
+                entry.date
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The table as it would look with the name typed so far
+fn preview_text(high_scores: &HighScores, name_entry: &NameEntry) -> String {
+    let mut preview = high_scores.clone();
+    let rank = preview.insert(name_entry.high_score());
+    table_text(&preview, rank)
+}
+
+fn name_prompt_text(name_entry: &NameEntry) -> String {
+    format!(
+        "New high score! Type your name and press Enter:\n{}_",
+        name_entry.name
+    )
+}
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    menu_input: MenuInput,
+    score: Res<Score>,
+    lives: Res<Lives>,
+    high_scores: Res<HighScores>,
+) {
+    // The game also ends when the last level is cleared
+    let heading = if **lives > 0 { "You win!" } else { "Game over" };
+
+    let (table, prompt_text) = if high_scores.rank(**score).is_some() {
+        let name_entry = NameEntry {
+            name: String::new(),
+            score: **score,
+        };
+        let texts = (
+            preview_text(&high_scores, &name_entry),
+            name_prompt_text(&name_entry),
+        );
+        commands.insert_resource(name_entry);
+        texts
+    } else {
+        (
+            table_text(&high_scores, None),
+            format!(
+                "Press {} to return to the title screen",
+                menu_input.confirm_name()
+            ),
+        )
+    };
+
+    commands.spawn(screen(
+        GameState::GameOver,
+        heading,
+        (
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(20.0),
+                ..default()
+            },
+            children![
+                prompt(format!("Final score: {}", **score)),
+                (prompt(table), HighScoreTable),
+                (prompt(prompt_text), GameOverPrompt),
+            ],
+        ),
+    ));
+}
+
+// Type a name for the new high score, then save it once Enter is pressed.
+// Players without a keyboard save it with a click or a gamepad's launch button instead,
+// under the default name if nothing was typed.
+#[allow(clippy::too_many_arguments)]
+fn enter_name(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    name_entry: Option<ResMut<NameEntry>>,
+    mut high_scores: ResMut<HighScores>,
+    high_scores_path: Res<HighScoresPath>,
+    menu_input: MenuInput,
+    mut table: Single<&mut Text, (With<HighScoreTable>, Without<GameOverPrompt>)>,
+    mut game_over_prompt: Single<&mut Text, With<GameOverPrompt>>,
+) {
+    // Ignore key presses from before the name could be typed, such as the one that led here
+    let Some(mut name_entry) = name_entry.filter(|name_entry| !name_entry.is_added()) else {
+        keyboard_events.clear();
+        return;
+    };
+
+    let mut finished = menu_input.confirm_without_keys();
+    for event in keyboard_events.read() {
+        if finished {
+            break;
+        }
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Enter => finished = true,
+            Key::Backspace => {
+                name_entry.name.pop();
+            }
+            Key::Space => name_entry.name.push(' '),
+            Key::Character(characters) => name_entry
+                .name
+                .extend(characters.chars().filter(|c| !c.is_control())),
+            _ => {}
+        }
+
+        let name: String = name_entry.name.chars().take(MAX_NAME_LENGTH).collect();
+        name_entry.name = name;
+    }
+
+    if finished {
+        let rank = high_scores.insert(name_entry.high_score());
+        if let Some(path) = &high_scores_path.0 {
+            persist::save(path, &*high_scores);
+        }
+        commands.remove_resource::<NameEntry>();
+
+        table.0 = table_text(&high_scores, rank);
+        game_over_prompt.0 = format!(
+            "Press {} to return to the title screen",
+            menu_input.confirm_name()
+        );
+        return;
+    }
+
+    if name_entry.is_changed() {
+        table.0 = preview_text(&high_scores, &name_entry);
+        game_over_prompt.0 = name_prompt_text(&name_entry);
+    }
+}
+
+fn return_to_title(
+    menu_input: MenuInput,
+    name_entry: Option<Res<NameEntry>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if name_entry.is_none() && menu_input.confirm() {
+        next_state.set(GameState::Title);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputBindings;
+
+    fn entry(name: &str, score: usize) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score,
+            date: "2024-01-01".to_string(),
+        }
+    }
+
+    fn scores(high_scores: &HighScores) -> Vec<usize> {
+        high_scores
+            .entries()
+            .iter()
+            .map(|entry| entry.score)
+            .collect()
+    }
+
+    #[test]
+    fn inserts_in_score_order() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.insert(entry("a", 10)), Some(0));
+        assert_eq!(high_scores.insert(entry("b", 30)), Some(0));
+        assert_eq!(high_scores.insert(entry("c", 20)), Some(1));
+        assert_eq!(scores(&high_scores), vec![30, 20, 10]);
+    }
+
+    #[test]
+    fn ties_go_below_existing_scores() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("first", 10));
+        assert_eq!(high_scores.insert(entry("second", 10)), Some(1));
+        assert_eq!(high_scores.entries()[0].name, "first");
+    }
+
+    #[test]
+    fn full_table_drops_lowest_score() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=MAX_HIGH_SCORES {
+            high_scores.insert(entry("old", score * 10));
+        }
+
+        // Too low, or tied with the lowest entry, doesn't make the table
+        assert_eq!(high_scores.rank(5), None);
+        assert_eq!(high_scores.insert(entry("tie", 10)), None);
+
+        assert_eq!(high_scores.insert(entry("new", 55)), Some(5));
+        assert_eq!(high_scores.entries().len(), MAX_HIGH_SCORES);
+        assert_eq!(high_scores.entries().last().unwrap().score, 20);
+    }
+
+    #[test]
+    fn zero_never_makes_the_table() {
+        assert_eq!(HighScores::default().rank(0), None);
+    }
+
+    #[test]
+    fn tidies_hand_edited_files() {
+        let loaded = HighScores((0..15).map(|score| entry("x", score)).collect()).tidy();
+        assert_eq!(scores(&loaded), (5..15).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn round_trips_through_file() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("a", 10));
+        let saved = ron::to_string(&high_scores).unwrap();
+        assert_eq!(ron::from_str::<HighScores>(&saved).unwrap(), high_scores);
+    }
+
+    #[test]
+    fn blank_names_use_default() {
+        let name_entry = NameEntry {
+            name: "  ".to_string(),
+            score: 5,
+        };
+        assert_eq!(name_entry.high_score().name, DEFAULT_NAME);
+    }
+
+    fn game_over_app(score: usize) -> App {
+        let mut app = App::new();
+        app.insert_resource(HighScores::default())
+            .insert_resource(HighScoresPath(None))
+            .insert_resource(NameEntry {
+                name: String::new(),
+                score,
+            })
+            .init_resource::<InputBindings>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<NextState<GameState>>()
+            .add_event::<KeyboardInput>()
+            .add_systems(Update, (return_to_title, enter_name).chain());
+        app.world_mut().spawn((Text::default(), HighScoreTable));
+        app.world_mut().spawn((Text::default(), GameOverPrompt));
+        app
+    }
+
+    // Press and release the launch button of `gamepad`, over two updates
+    fn press_launch(app: &mut App, gamepad: Entity) {
+        let mut buttons = app.world_mut().get_mut::<Gamepad>(gamepad).unwrap();
+        buttons.digital_mut().press(GamepadButton::South);
+        app.update();
+        let mut buttons = app.world_mut().get_mut::<Gamepad>(gamepad).unwrap();
+        buttons.digital_mut().release(GamepadButton::South);
+        buttons.digital_mut().clear();
+        app.update();
+    }
+
+    #[test]
+    fn gamepad_saves_the_default_name_and_leaves() {
+        let mut app = game_over_app(100);
+        let gamepad = app.world_mut().spawn(Gamepad::default()).id();
+        app.update();
+
+        press_launch(&mut app, gamepad);
+        assert!(!app.world().contains_resource::<NameEntry>());
+        assert_eq!(
+            app.world().resource::<HighScores>().entries()[0].name,
+            DEFAULT_NAME
+        );
+        assert!(matches!(
+            app.world().resource::<NextState<GameState>>(),
+            NextState::Unchanged
+        ));
+
+        press_launch(&mut app, gamepad);
+        assert!(matches!(
+            app.world().resource::<NextState<GameState>>(),
+            NextState::Pending(GameState::Title)
+        ));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+    }
+}
@@ -0,0 +1,578 @@
+use std::path::PathBuf;
+
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    persist,
+    state::{GameState, prompt, screen},
+    versus::GameMode,
+};
+
+const INPUT_CONFIG_FILE: &str = "input.ron";
+const PLAYER_TWO_INPUT_CONFIG_FILE: &str = "input_player_two.ron";
+
+// These keys pause the game and leave the controls screen, so they can't be rebound
+const RESERVED_KEYS: [KeyCode; 2] = [KeyCode::Escape, KeyCode::KeyP];
+
+/// The range of [`InputBindings::paddle_sensitivity`]
+pub const MIN_PADDLE_SENSITIVITY: f32 = 0.5;
+pub const MAX_PADDLE_SENSITIVITY: f32 = 1.5;
+
+/// Loads the player's [`InputBindings`] and turns their input into a [`PaddleIntent`].
+/// In versus matches, [`PlayerTwoBindings`] steer the second paddle through [`PlayerTwoIntent`].
+pub struct PaddleInputPlugin;
+
+impl Plugin for PaddleInputPlugin {
+    fn build(&self, app: &mut App) {
+        let config_path = persist::config_path(INPUT_CONFIG_FILE);
+        let bindings = config_path
+            .as_deref()
+            .and_then(persist::load)
+            .unwrap_or_default();
+        // Player two's bindings can only be changed by editing their file
+        let player_two_bindings = persist::config_path(PLAYER_TWO_INPUT_CONFIG_FILE)
+            .as_deref()
+            .and_then(persist::load)
+            .unwrap_or_default();
+
+        app.insert_resource::<InputBindings>(bindings)
+            .insert_resource::<PlayerTwoBindings>(player_two_bindings)
+            .insert_resource(InputConfigPath(config_path))
+            .add_systems(PreUpdate, read_paddle_input.after(InputSystem))
+            .add_systems(OnEnter(GameState::Controls), start_rebinding)
+            .add_systems(Update, rebind.run_if(in_state(GameState::Controls)))
+            .add_systems(
+                Update,
+                save_bindings.run_if(
+                    resource_changed::<InputBindings>.and(not(resource_added::<InputBindings>)),
+                ),
+            );
+    }
+}
+
+/// The things the player can do with the paddle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddleAction {
+    Left,
+    Right,
+    Launch,
+}
+
+impl PaddleAction {
+    const ALL: [PaddleAction; 3] = [
+        PaddleAction::Left,
+        PaddleAction::Right,
+        PaddleAction::Launch,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PaddleAction::Left => "move left",
+            PaddleAction::Right => "move right",
+            PaddleAction::Launch => "launch",
+        }
+    }
+}
+
+/// Which keys and gamepad buttons control the paddle.
+///
+/// Saved as `input.ron` in the user's config directory. Missing fields use their defaults.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct InputBindings {
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub launch: Vec<KeyCode>,
+    pub gamepad_left: Vec<GamepadButton>,
+    pub gamepad_right: Vec<GamepadButton>,
+    pub gamepad_launch: Vec<GamepadButton>,
+    /// Move the paddle towards the mouse cursor, and launch with a left click
+    pub mouse: bool,
+    /// Gamepad stick movement smaller than this is ignored
+    pub stick_dead_zone: f32,
+    /// How fast the keys, d-pad and stick move the paddle, as a multiple of its usual speed,
+    /// from `MIN_PADDLE_SENSITIVITY` to `MAX_PADDLE_SENSITIVITY`
+    pub paddle_sensitivity: f32,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings {
+            left: vec![KeyCode::ArrowLeft, KeyCode::KeyA],
+            right: vec![KeyCode::ArrowRight, KeyCode::KeyD],
+            launch: vec![KeyCode::Space],
+            gamepad_left: vec![GamepadButton::DPadLeft],
+            gamepad_right: vec![GamepadButton::DPadRight],
+            gamepad_launch: vec![GamepadButton::South],
+            mouse: true,
+            stick_dead_zone: 0.2,
+            paddle_sensitivity: 1.0,
+        }
+    }
+}
+
+impl InputBindings {
+    fn keys_mut(&mut self, action: PaddleAction) -> &mut Vec<KeyCode> {
+        match action {
+            PaddleAction::Left => &mut self.left,
+            PaddleAction::Right => &mut self.right,
+            PaddleAction::Launch => &mut self.launch,
+        }
+    }
+
+    fn buttons_mut(&mut self, action: PaddleAction) -> &mut Vec<GamepadButton> {
+        match action {
+            PaddleAction::Left => &mut self.gamepad_left,
+            PaddleAction::Right => &mut self.gamepad_right,
+            PaddleAction::Launch => &mut self.gamepad_launch,
+        }
+    }
+
+    /// Make `key` the only key for `action`, taking it away from any other action
+    pub fn bind_key(&mut self, action: PaddleAction, key: KeyCode) {
+        for other in PaddleAction::ALL {
+            self.keys_mut(other).retain(|&bound| bound != key);
+        }
+        *self.keys_mut(action) = vec![key];
+    }
+
+    /// Make `button` the only gamepad button for `action`, taking it away from any other action
+    pub fn bind_button(&mut self, action: PaddleAction, button: GamepadButton) {
+        for other in PaddleAction::ALL {
+            self.buttons_mut(other).retain(|&bound| bound != button);
+        }
+        *self.buttons_mut(action) = vec![button];
+    }
+
+    /// A short description of what is bound to `action`, for prompts
+    pub fn describe(&self, action: PaddleAction) -> String {
+        let (keys, buttons) = match action {
+            PaddleAction::Left => (&self.left, &self.gamepad_left),
+            PaddleAction::Right => (&self.right, &self.gamepad_right),
+            PaddleAction::Launch => (&self.launch, &self.gamepad_launch),
+        };
+        let names: Vec<String> = keys
+            .iter()
+            .map(|key| format!("{key:?}"))
+            .chain(buttons.iter().map(|button| format!("{button:?}")))
+            .collect();
+
+        if names.is_empty() {
+            "nothing".to_string()
+        } else {
+            names.join(" / ")
+        }
+    }
+
+    /// How far left (-1.0) or right (1.0) the player is steering with keys, buttons or sticks
+    pub fn direction<'a>(
+        &self,
+        keyboard: &ButtonInput<KeyCode>,
+        gamepads: impl IntoIterator<Item = &'a Gamepad>,
+    ) -> f32 {
+        let mut direction = 0.0;
+
+        if keyboard.any_pressed(self.left.iter().copied()) {
+            direction -= 1.0;
+        }
+        if keyboard.any_pressed(self.right.iter().copied()) {
+            direction += 1.0;
+        }
+
+        for gamepad in gamepads {
+            if gamepad.any_pressed(self.gamepad_left.iter().copied()) {
+                direction -= 1.0;
+            }
+            if gamepad.any_pressed(self.gamepad_right.iter().copied()) {
+                direction += 1.0;
+            }
+
+            let stick = gamepad.left_stick().x;
+            if stick.abs() > self.stick_dead_zone {
+                direction += stick;
+            }
+        }
+
+        direction.clamp(-1.0, 1.0)
+    }
+
+    /// The paddle sensitivity, kept in range in case the config file was edited by hand
+    pub fn sensitivity(&self) -> f32 {
+        self.paddle_sensitivity
+            .clamp(MIN_PADDLE_SENSITIVITY, MAX_PADDLE_SENSITIVITY)
+    }
+
+    /// Whether any of the launch bindings were pressed this frame
+    pub fn launch_just_pressed<'a>(
+        &self,
+        keyboard: &ButtonInput<KeyCode>,
+        mouse_buttons: &ButtonInput<MouseButton>,
+        gamepads: impl IntoIterator<Item = &'a Gamepad>,
+    ) -> bool {
+        keyboard.any_just_pressed(self.launch.iter().copied())
+            || (self.mouse && mouse_buttons.just_pressed(MouseButton::Left))
+            || gamepads
+                .into_iter()
+                .any(|gamepad| gamepad.any_just_pressed(self.gamepad_launch.iter().copied()))
+    }
+}
+
+/// Which keys and gamepad buttons control player two's paddle in versus matches.
+///
+/// Saved as `input_player_two.ron` next to `input.ron`, in the same format.
+/// They default to keys that don't clash with player one's, and no mouse.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq, Deref, DerefMut)]
+#[serde(transparent)]
+pub struct PlayerTwoBindings(pub InputBindings);
+
+impl Default for PlayerTwoBindings {
+    fn default() -> Self {
+        PlayerTwoBindings(InputBindings {
+            left: vec![KeyCode::KeyJ],
+            right: vec![KeyCode::KeyL],
+            launch: vec![KeyCode::KeyI],
+            mouse: false,
+            ..default()
+        })
+    }
+}
+
+/// Where the input bindings are saved, if the platform has a config directory
+#[derive(Resource)]
+struct InputConfigPath(Option<PathBuf>);
+
+/// What the player wants the paddle to do this frame, whichever device they are using.
+///
+/// Replays record and play back this intent rather than raw input, see [`crate::replay`].
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq)]
+pub struct PaddleIntent {
+    /// Move left (-1.0) or right (1.0), as a fraction of the paddle's speed.
+    /// The paddle sensitivity can take this past 1.0, up to `MAX_PADDLE_SENSITIVITY`.
+    pub direction: f32,
+    /// Move towards this x position instead, when following the mouse
+    pub target_x: Option<f32>,
+    /// Launch any balls stuck to the paddle.
+    /// This stays set until the next fixed timestep, so that short presses are never missed.
+    pub launch: bool,
+}
+
+/// What player two wants their paddle to do, in versus matches
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Deref, DerefMut)]
+pub struct PlayerTwoIntent(pub PaddleIntent);
+
+#[allow(clippy::too_many_arguments)]
+fn read_paddle_input(
+    bindings: Res<InputBindings>,
+    player_two_bindings: Res<PlayerTwoBindings>,
+    game_mode: Res<GameMode>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut intent: ResMut<PaddleIntent>,
+    mut player_two_intent: ResMut<PlayerTwoIntent>,
+) {
+    // In versus matches, each player gets a gamepad of their own, in the order they connected
+    let gamepads: Vec<&Gamepad> = gamepads.iter().collect();
+    let (gamepads, player_two_gamepads) = match *game_mode {
+        GameMode::Solo => (&gamepads[..], &[][..]),
+        GameMode::Versus => gamepads.split_at(gamepads.len().min(1)),
+    };
+
+    if *game_mode == GameMode::Versus {
+        player_two_intent.direction = player_two_bindings
+            .direction(&keyboard, player_two_gamepads.iter().copied())
+            * player_two_bindings.sensitivity();
+        player_two_intent.launch |= player_two_bindings.launch_just_pressed(
+            &keyboard,
+            &mouse_buttons,
+            player_two_gamepads.iter().copied(),
+        );
+    }
+
+    intent.direction =
+        bindings.direction(&keyboard, gamepads.iter().copied()) * bindings.sensitivity();
+    intent.launch |=
+        bindings.launch_just_pressed(&keyboard, &mouse_buttons, gamepads.iter().copied());
+
+    let cursor = cursor_moved.read().last().map(|moved| moved.position);
+
+    // Steering with keys or a gamepad takes over from the mouse until it moves again
+    if intent.direction != 0.0 {
+        intent.target_x = None;
+    } else if bindings.mouse
+        && let Some(cursor) = cursor
+        && let Ok((camera, camera_transform)) = camera_query.single()
+        && let Ok(position) = camera.viewport_to_world_2d(camera_transform, cursor)
+    {
+        intent.target_x = Some(position.x);
+    }
+}
+
+/// Input for the screens between levels, which are confirmed with the launch bindings,
+/// and for the menus, which are moved through with the arrow keys or a gamepad's d-pad
+#[derive(SystemParam)]
+pub struct MenuInput<'w, 's> {
+    bindings: Res<'w, InputBindings>,
+    keyboard: Res<'w, ButtonInput<KeyCode>>,
+    mouse_buttons: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl MenuInput<'_, '_> {
+    pub fn confirm(&self) -> bool {
+        self.bindings
+            .launch_just_pressed(&self.keyboard, &self.mouse_buttons, self.gamepads.iter())
+    }
+
+    /// Like `confirm`, but only with the mouse or a gamepad, for screens where keys type text
+    pub fn confirm_without_keys(&self) -> bool {
+        self.bindings.launch_just_pressed(
+            &ButtonInput::default(),
+            &self.mouse_buttons,
+            self.gamepads.iter(),
+        )
+    }
+
+    /// Whether any key, mouse button or gamepad button was just pressed
+    pub fn any(&self) -> bool {
+        self.keyboard.get_just_pressed().next().is_some()
+            || self.mouse_buttons.get_just_pressed().next().is_some()
+            || self
+                .gamepads
+                .iter()
+                .any(|gamepad| gamepad.get_just_pressed().next().is_some())
+    }
+
+    /// Up (-1) or down (1) through a menu
+    pub fn vertical(&self) -> i32 {
+        self.axis(
+            [KeyCode::ArrowUp, KeyCode::ArrowDown],
+            [GamepadButton::DPadUp, GamepadButton::DPadDown],
+        )
+    }
+
+    /// Left (-1) or right (1), to change a setting
+    pub fn horizontal(&self) -> i32 {
+        self.axis(
+            [KeyCode::ArrowLeft, KeyCode::ArrowRight],
+            [GamepadButton::DPadLeft, GamepadButton::DPadRight],
+        )
+    }
+
+    /// Whether the focused menu item was chosen, with Enter or the launch bindings.
+    /// Unlike `confirm`, a mouse click doesn't count, as it chooses whatever is under the cursor.
+    pub fn select(&self) -> bool {
+        self.keyboard
+            .any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter])
+            || self
+                .keyboard
+                .any_just_pressed(self.bindings.launch.iter().copied())
+            || self.gamepads.iter().any(|gamepad| {
+                gamepad.any_just_pressed(self.bindings.gamepad_launch.iter().copied())
+            })
+    }
+
+    /// Whether the player wants to go back a menu, with Esc or a gamepad's east button
+    pub fn back(&self) -> bool {
+        self.keyboard.just_pressed(KeyCode::Escape)
+            || self
+                .gamepads
+                .iter()
+                .any(|gamepad| gamepad.just_pressed(GamepadButton::East))
+    }
+
+    // -1, 0 or 1, depending on which of the two keys or buttons was just pressed
+    fn axis(&self, keys: [KeyCode; 2], buttons: [GamepadButton; 2]) -> i32 {
+        let pressed = |index: usize| {
+            self.keyboard.just_pressed(keys[index])
+                || self
+                    .gamepads
+                    .iter()
+                    .any(|gamepad| gamepad.just_pressed(buttons[index]))
+        };
+        i32::from(pressed(1)) - i32::from(pressed(0))
+    }
+
+    /// The name of the first launch binding, for prompts like "Press Space to start"
+    pub fn confirm_name(&self) -> String {
+        match self.bindings.launch.first() {
+            Some(key) => format!("{key:?}"),
+            None => self.bindings.describe(PaddleAction::Launch),
+        }
+    }
+}
+
+/// Progress through rebinding each action in turn on the controls screen
+#[derive(Resource)]
+struct Rebinding {
+    next: usize,
+    bindings: InputBindings,
+}
+
+#[derive(Component)]
+struct RebindPrompt;
+
+fn rebind_prompt_text(rebinding: &Rebinding) -> String {
+    let action = PaddleAction::ALL[rebinding.next];
+    format!(
+        "Press a key or gamepad button to {}\nCurrently: {}\nPress Esc to cancel",
+        action.name(),
+        rebinding.bindings.describe(action)
+    )
+}
+
+fn start_rebinding(mut commands: Commands, bindings: Res<InputBindings>) {
+    let rebinding = Rebinding {
+        next: 0,
+        bindings: bindings.clone(),
+    };
+
+    commands.spawn(screen(
+        GameState::Controls,
+        "Controls",
+        (prompt(rebind_prompt_text(&rebinding)), RebindPrompt),
+    ));
+    commands.insert_resource(rebinding);
+}
+
+// Ask for a new binding for each action in turn, and save them once every action is bound
+fn rebind(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<InputBindings>,
+    mut rebind_prompt: Single<&mut Text, With<RebindPrompt>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Title);
+        return;
+    }
+
+    let action = PaddleAction::ALL[rebinding.next];
+    let key = keyboard
+        .get_just_pressed()
+        .find(|key| !RESERVED_KEYS.contains(key));
+    let button = gamepads
+        .iter()
+        .find_map(|gamepad| gamepad.get_just_pressed().next());
+
+    if let Some(&key) = key {
+        rebinding.bindings.bind_key(action, key);
+    } else if let Some(&button) = button {
+        rebinding.bindings.bind_button(action, button);
+    } else {
+        return;
+    }
+
+    rebinding.next += 1;
+    if rebinding.next < PaddleAction::ALL.len() {
+        rebind_prompt.0 = rebind_prompt_text(&rebinding);
+        return;
+    }
+
+    // `save_bindings` saves them
+    *bindings = rebinding.bindings.clone();
+    next_state.set(GameState::Title);
+}
+
+fn save_bindings(bindings: Res<InputBindings>, config_path: Res<InputConfigPath>) {
+    if let Some(path) = &config_path.0 {
+        persist::save(path, &*bindings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pressed(keys: &[KeyCode]) -> ButtonInput<KeyCode> {
+        let mut keyboard = ButtonInput::default();
+        for &key in keys {
+            keyboard.press(key);
+        }
+        keyboard
+    }
+
+    #[test]
+    fn keyboard_direction() {
+        let bindings = InputBindings::default();
+        let no_gamepads = std::iter::empty();
+
+        assert_eq!(bindings.direction(&pressed(&[]), no_gamepads.clone()), 0.0);
+        assert_eq!(
+            bindings.direction(&pressed(&[KeyCode::KeyA]), no_gamepads.clone()),
+            -1.0
+        );
+        assert_eq!(
+            bindings.direction(&pressed(&[KeyCode::ArrowRight]), no_gamepads.clone()),
+            1.0
+        );
+        // Holding both directions cancels out, and two keys for one direction don't add up
+        assert_eq!(
+            bindings.direction(
+                &pressed(&[KeyCode::KeyA, KeyCode::KeyD]),
+                no_gamepads.clone()
+            ),
+            0.0
+        );
+        assert_eq!(
+            bindings.direction(&pressed(&[KeyCode::KeyA, KeyCode::ArrowLeft]), no_gamepads),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn gamepad_direction() {
+        let bindings = InputBindings::default();
+        let keyboard = pressed(&[]);
+
+        let mut gamepad = Gamepad::default();
+        gamepad.analog_mut().set(GamepadAxis::LeftStickX, 0.1);
+        assert_eq!(bindings.direction(&keyboard, [&gamepad]), 0.0);
+
+        gamepad.analog_mut().set(GamepadAxis::LeftStickX, -0.5);
+        assert_eq!(bindings.direction(&keyboard, [&gamepad]), -0.5);
+
+        gamepad.analog_mut().set(GamepadAxis::LeftStickX, 0.0);
+        gamepad.digital_mut().press(GamepadButton::DPadRight);
+        assert_eq!(bindings.direction(&keyboard, [&gamepad]), 1.0);
+    }
+
+    #[test]
+    fn rebinding_moves_keys_between_actions() {
+        let mut bindings = InputBindings::default();
+
+        bindings.bind_key(PaddleAction::Left, KeyCode::KeyJ);
+        assert_eq!(bindings.left, vec![KeyCode::KeyJ]);
+
+        // Taking a key from another action stops it doing both
+        bindings.bind_key(PaddleAction::Right, KeyCode::KeyJ);
+        assert!(bindings.left.is_empty());
+        assert_eq!(bindings.right, vec![KeyCode::KeyJ]);
+        assert_eq!(bindings.describe(PaddleAction::Left), "DPadLeft");
+
+        bindings.bind_button(PaddleAction::Launch, GamepadButton::DPadLeft);
+        assert!(bindings.gamepad_left.is_empty());
+        assert_eq!(bindings.gamepad_launch, vec![GamepadButton::DPadLeft]);
+    }
+
+    #[test]
+    fn bindings_round_trip_through_config_file() {
+        let mut bindings = InputBindings::default();
+        bindings.bind_key(PaddleAction::Launch, KeyCode::Enter);
+        bindings.mouse = false;
+
+        let saved = ron::to_string(&bindings).unwrap();
+        assert_eq!(ron::from_str::<InputBindings>(&saved).unwrap(), bindings);
+
+        // Fields missing from older config files keep their defaults
+        let partial: InputBindings = ron::from_str("(launch: [Enter])").unwrap();
+        assert_eq!(partial.launch, vec![KeyCode::Enter]);
+        assert_eq!(partial.left, InputBindings::default().left);
+        assert_eq!(partial.paddle_sensitivity, 1.0);
+    }
+}
@@ -0,0 +1,809 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    BALL_SPEED, BRICK_SIZE, GAP_BETWEEN_BRICKS, GAP_BETWEEN_BRICKS_AND_CEILING,
+    GAP_BETWEEN_BRICKS_AND_SIDES, GAP_BETWEEN_PADDLE_AND_BRICKS, arena::ArenaConfig,
+    difficulty::Difficulty, obstacle::LevelObstacle,
+};
+
+/// The bundled levels, in the order they are played.
+/// Together they make up the campaign, see [`crate::campaign`].
+pub const LEVELS: &[&str] = &[
+    "levels/01_classic.level.ron",
+    "levels/02_checkerboard.level.ron",
+    "levels/03_pyramid.level.ron",
+    "levels/04_fortress.level.ron",
+    "levels/05_fuse.level.ron",
+    "levels/06_windmill.level.ron",
+];
+
+/// The most stars a level can be rated with
+pub const MAX_STARS: usize = 3;
+
+/// Registers the [`Level`] asset and its loader
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Level>().init_asset_loader::<LevelLoader>();
+    }
+}
+
+/// Plays a level file from anywhere on disk instead of the bundled levels,
+/// such as one saved by the level editor
+#[derive(Default)]
+pub struct LevelFilePlugin {
+    pub path: Option<PathBuf>,
+}
+
+impl LevelFilePlugin {
+    /// Reads `--level <file>` from the command line
+    pub fn from_args() -> LevelFilePlugin {
+        let mut plugin = LevelFilePlugin::default();
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            if arg == "--level" {
+                plugin.path = args.next().map(PathBuf::from);
+            }
+        }
+
+        plugin
+    }
+}
+
+impl Plugin for LevelFilePlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(path) = &self.path {
+            app.insert_resource(LevelFilePath(path.clone()))
+                .add_systems(Startup, load_level_file);
+        }
+    }
+}
+
+/// The level file given on the command line, which the level editor also saves to
+#[derive(Resource, Debug, Clone, Deref)]
+pub struct LevelFilePath(pub PathBuf);
+
+/// While present, this level is played instead of the bundled [`Levels`](crate::Levels),
+/// and the game ends when it is cleared
+#[derive(Resource, Debug, Clone, Deref)]
+pub struct CustomLevel(pub Handle<Level>);
+
+fn load_level_file(
+    mut commands: Commands,
+    path: Res<LevelFilePath>,
+    arena: Res<ArenaConfig>,
+    mut levels: ResMut<Assets<Level>>,
+) {
+    match Level::load(&path, &arena) {
+        Ok(level) => commands.insert_resource(CustomLevel(levels.add(level))),
+        Err(error) => error!("Could not load level {}: {error}", path.display()),
+    }
+}
+
+/// The different kinds of brick a level can contain
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize, Reflect)]
+pub enum BrickKind {
+    Normal,
+    MultiHit,
+    Unbreakable,
+    Explosive,
+}
+
+impl BrickKind {
+    pub const ALL: [BrickKind; 4] = [
+        BrickKind::Normal,
+        BrickKind::MultiHit,
+        BrickKind::Unbreakable,
+        BrickKind::Explosive,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BrickKind::Normal => "Normal",
+            BrickKind::MultiHit => "Multi-hit",
+            BrickKind::Unbreakable => "Unbreakable",
+            BrickKind::Explosive => "Explosive",
+        }
+    }
+
+    /// Unbreakable bricks don't need to be destroyed to clear a level
+    pub fn is_breakable(self) -> bool {
+        self != BrickKind::Unbreakable
+    }
+
+    /// Parse a single layout cell. `Some(None)` is an empty cell, and `None` an unknown one.
+    fn from_cell(cell: char) -> Option<Option<BrickKind>> {
+        match cell {
+            '.' | ' ' => Some(None),
+            '#' => Some(Some(BrickKind::Normal)),
+            'H' => Some(Some(BrickKind::MultiHit)),
+            'U' => Some(Some(BrickKind::Unbreakable)),
+            'E' => Some(Some(BrickKind::Explosive)),
+            _ => None,
+        }
+    }
+
+    /// The layout cell for `kind`, the reverse of `from_cell`
+    fn to_cell(kind: Option<BrickKind>) -> char {
+        match kind {
+            None => '.',
+            Some(BrickKind::Normal) => '#',
+            Some(BrickKind::MultiHit) => 'H',
+            Some(BrickKind::Unbreakable) => 'U',
+            Some(BrickKind::Explosive) => 'E',
+        }
+    }
+}
+
+/// A brick layout, as described by a `.level.ron` file in `assets/levels`,
+/// or saved by the level editor.
+///
+/// The game can start from any level file with `--level <file>`.
+///
+/// ```ron
+/// (
+///     name: "Classic",
+///     // Optional, defaults to `BALL_SPEED`
+///     ball_speed: 400.0,
+///     // Optional, defaults to `GAP_BETWEEN_BRICKS`
+///     gap: 5.0,
+///     // Optional, overrides the difficulty chosen by the player for this level
+///     difficulty: Some(Hard),
+///     // Optional, the score in this level that earns one, two and three stars
+///     stars: [150, 250, 400],
+///     // One character per brick cell, top row first:
+///     // `#` normal, `H` multi-hit, `U` unbreakable, `E` explosive, `.` empty
+///     layout: [
+///         "########",
+///         "#.H..H.#",
+///     ],
+///     // Optional, things that aren't on the brick grid. Positions are from the arena's center.
+///     obstacles: [
+///         // A bar 120 wide and 20 tall, turning at 90 degrees per second
+///         (shape: Bar(120.0, 20.0), position: (0.0, -50.0), spin: 90.0),
+///         // A disc 40 wide, going back and forth between two points at 150 units per second
+///         (shape: Disc(40.0), position: (-200.0, 0.0), path: [(200.0, 0.0)], speed: 150.0),
+///         // A brick that moves like the disc, tilted by 30 degrees
+///         (shape: Brick(Explosive), position: (0.0, 0.0), angle: 30.0),
+///     ],
+/// )
+/// ```
+#[derive(Asset, TypePath, Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub ball_speed: f32,
+    pub gap: f32,
+    pub difficulty: Option<Difficulty>,
+    /// The scores that earn each star, lowest first. Levels without any aren't rated.
+    pub stars: Vec<usize>,
+    /// Rows of cells, top row first. Rows may be shorter than the widest row.
+    pub cells: Vec<Vec<Option<BrickKind>>>,
+    pub obstacles: Vec<LevelObstacle>,
+}
+
+/// The on-disk representation of a [`Level`]
+#[derive(Serialize, Deserialize)]
+struct LevelFile {
+    name: String,
+    #[serde(default = "default_ball_speed")]
+    ball_speed: f32,
+    #[serde(default = "default_gap")]
+    gap: f32,
+    #[serde(default)]
+    difficulty: Option<Difficulty>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stars: Vec<usize>,
+    layout: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    obstacles: Vec<LevelObstacle>,
+}
+
+fn default_ball_speed() -> f32 {
+    BALL_SPEED
+}
+
+fn default_gap() -> f32 {
+    GAP_BETWEEN_BRICKS
+}
+
+#[derive(Debug, Error)]
+pub enum LevelError {
+    #[error("could not read level file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse level file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("could not write level file: {0}")]
+    Write(#[from] ron::Error),
+    #[error("level has no bricks that can be destroyed")]
+    Empty,
+    #[error("ball speed must be positive, got {0}")]
+    BallSpeed(f32),
+    #[error("gap between bricks can't be negative, got {0}")]
+    Gap(f32),
+    #[error("star scores must go up, with at most {MAX_STARS} of them, got {0:?}")]
+    Stars(Vec<usize>),
+    #[error("unknown brick {cell:?} at row {row}, column {column}")]
+    UnknownCell {
+        cell: char,
+        row: usize,
+        column: usize,
+    },
+    #[error(
+        "{columns} columns of bricks need {needed}px but only {available}px fit between the side walls"
+    )]
+    TooWide {
+        columns: usize,
+        needed: f32,
+        available: f32,
+    },
+    #[error(
+        "{rows} rows of bricks need {needed}px but only {available}px fit between the ceiling and the paddle"
+    )]
+    TooTall {
+        rows: usize,
+        needed: f32,
+        available: f32,
+    },
+    #[error("obstacle {index} goes outside the arena, to {point}")]
+    ObstacleOutside { index: usize, point: Vec2 },
+}
+
+impl Level {
+    /// Parse the contents of a `.level.ron` file, and check that it fits in `arena`
+    pub fn parse(bytes: &[u8], arena: &ArenaConfig) -> Result<Level, LevelError> {
+        let file: LevelFile = ron::de::from_bytes(bytes)?;
+
+        if !(file.ball_speed.is_finite() && file.ball_speed > 0.0) {
+            return Err(LevelError::BallSpeed(file.ball_speed));
+        }
+        if !(file.gap.is_finite() && file.gap >= 0.0) {
+            return Err(LevelError::Gap(file.gap));
+        }
+        if file.stars.len() > MAX_STARS || !file.stars.is_sorted_by(|a, b| a < b) {
+            return Err(LevelError::Stars(file.stars));
+        }
+
+        let mut cells = Vec::with_capacity(file.layout.len());
+        for (row, line) in file.layout.iter().enumerate() {
+            let mut parsed_row = Vec::with_capacity(line.len());
+            for (column, cell) in line.chars().enumerate() {
+                let kind = BrickKind::from_cell(cell).ok_or(LevelError::UnknownCell {
+                    cell,
+                    row,
+                    column,
+                })?;
+                parsed_row.push(kind);
+            }
+            cells.push(parsed_row);
+        }
+
+        let level = Level {
+            name: file.name,
+            ball_speed: file.ball_speed,
+            gap: file.gap,
+            difficulty: file.difficulty,
+            stars: file.stars,
+            cells,
+            obstacles: file.obstacles,
+        };
+
+        // Catch layouts that don't fit in the arena at load time, rather than when spawning
+        level.bricks(arena)?;
+        for (index, obstacle) in level.obstacles.iter().enumerate() {
+            if let Some(point) = obstacle.points(arena).find(|point| !arena.contains(*point)) {
+                return Err(LevelError::ObstacleOutside { index, point });
+            }
+        }
+
+        Ok(level)
+    }
+
+    /// Read and [`parse`](Level::parse) a level file
+    pub fn load(path: &Path, arena: &ArenaConfig) -> Result<Level, LevelError> {
+        Level::parse(&std::fs::read(path)?, arena)
+    }
+
+    /// The contents of a `.level.ron` file for this level, which `parse` reads back
+    pub fn to_ron(&self) -> Result<String, LevelError> {
+        let file = LevelFile {
+            name: self.name.clone(),
+            ball_speed: self.ball_speed,
+            gap: self.gap,
+            difficulty: self.difficulty,
+            stars: self.stars.clone(),
+            layout: self
+                .cells
+                .iter()
+                .map(|row| row.iter().map(|&kind| BrickKind::to_cell(kind)).collect())
+                .collect(),
+            obstacles: self.obstacles.clone(),
+        };
+        let config = ron::ser::PrettyConfig::default().struct_names(false);
+        Ok(ron::ser::to_string_pretty(&file, config)?)
+    }
+
+    /// Write this level to `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> Result<(), LevelError> {
+        let contents = self.to_ron()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// How many stars `score` earns in this level
+    pub fn stars_for(&self, score: usize) -> usize {
+        self.stars
+            .iter()
+            .take_while(|&&threshold| score >= threshold)
+            .count()
+    }
+
+    fn n_rows(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn n_columns(&self) -> usize {
+        self.cells.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// The center position and kind of every brick in the layout, placed by a [`BrickGrid`]
+    pub fn bricks(&self, arena: &ArenaConfig) -> Result<Vec<(Vec2, BrickKind)>, LevelError> {
+        if !self
+            .cells
+            .iter()
+            .flatten()
+            .flatten()
+            .any(|kind| kind.is_breakable())
+        {
+            return Err(LevelError::Empty);
+        }
+
+        let grid = BrickGrid::new(self.n_columns(), self.n_rows(), self.gap, arena)?;
+        let bricks = self
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(row, cells)| {
+                cells.iter().enumerate().filter_map(move |(column, kind)| {
+                    kind.map(|kind| (grid.position(column, row), kind))
+                })
+            })
+            .collect();
+
+        Ok(bricks)
+    }
+}
+
+/// Where the cells of a brick layout are in the arena.
+///
+/// The layout is centered horizontally between the side walls,
+/// and its top edge sits `GAP_BETWEEN_BRICKS_AND_CEILING` below the top wall.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrickGrid {
+    pub columns: usize,
+    pub rows: usize,
+    gap: f32,
+    // The center of the top-left cell
+    top_left: Vec2,
+}
+
+impl BrickGrid {
+    /// The grid for a layout of `columns` by `rows` cells, if it fits in `arena`
+    pub fn new(
+        columns: usize,
+        rows: usize,
+        gap: f32,
+        arena: &ArenaConfig,
+    ) -> Result<BrickGrid, LevelError> {
+        let needed_width = columns as f32 * BRICK_SIZE.x + columns.saturating_sub(1) as f32 * gap;
+        let available_width = Self::available_size(arena).x;
+        if needed_width > available_width {
+            return Err(LevelError::TooWide {
+                columns,
+                needed: needed_width,
+                available: available_width,
+            });
+        }
+
+        let needed_height = rows as f32 * BRICK_SIZE.y + rows.saturating_sub(1) as f32 * gap;
+        let available_height = Self::available_size(arena).y;
+        if needed_height > available_height {
+            return Err(LevelError::TooTall {
+                rows,
+                needed: needed_height,
+                available: available_height,
+            });
+        }
+
+        // In Bevy, the `translation` of an entity describes the center point,
+        // not its top-left corner
+        let top_left = Vec2::new(
+            arena.center_x() - needed_width / 2. + BRICK_SIZE.x / 2.,
+            arena.top - GAP_BETWEEN_BRICKS_AND_CEILING - BRICK_SIZE.y / 2.,
+        );
+
+        Ok(BrickGrid {
+            columns,
+            rows,
+            gap,
+            top_left,
+        })
+    }
+
+    /// The grid with as many cells as fit in `arena`, which the level editor paints on.
+    /// Fails if not even one brick fits.
+    pub fn largest(gap: f32, arena: &ArenaConfig) -> Result<BrickGrid, LevelError> {
+        if !(gap.is_finite() && gap >= 0.0) {
+            return Err(LevelError::Gap(gap));
+        }
+
+        let cells = (Self::available_size(arena) + gap) / (BRICK_SIZE + gap);
+        let columns = cells.x.max(1.0) as usize;
+        let rows = cells.y.max(1.0) as usize;
+        BrickGrid::new(columns, rows, gap, arena)
+    }
+
+    // The space for bricks between the side walls,
+    // and between the ceiling and the paddle
+    fn available_size(arena: &ArenaConfig) -> Vec2 {
+        let top_edge_of_bricks = arena.top - GAP_BETWEEN_BRICKS_AND_CEILING;
+        Vec2::new(
+            arena.width() - 2. * GAP_BETWEEN_BRICKS_AND_SIDES,
+            top_edge_of_bricks - (arena.paddle_y() + GAP_BETWEEN_PADDLE_AND_BRICKS),
+        )
+    }
+
+    /// The center of the cell at `column` and `row`, counting from the top left
+    pub fn position(&self, column: usize, row: usize) -> Vec2 {
+        self.top_left + Vec2::new(column as f32, -(row as f32)) * (BRICK_SIZE + self.gap)
+    }
+
+    /// The `(column, row)` of the cell nearest to `point`, if `point` is within the grid.
+    /// The gaps between cells are split between the cells on either side.
+    pub fn cell_at(&self, point: Vec2) -> Option<(usize, usize)> {
+        let offset = (point - self.top_left) * Vec2::new(1.0, -1.0) / (BRICK_SIZE + self.gap);
+        let (column, row) = (offset.x.round(), offset.y.round());
+        if column < 0.0 || row < 0.0 || column as usize >= self.columns || row as usize >= self.rows
+        {
+            return None;
+        }
+
+        Some((column as usize, row as usize))
+    }
+}
+
+/// Loads `.level.ron` files, checking that they fit in the [`ArenaConfig`]
+/// that existed when the loader was added
+pub struct LevelLoader {
+    arena: ArenaConfig,
+}
+
+impl FromWorld for LevelLoader {
+    fn from_world(world: &mut World) -> Self {
+        LevelLoader {
+            arena: world
+                .get_resource::<ArenaConfig>()
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Level, LevelError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Level::parse(&bytes, &self.arena)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obstacle::ObstacleShape;
+
+    fn read_level(path: &str) -> Vec<u8> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join(path);
+        std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+    }
+
+    #[test]
+    fn bundled_levels_parse() {
+        for path in LEVELS {
+            let level = Level::parse(&read_level(path), &ArenaConfig::default())
+                .unwrap_or_else(|e| panic!("{path}: {e}"));
+            assert!(
+                !level.bricks(&ArenaConfig::default()).unwrap().is_empty(),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn every_level_file_is_bundled() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/levels");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let name = entry.unwrap().file_name().into_string().unwrap();
+            assert!(
+                LEVELS.contains(&format!("levels/{name}").as_str()),
+                "{name} is missing from LEVELS"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_cells() {
+        let level = Level::parse(
+            br##"(name: "t", layout: ["#H", "U.E"])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(level.ball_speed, BALL_SPEED);
+        assert_eq!(level.gap, GAP_BETWEEN_BRICKS);
+        assert_eq!(level.difficulty, None);
+        assert!(level.stars.is_empty());
+        assert_eq!(level.stars_for(1000), 0);
+        assert_eq!(
+            level.cells,
+            vec![
+                vec![Some(BrickKind::Normal), Some(BrickKind::MultiHit)],
+                vec![
+                    Some(BrickKind::Unbreakable),
+                    None,
+                    Some(BrickKind::Explosive)
+                ],
+            ]
+        );
+        assert_eq!(level.bricks(&ArenaConfig::default()).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn parses_difficulty_override() {
+        let level = Level::parse(
+            br##"(name: "t", difficulty: Some(Hard), layout: ["#"])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(level.difficulty, Some(Difficulty::Hard));
+    }
+
+    #[test]
+    fn rejects_unknown_cell() {
+        let error = Level::parse(
+            br##"(name: "t", ball_speed: 100.0, layout: ["#?"])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            LevelError::UnknownCell {
+                cell: '?',
+                row: 0,
+                column: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_ball_speeds_that_are_not_positive() {
+        for ball_speed in ["0.0", "-100.0", "NaN", "inf"] {
+            let ron = format!(r##"(name: "t", ball_speed: {ball_speed}, layout: ["#"])"##);
+            let error = Level::parse(ron.as_bytes(), &ArenaConfig::default()).unwrap_err();
+            assert!(matches!(error, LevelError::BallSpeed(_)), "{ball_speed}");
+        }
+    }
+
+    #[test]
+    fn rejects_negative_gaps() {
+        for gap in ["-30.0", "-50.0", "NaN", "inf"] {
+            let ron = format!(r##"(name: "t", gap: {gap}, layout: ["#", "#"])"##);
+            let error = Level::parse(ron.as_bytes(), &ArenaConfig::default()).unwrap_err();
+            assert!(matches!(error, LevelError::Gap(_)), "{gap}");
+        }
+
+        let level = Level::parse(
+            br##"(name: "t", gap: 0.0, layout: ["#", "#"])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(level.gap, 0.0);
+    }
+
+    #[test]
+    fn rates_scores_against_the_star_scores() {
+        let level = Level::parse(
+            br##"(name: "t", stars: [10, 20, 30], layout: ["#"])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(level.stars_for(0), 0);
+        assert_eq!(level.stars_for(10), 1);
+        assert_eq!(level.stars_for(29), 2);
+        assert_eq!(level.stars_for(1000), MAX_STARS);
+    }
+
+    #[test]
+    fn rejects_star_scores_out_of_order() {
+        for stars in ["[20, 10]", "[10, 10]", "[1, 2, 3, 4]"] {
+            let ron = format!(r##"(name: "t", stars: {stars}, layout: ["#"])"##);
+            let error = Level::parse(ron.as_bytes(), &ArenaConfig::default()).unwrap_err();
+            assert!(matches!(error, LevelError::Stars(_)), "{stars}");
+        }
+    }
+
+    #[test]
+    fn rejects_layout_wider_than_arena() {
+        let error = Level::parse(
+            br#"(name: "t", ball_speed: 100.0, layout: ["HHHHHHHHH"])"#,
+            &ArenaConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, LevelError::TooWide { columns: 9, .. }));
+    }
+
+    #[test]
+    fn rejects_layout_taller_than_arena() {
+        let error = Level::parse(
+            br##"(name: "t", ball_speed: 100.0, layout: ["#", "#", "#", "#", "#", "#", "#", "#"])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, LevelError::TooTall { rows: 8, .. }));
+    }
+
+    #[test]
+    fn parses_obstacles() {
+        let level = Level::parse(
+            br##"(name: "t", layout: ["#"], obstacles: [
+                (shape: Disc(40.0), position: (0.0, 0.0)),
+                (shape: Brick(MultiHit), position: (-50.0, 0.0), path: [(50.0, 0.0)], speed: 10.0),
+            ])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(level.obstacles.len(), 2);
+        assert_eq!(level.obstacles[0].shape, ObstacleShape::Disc(40.0));
+        assert_eq!(level.obstacles[0].spin, 0.0);
+        assert_eq!(
+            level.obstacles[1].shape,
+            ObstacleShape::Brick(BrickKind::MultiHit)
+        );
+        assert_eq!(level.obstacles[1].path, vec![Vec2::new(50.0, 0.0)]);
+    }
+
+    #[test]
+    fn rejects_obstacle_paths_outside_arena() {
+        let error = Level::parse(
+            br##"(name: "t", layout: ["#"], obstacles: [
+                (shape: Disc(40.0), position: (0.0, 0.0), path: [(1000.0, 0.0)], speed: 10.0),
+            ])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            LevelError::ObstacleOutside { index: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_layout() {
+        let error = Level::parse(
+            br##"(name: "t", ball_speed: 100.0, layout: ["...."])"##,
+            &ArenaConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, LevelError::Empty));
+
+        let error = Level::parse(
+            br#"(name: "t", ball_speed: 100.0, layout: ["U.U"])"#,
+            &ArenaConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, LevelError::Empty));
+    }
+
+    #[test]
+    fn largest_grid_fills_the_arena() {
+        let arena = ArenaConfig::default();
+        let grid = BrickGrid::largest(GAP_BETWEEN_BRICKS, &arena).unwrap();
+        assert_eq!((grid.columns, grid.rows), (8, 7));
+
+        let too_wide = BrickGrid::new(grid.columns + 1, 1, GAP_BETWEEN_BRICKS, &arena);
+        assert!(matches!(too_wide, Err(LevelError::TooWide { .. })));
+        let too_tall = BrickGrid::new(1, grid.rows + 1, GAP_BETWEEN_BRICKS, &arena);
+        assert!(matches!(too_tall, Err(LevelError::TooTall { .. })));
+    }
+
+    #[test]
+    fn no_largest_grid_without_room_for_a_brick() {
+        let tiny = ArenaConfig {
+            left: -100.0,
+            right: 100.0,
+            bottom: -100.0,
+            top: 100.0,
+            ..default()
+        };
+        assert!(matches!(
+            BrickGrid::largest(GAP_BETWEEN_BRICKS, &tiny),
+            Err(LevelError::TooTall { .. })
+        ));
+        assert!(matches!(
+            BrickGrid::largest(-30.0, &ArenaConfig::default()),
+            Err(LevelError::Gap(_))
+        ));
+    }
+
+    #[test]
+    fn finds_the_cell_at_a_point() {
+        let grid = BrickGrid::largest(GAP_BETWEEN_BRICKS, &ArenaConfig::default()).unwrap();
+
+        for (column, row) in [(0, 0), (3, 2), (grid.columns - 1, grid.rows - 1)] {
+            let center = grid.position(column, row);
+            assert_eq!(grid.cell_at(center), Some((column, row)));
+            assert_eq!(grid.cell_at(center + BRICK_SIZE * 0.4), Some((column, row)));
+        }
+
+        let outside = grid.position(0, 0) - BRICK_SIZE * Vec2::new(1.0, -1.0);
+        assert_eq!(grid.cell_at(outside), None);
+    }
+
+    #[test]
+    fn saved_levels_parse_back() {
+        let level = Level {
+            name: "Saved".to_string(),
+            ball_speed: 300.0,
+            gap: 5.0,
+            difficulty: Some(Difficulty::Hard),
+            stars: vec![10, 20, 30],
+            cells: vec![
+                vec![Some(BrickKind::Normal), None, Some(BrickKind::MultiHit)],
+                vec![
+                    Some(BrickKind::Unbreakable),
+                    Some(BrickKind::Explosive),
+                    None,
+                ],
+            ],
+            obstacles: vec![LevelObstacle {
+                shape: ObstacleShape::Bar(120.0, 20.0),
+                position: Vec2::new(-100.0, 0.0),
+                angle: 45.0,
+                path: vec![Vec2::new(100.0, 0.0)],
+                speed: 100.0,
+                spin: 90.0,
+            }],
+        };
+
+        let ron = level.to_ron().unwrap();
+        assert_eq!(
+            Level::parse(ron.as_bytes(), &ArenaConfig::default()).unwrap(),
+            level
+        );
+    }
+}
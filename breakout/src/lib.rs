@@ -0,0 +1,1185 @@
+//! Breakout, split into the game's rules in [`BreakoutSimPlugin`]
+//! and everything the player sees, hears and touches in [`BreakoutPresentationPlugin`].
+//!
+//! The simulation can run on its own, which is how the tests in `tests/` drive it.
+
+use std::collections::VecDeque;
+
+use bevy::{
+    math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume},
+    prelude::*,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+pub mod arena;
+mod attract;
+pub mod autopilot;
+pub mod broadphase;
+pub mod campaign;
+mod collision;
+pub mod difficulty;
+mod editor;
+mod effects;
+pub mod high_score;
+pub mod input;
+pub mod level;
+mod menu;
+pub mod obstacle;
+mod persist;
+pub mod power_up;
+mod presentation;
+pub mod replay;
+pub mod save_game;
+pub mod scoring;
+mod settings;
+mod sound;
+pub mod state;
+pub mod stepping;
+mod synth;
+pub mod versus;
+mod watchdog;
+
+use arena::ArenaConfig;
+use autopilot::AutopilotPlugin;
+use broadphase::{
+    Broadphase, add_to_broadphase, remove_from_broadphase, swept_aabb, update_broadphase,
+};
+pub use collision::ColliderShape;
+use collision::{Collision, Volume};
+use difficulty::{BallSpeedMultiplier, Difficulty, ramp_up_ball_speed};
+use input::{MAX_PADDLE_SENSITIVITY, PaddleIntent, PlayerTwoIntent};
+use level::{BrickKind, CustomLevel, LEVELS, Level, LevelPlugin};
+use obstacle::{Obstacle, patrol, push_balls_out_of_the_way, spawn_obstacle, spin};
+use power_up::{
+    ActivePowerUps, apply_power_up_effects, catch_power_ups, check_for_laser_hits, drop_power_ups,
+    fire_lasers, tick_power_ups,
+};
+pub use presentation::BreakoutPresentationPlugin;
+use save_game::SaveGamePlugin;
+use scoring::{Combo, ScoreEvent, add_score, award_points};
+use state::{GameState, GameStatePlugin, InGame};
+use versus::{
+    GameMode, Player, Standing, center_bricks, check_for_match_over, is_versus, lose_lives,
+};
+use watchdog::{Trajectory, watch_for_stuck_balls};
+
+// These constants are defined in `Transform` units, like the `ArenaConfig`.
+// The camera scales the arena to fit the window.
+const PADDLE_SIZE: Vec2 = Vec2::new(120.0, 20.0);
+const GAP_BETWEEN_PADDLE_AND_FLOOR: f32 = 60.0;
+const PADDLE_SPEED: f32 = 500.0;
+// When following the mouse, the paddle can move this many times faster
+const MOUSE_PADDLE_SPEED_SCALE: f32 = 3.0;
+// How close can the paddle get to the wall
+const PADDLE_PADDING: f32 = 10.0;
+
+const BALL_DIAMETER: f32 = 30.;
+// Default ball speed for levels that don't set their own
+const BALL_SPEED: f32 = 400.0;
+const INITIAL_BALL_DIRECTION: Vec2 = Vec2::new(0.5, 0.5);
+// When bouncing off the paddle, the ball always keeps at least this fraction of its speed vertically,
+// so that hitting the very edge of the paddle doesn't send it sideways forever
+const MIN_BALL_VERTICAL_SPEED_FRACTION: f32 = 0.4;
+// A fast ball can bounce several times in one fixed timestep, e.g. in a narrow gap between bricks.
+// Any motion left over after this many bounces is dropped.
+const MAX_BALL_BOUNCES_PER_STEP: usize = 4;
+
+const STARTING_LIVES: u32 = 3;
+
+const BRICK_SIZE: Vec2 = Vec2::new(100., 30.);
+// These values are exact
+const GAP_BETWEEN_BRICKS_AND_CEILING: f32 = 20.0;
+// Levels can override this
+const GAP_BETWEEN_BRICKS: f32 = 5.0;
+// These values are lower bounds, as the size of the brick layout depends on the level
+const GAP_BETWEEN_PADDLE_AND_BRICKS: f32 = 270.0;
+const GAP_BETWEEN_BRICKS_AND_SIDES: f32 = 20.0;
+
+const MULTI_HIT_BRICK_HIT_POINTS: u32 = 3;
+// Explosive bricks damage every brick whose center is within this distance on both axes,
+// which covers the eight bricks around them in a regular layout
+const EXPLOSION_RANGE: Vec2 = Vec2::new(BRICK_SIZE.x * 1.5, BRICK_SIZE.y * 1.5);
+
+const SCOREBOARD_FONT_SIZE: f32 = 33.0;
+const SCOREBOARD_TEXT_PADDING: Val = Val::Px(5.0);
+
+const BACKGROUND_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const PADDLE_COLOR: Color = Color::srgb(0.3, 0.3, 0.7);
+const BALL_COLOR: Color = Color::srgb(1.0, 0.5, 0.5);
+const BRICK_COLOR: Color = Color::srgb(0.5, 0.5, 1.0);
+const UNBREAKABLE_BRICK_COLOR: Color = Color::srgb(0.4, 0.4, 0.4);
+const EXPLOSIVE_BRICK_COLOR: Color = Color::srgb(1.0, 0.6, 0.2);
+// How much darker a multi-hit brick is for each hit it can still take
+const BRICK_DAMAGE_COLOR_STEP: f32 = 0.15;
+const WALL_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
+const TEXT_COLOR: Color = Color::srgb(0.5, 0.5, 1.0);
+const SCORE_COLOR: Color = Color::srgb(1.0, 0.5, 0.5);
+
+/// The rules of the game: levels, states, the paddle, balls and bricks, and the `FixedUpdate` chain.
+///
+/// Nothing here draws, plays sounds or reads input devices, so it can run headless.
+/// It needs the time, asset and state plugins, which `DefaultPlugins` includes,
+/// or `MinimalPlugins` with `AssetPlugin` and `StatesPlugin`.
+/// The paddle is controlled through the [`PaddleIntent`] resource,
+/// and the size of the arena can be changed with an [`ArenaConfig`].
+/// Setting the [`GameMode`] to versus before a level starts makes it a two-player match,
+/// see [`versus`].
+pub struct BreakoutSimPlugin;
+
+impl Plugin for BreakoutSimPlugin {
+    fn build(&self, app: &mut App) {
+        // The level loader needs the arena, so this goes first
+        app.init_resource::<ArenaConfig>()
+            .add_plugins((
+                LevelPlugin,
+                GameStatePlugin,
+                AutopilotPlugin,
+                SaveGamePlugin,
+            ))
+            .insert_resource(Score(0))
+            .insert_resource(Lives(STARTING_LIVES))
+            .insert_resource(BallSpeed(BALL_SPEED))
+            .insert_resource(CurrentLevel(0))
+            .insert_resource(LevelSeed(rand::random()))
+            .init_resource::<ActivePowerUps>()
+            .init_resource::<Difficulty>()
+            .init_resource::<BallSpeedMultiplier>()
+            .init_resource::<PaddleIntent>()
+            .init_resource::<PlayerTwoIntent>()
+            .init_resource::<GameMode>()
+            .init_resource::<Combo>()
+            .init_resource::<Broadphase>()
+            .add_event::<CollisionEvent>()
+            .add_event::<BrickHit>()
+            .add_event::<BrickDestroyed>()
+            .add_event::<ScoreEvent>()
+            .add_event::<BallLost>()
+            .add_observer(add_to_broadphase)
+            .add_observer(remove_from_broadphase)
+            .add_systems(Startup, setup)
+            // The paddle, ball and bricks only exist while a level is being played
+            .add_systems(OnEnter(InGame), spawn_level)
+            .add_systems(OnExit(InGame), choose_next_level_seed)
+            .add_systems(OnExit(GameState::Paused), forget_launch)
+            // Add our gameplay simulation systems to the fixed timestep schedule
+            // which runs at 64 Hz by default
+            .add_systems(
+                FixedUpdate,
+                (
+                    apply_velocity,
+                    move_paddle,
+                    follow_paddle,
+                    launch_ball,
+                    apply_power_up_effects,
+                    // Moving colliders move before the balls, so balls see where they are now,
+                    // and the broadphase is told where everything has moved to
+                    (patrol, spin, push_balls_out_of_the_way, update_broadphase).chain(),
+                    check_for_collisions,
+                    watch_for_stuck_balls,
+                    check_for_laser_hits,
+                    damage_bricks,
+                    award_points,
+                    add_score,
+                    ramp_up_ball_speed,
+                    // Power-ups fall towards player one, so only drop in solo games
+                    drop_power_ups.run_if(not(is_versus)),
+                    catch_power_ups,
+                    tick_power_ups,
+                    fire_lasers,
+                    check_for_ball_lost,
+                    // Solo games and versus matches are lost and won differently
+                    (lose_life, check_for_level_cleared)
+                        .chain()
+                        .run_if(not(is_versus)),
+                    (lose_lives, check_for_match_over).chain().run_if(is_versus),
+                )
+                    // `chain`ing systems together runs them in order
+                    .chain()
+                    // The simulation is frozen outside of gameplay, including while paused
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Each paddle belongs to a [`Player`]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+#[require(Player)]
+pub struct Paddle;
+
+// Balls are watched in case they get stuck in a loop.
+// Their `Player` is whoever last hit them, who gets the points for the bricks they break.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+#[require(Trajectory, Player)]
+pub struct Ball;
+
+#[derive(Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct Velocity(pub Vec2);
+
+/// Sent whenever a ball bounces off anything
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CollisionEvent {
+    pub ball: Entity,
+    pub collider: ColliderKind,
+    /// Points out of the side of the collider that the ball bounced off
+    pub normal: Vec2,
+    /// How fast the ball was moving into the collider, along the normal
+    pub impact_speed: f32,
+}
+
+/// What a ball bounced off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColliderKind {
+    Wall,
+    Paddle,
+    Brick,
+    UnbreakableBrick,
+    Obstacle,
+}
+
+// Sent when something hits a brick, which then takes damage,
+// with the player whose ball or laser hit it
+#[derive(Event)]
+struct BrickHit(Entity, Player);
+
+// Sent when a brick has been destroyed
+#[derive(Event)]
+struct BrickDestroyed {
+    position: Vec2,
+    kind: BrickKind,
+    // The brick's points, before any combo multiplier
+    score: usize,
+    // Who gets the points
+    player: Player,
+}
+
+// The source of randomness for gameplay, such as power-up drops.
+// It is seeded from `LevelSeed` at the start of each level, so that levels can be replayed.
+#[derive(Resource, Deref, DerefMut)]
+struct GameRng(ChaCha8Rng);
+
+/// The random seed for the next level to be played
+#[derive(Resource, Deref, DerefMut)]
+pub struct LevelSeed(pub u64);
+
+// Everything a ball needs. Newly spawned balls are in play, unless `StuckToPaddle` is added.
+fn ball_bundle(translation: Vec3, velocity: Vec2) -> impl Bundle {
+    (
+        Transform::from_translation(translation).with_scale(Vec2::splat(BALL_DIAMETER).extend(1.)),
+        Ball,
+        Velocity(velocity),
+        StateScoped(InGame),
+    )
+}
+
+/// A ball that is stuck to the paddle follows it around until it is launched
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct StuckToPaddle;
+
+// The speed the ball is launched at, set by the current level.
+// The ball speeds up from there, see `BallSpeedMultiplier`.
+#[derive(Resource, Deref, DerefMut)]
+struct BallSpeed(f32);
+
+// Sent when a ball falls into a kill zone, with the player whose kill zone it was
+#[derive(Event)]
+struct BallLost(Entity, Player);
+
+// Balls that touch this are lost, costing its player a life.
+// Player one's is below their paddle, and in versus matches, player two's is above theirs.
+#[derive(Component)]
+#[require(Player)]
+struct KillZone;
+
+// The kill zone behind `player`'s paddle, as wide as the arena
+fn kill_zone(arena: &ArenaConfig, player: Player) -> impl Bundle {
+    (
+        KillZone,
+        player,
+        Transform {
+            translation: Vec3::new(arena.center_x(), player.mirror_y(arena, arena.bottom), 0.0),
+            scale: Vec3::new(
+                arena.width() + arena.wall_thickness,
+                arena.wall_thickness,
+                1.0,
+            ),
+            ..default()
+        },
+    )
+}
+
+/// Bricks take a number of hits to destroy depending on their kind,
+/// and are worth more points the harder they are to destroy
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Brick {
+    pub kind: BrickKind,
+    pub hit_points: u32,
+    pub score: usize,
+}
+
+impl Brick {
+    fn new(kind: BrickKind) -> Brick {
+        let (hit_points, score) = match kind {
+            BrickKind::Normal => (1, 1),
+            BrickKind::MultiHit => (MULTI_HIT_BRICK_HIT_POINTS, 3),
+            BrickKind::Unbreakable => (0, 0),
+            BrickKind::Explosive => (1, 2),
+        };
+
+        Brick {
+            kind,
+            hit_points,
+            score,
+        }
+    }
+
+    /// Take a hit, returning `true` if that destroyed the brick
+    fn damage(&mut self) -> bool {
+        if !self.kind.is_breakable() {
+            return false;
+        }
+
+        self.hit_points = self.hit_points.saturating_sub(1);
+        self.hit_points == 0
+    }
+
+    fn color(&self) -> Color {
+        match self.kind {
+            BrickKind::Normal => BRICK_COLOR,
+            // Multi-hit bricks fade towards the normal brick color as they take damage
+            BrickKind::MultiHit => BRICK_COLOR
+                .darker(BRICK_DAMAGE_COLOR_STEP * self.hit_points.saturating_sub(1) as f32),
+            BrickKind::Unbreakable => UNBREAKABLE_BRICK_COLOR,
+            BrickKind::Explosive => EXPLOSIVE_BRICK_COLOR,
+        }
+    }
+}
+
+/// Handles to every bundled level, in the order they are played
+#[derive(Resource)]
+pub struct Levels(pub Vec<Handle<Level>>);
+
+/// Index into `Levels` of the level being played
+#[derive(Resource, Deref, DerefMut, Reflect)]
+#[reflect(Resource)]
+pub struct CurrentLevel(pub usize);
+
+// Default must be implemented to define this as a required component for the Wall component below
+#[derive(Component, Default)]
+#[require(ColliderShape)]
+pub struct Collider;
+
+// This is a collection of the components that define a "Wall" in our game
+#[derive(Component)]
+#[require(Transform, Collider)]
+pub struct Wall;
+
+/// Which side of the arena is this wall located on?
+enum WallLocation {
+    Left,
+    Right,
+    Top,
+}
+
+impl WallLocation {
+    /// Location of the *center* of the wall, used in `transform.translation()`
+    fn position(&self, arena: &ArenaConfig) -> Vec2 {
+        let middle_y = (arena.bottom + arena.top) / 2.0;
+        match self {
+            WallLocation::Left => Vec2::new(arena.left, middle_y),
+            WallLocation::Right => Vec2::new(arena.right, middle_y),
+            WallLocation::Top => Vec2::new(arena.center_x(), arena.top),
+        }
+    }
+
+    /// (x, y) dimensions of the wall, used in `transform.scale()`
+    fn size(&self, arena: &ArenaConfig) -> Vec2 {
+        // Make sure we haven't been given an inside-out arena
+        assert!(arena.height() > 0.0);
+        assert!(arena.width() > 0.0);
+
+        match self {
+            WallLocation::Left | WallLocation::Right => {
+                Vec2::new(arena.wall_thickness, arena.height() + arena.wall_thickness)
+            }
+            WallLocation::Top => {
+                Vec2::new(arena.width() + arena.wall_thickness, arena.wall_thickness)
+            }
+        }
+    }
+}
+
+impl Wall {
+    // This "builder method" allows us to reuse logic across our wall entities,
+    // making our code easier to read and less prone to bugs when we change the logic
+    // Notice the use of Transform alongside Wall, overwriting the default value defined for the required component
+    fn new(location: WallLocation, arena: &ArenaConfig) -> (Wall, Transform) {
+        (
+            Wall,
+            Transform {
+                // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
+                // This is used to determine the order of our sprites
+                translation: location.position(arena).extend(0.0),
+                // The z-scale of 2D objects must always be 1.0,
+                // or their ordering will be affected in surprising ways.
+                // See https://github.com/bevyengine/bevy/issues/4149
+                scale: location.size(arena).extend(1.0),
+                ..default()
+            },
+        )
+    }
+}
+
+/// This resource tracks the game's score
+#[derive(Resource, Deref, DerefMut, Reflect)]
+#[reflect(Resource)]
+pub struct Score(pub usize);
+
+/// This resource tracks how many more balls the player can lose before the game is over
+#[derive(Resource, Deref, DerefMut, Reflect)]
+#[reflect(Resource)]
+pub struct Lives(pub u32);
+
+// Add the game's entities to our world
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, arena: Res<ArenaConfig>) {
+    // Levels
+    // The title screen waits for these to finish loading before the game can start
+    commands.insert_resource(Levels(
+        LEVELS.iter().map(|path| asset_server.load(*path)).collect(),
+    ));
+
+    // Walls
+    // The top of the arena depends on the game mode, so is spawned with each level
+    commands.spawn(Wall::new(WallLocation::Left, &arena));
+    commands.spawn(Wall::new(WallLocation::Right, &arena));
+
+    // Kill zone
+    // This sits where the bottom wall would be
+    commands.spawn(kill_zone(&arena, Player::One));
+}
+
+// Spawn the paddle, ball and bricks of the current level, and a paddle and ball for each player
+#[allow(clippy::too_many_arguments)]
+fn spawn_level(
+    mut commands: Commands,
+    level_seed: Res<LevelSeed>,
+    level_assets: Res<Assets<Level>>,
+    levels: Res<Levels>,
+    current_level: Res<CurrentLevel>,
+    custom_level: Option<Res<CustomLevel>>,
+    difficulty: Res<Difficulty>,
+    game_mode: Res<GameMode>,
+    arena: Res<ArenaConfig>,
+) {
+    let handle = match &custom_level {
+        Some(custom_level) => &**custom_level,
+        None => &levels.0[**current_level],
+    };
+    // The title screen only lets the game start once every level has loaded
+    let Some(level) = level_assets.get(handle) else {
+        error!("Level {} has not been loaded", **current_level);
+        return;
+    };
+
+    let players: &[Player] = match *game_mode {
+        GameMode::Solo => &[Player::One],
+        GameMode::Versus => &Player::ALL,
+    };
+    for &player in players {
+        // Paddle
+        let mut paddle = commands.spawn((
+            Transform {
+                translation: Vec3::new(arena.center_x(), player.paddle_y(&arena), 0.0),
+                scale: PADDLE_SIZE.extend(1.0),
+                ..default()
+            },
+            Paddle,
+            player,
+            Collider,
+            StateScoped(InGame),
+        ));
+        // Solo games keep the score and lives in resources, as they carry over between levels
+        if *game_mode == GameMode::Versus {
+            paddle.insert(Standing::default());
+        }
+
+        // Ball
+        commands.spawn((
+            ball_bundle(player.ball_starting_position(&arena), Vec2::ZERO),
+            player,
+            StuckToPaddle,
+        ));
+    }
+
+    // The top of the arena is a wall to bounce off, or in versus matches, player two's kill zone
+    match *game_mode {
+        GameMode::Solo => {
+            commands.spawn((Wall::new(WallLocation::Top, &arena), StateScoped(InGame)));
+        }
+        GameMode::Versus => {
+            commands.spawn((kill_zone(&arena, Player::Two), StateScoped(InGame)));
+        }
+    }
+    commands.insert_resource(BallSpeed(level.ball_speed));
+    commands.insert_resource(BallSpeedMultiplier::new(
+        level.difficulty.unwrap_or(*difficulty).speed_ramp(),
+    ));
+    commands.insert_resource(ActivePowerUps::default());
+    commands.insert_resource(Combo::default());
+    commands.insert_resource(GameRng(ChaCha8Rng::seed_from_u64(**level_seed)));
+    // Forget any input from before the level started, such as the key press that started it
+    commands.insert_resource(PaddleIntent::default());
+    commands.insert_resource(PlayerTwoIntent::default());
+
+    // Bricks
+    // Layouts are validated by the loader, but report rather than panic if one slips through
+    let mut bricks = match level.bricks(&arena) {
+        Ok(bricks) => bricks,
+        Err(error) => {
+            error!("Could not spawn level {:?}: {error}", level.name);
+            return;
+        }
+    };
+
+    if *game_mode == GameMode::Versus {
+        center_bricks(&mut bricks, &arena);
+    }
+
+    for (brick_position, kind) in bricks {
+        let brick = Brick::new(kind);
+
+        // brick
+        commands.spawn((
+            Transform {
+                translation: brick_position.extend(0.0),
+                scale: Vec3::new(BRICK_SIZE.x, BRICK_SIZE.y, 1.0),
+                ..default()
+            },
+            brick,
+            Collider,
+            StateScoped(InGame),
+        ));
+    }
+
+    for obstacle in &level.obstacles {
+        spawn_obstacle(&mut commands, obstacle, &arena);
+    }
+}
+
+fn choose_next_level_seed(mut level_seed: ResMut<LevelSeed>) {
+    **level_seed = rand::random();
+}
+
+// The pause menu is chosen from with the launch bindings too, so resuming shouldn't launch the ball
+fn forget_launch(mut intent: ResMut<PaddleIntent>, mut player_two_intent: ResMut<PlayerTwoIntent>) {
+    intent.launch = false;
+    player_two_intent.launch = false;
+}
+
+fn move_paddle(
+    intent: Res<PaddleIntent>,
+    player_two_intent: Res<PlayerTwoIntent>,
+    mut paddle_query: Query<(&Player, &mut Transform), With<Paddle>>,
+    arena: Res<ArenaConfig>,
+    time: Res<Time>,
+) {
+    let max_distance = PADDLE_SPEED * time.delta_secs();
+
+    for (player, mut paddle_transform) in &mut paddle_query {
+        let intent = match player {
+            Player::One => &*intent,
+            Player::Two => &**player_two_intent,
+        };
+
+        // Calculate the new horizontal paddle position based on player input
+        let distance = match intent.target_x {
+            Some(target_x) => {
+                let max_distance = max_distance * MOUSE_PADDLE_SPEED_SCALE;
+                (target_x - paddle_transform.translation.x).clamp(-max_distance, max_distance)
+            }
+            None => {
+                intent
+                    .direction
+                    .clamp(-MAX_PADDLE_SENSITIVITY, MAX_PADDLE_SENSITIVITY)
+                    * max_distance
+            }
+        };
+        let new_paddle_position = paddle_transform.translation.x + distance;
+
+        // Update the paddle position,
+        // making sure it doesn't cause the paddle to leave the arena.
+        // Power-ups can change the paddle's width, so use its current size.
+        let paddle_half_width = paddle_transform.scale.x / 2.0;
+        let left_bound =
+            arena.left + arena.wall_thickness / 2.0 + paddle_half_width + PADDLE_PADDING;
+        let right_bound =
+            arena.right - arena.wall_thickness / 2.0 - paddle_half_width - PADDLE_PADDING;
+
+        paddle_transform.translation.x = new_paddle_position.clamp(left_bound, right_bound);
+    }
+}
+
+// Balls are moved by `check_for_collisions` instead, so that they can't pass through anything
+fn apply_velocity(mut query: Query<(&mut Transform, &Velocity), Without<Ball>>, time: Res<Time>) {
+    for (mut transform, velocity) in &mut query {
+        transform.translation.x += velocity.x * time.delta_secs();
+        transform.translation.y += velocity.y * time.delta_secs();
+    }
+}
+
+// Move each ball along its velocity for this timestep, bouncing off anything in its way.
+// Each bounce is resolved at the exact time the ball touches a collider,
+// so fast balls can't skip over walls or hit several bricks at once.
+// Only the colliders that the `Broadphase` finds near the ball's path are checked.
+#[allow(clippy::type_complexity)]
+fn check_for_collisions(
+    mut ball_query: Query<
+        (Entity, &mut Velocity, &mut Transform, &mut Player),
+        (With<Ball>, Without<StuckToPaddle>),
+    >,
+    // Of the colliders, only paddles belong to a player
+    collider_query: Query<
+        (
+            Entity,
+            &Transform,
+            &ColliderShape,
+            Option<&Brick>,
+            Option<&Player>,
+            Has<Obstacle>,
+        ),
+        (With<Collider>, Without<Ball>),
+    >,
+    broadphase: Res<Broadphase>,
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut brick_hits: EventWriter<BrickHit>,
+    time: Res<Time>,
+) {
+    // Bricks are despawned after this system, so remember which ones this hit will destroy
+    let mut destroyed_bricks = Vec::new();
+
+    for (ball_entity, mut ball_velocity, mut ball_transform, mut ball_player) in &mut ball_query {
+        let mut remaining_time = time.delta_secs();
+
+        for _ in 0..MAX_BALL_BOUNCES_PER_STEP {
+            let ball =
+                BoundingCircle::new(ball_transform.translation.truncate(), BALL_DIAMETER / 2.);
+            let motion = **ball_velocity * remaining_time;
+
+            // Find the first collider the ball would touch, of those near its path
+            let nearby = broadphase.query(swept_aabb(ball, motion));
+            let first_hit = collider_query
+                .iter_many(&nearby)
+                .filter(|(entity, ..)| !destroyed_bricks.contains(entity))
+                .filter_map(|collider| {
+                    let (_, collider_transform, shape, ..) = collider;
+                    Volume::new(collider_transform, *shape)
+                        .sweep(ball, motion)
+                        .map(|hit| (hit, collider))
+                })
+                .min_by(|(a, _), (b, _)| a.time.total_cmp(&b.time));
+
+            let Some((
+                hit,
+                (collider_entity, collider_transform, _, maybe_brick, paddle_player, is_obstacle),
+            )) = first_hit
+            else {
+                ball_transform.translation += motion.extend(0.0);
+                break;
+            };
+
+            // Move the ball up to the point of contact
+            ball_transform.translation += (motion * hit.time).extend(0.0);
+            remaining_time *= 1.0 - hit.time;
+
+            // Writes a collision event so that other systems can react to the collision
+            let collider = match maybe_brick {
+                Some(brick) if brick.kind.is_breakable() => ColliderKind::Brick,
+                Some(_) => ColliderKind::UnbreakableBrick,
+                None if paddle_player.is_some() => ColliderKind::Paddle,
+                None if is_obstacle => ColliderKind::Obstacle,
+                None => ColliderKind::Wall,
+            };
+            collision_events.write(CollisionEvent {
+                ball: ball_entity,
+                collider,
+                normal: hit.normal,
+                impact_speed: -ball_velocity.dot(hit.normal),
+            });
+
+            // Bricks take damage from each hit
+            if let Some(brick) = maybe_brick {
+                brick_hits.write(BrickHit(collider_entity, *ball_player));
+
+                if brick.kind.is_breakable() && brick.hit_points <= 1 {
+                    destroyed_bricks.push(collider_entity);
+                }
+            }
+
+            // Landing on the front of the paddle lets the player aim the ball.
+            // Player two's paddle faces down, so their bounces are upside down.
+            if let Some(&player) = paddle_player {
+                *ball_player = player;
+
+                let front = match player {
+                    Player::One => Collision::Top,
+                    Player::Two => Collision::Bottom,
+                };
+                if hit.normal == front.normal() {
+                    let paddle_half_width = collider_transform.scale.x / 2.;
+                    let offset = (ball_transform.translation.x - collider_transform.translation.x)
+                        / paddle_half_width;
+                    **ball_velocity = paddle_bounce(offset, ball_velocity.length())
+                        * Vec2::new(1.0, player.forward());
+                    continue;
+                }
+            }
+
+            // Reflect the ball's velocity off the surface it hit.
+            // `sweep` only reports hits where the ball is moving into that surface,
+            // so this always sends the ball away from the collider.
+            **ball_velocity = ball_velocity.reflect(hit.normal);
+        }
+    }
+}
+
+// Apply the damage from every brick hit this step.
+// Bricks that run out of hit points are despawned and reported, for `award_points` to score,
+// and explosive bricks damage the bricks around them, which can set off other explosive bricks.
+fn damage_bricks(
+    mut commands: Commands,
+    mut brick_hits: EventReader<BrickHit>,
+    mut brick_query: Query<(Entity, &Transform, &mut Brick)>,
+    mut destroyed_events: EventWriter<BrickDestroyed>,
+) {
+    let mut pending_hits: VecDeque<(Entity, Player)> = brick_hits
+        .read()
+        .map(|BrickHit(brick, player)| (*brick, *player))
+        .collect();
+    let mut destroyed_bricks = Vec::new();
+
+    while let Some((brick_entity, player)) = pending_hits.pop_front() {
+        if destroyed_bricks.contains(&brick_entity) {
+            continue;
+        }
+
+        let Ok((_, brick_transform, mut brick)) = brick_query.get_mut(brick_entity) else {
+            continue;
+        };
+
+        if !brick.damage() {
+            continue;
+        }
+
+        let position = brick_transform.translation.truncate();
+        let kind = brick.kind;
+        commands.entity(brick_entity).despawn();
+        destroyed_bricks.push(brick_entity);
+        destroyed_events.write(BrickDestroyed {
+            position,
+            kind,
+            score: brick.score,
+            player,
+        });
+
+        // Whoever set off the explosion gets the points for what it destroys
+        if kind == BrickKind::Explosive {
+            let in_range = |transform: &Transform| {
+                (transform.translation.truncate() - position)
+                    .abs()
+                    .cmple(EXPLOSION_RANGE)
+                    .all()
+            };
+            pending_hits.extend(
+                brick_query
+                    .iter()
+                    .filter(|(_, transform, _)| in_range(transform))
+                    .map(|(entity, ..)| (entity, player)),
+            );
+        }
+    }
+}
+
+// The velocity of a ball bouncing off the top of the paddle.
+// `offset` is where the ball hit the paddle, from -1.0 at its left edge to 1.0 at its right edge.
+// Hits further from the center send the ball off at a steeper angle, keeping its speed.
+fn paddle_bounce(offset: f32, speed: f32) -> Vec2 {
+    let max_horizontal = (1.0 - MIN_BALL_VERTICAL_SPEED_FRACTION.powi(2)).sqrt();
+    let horizontal = offset.clamp(-1.0, 1.0) * max_horizontal;
+    let vertical = (1.0 - horizontal * horizontal).sqrt();
+
+    Vec2::new(horizontal, vertical) * speed
+}
+
+// Keep balls that haven't been launched yet on their player's paddle
+#[allow(clippy::type_complexity)]
+fn follow_paddle(
+    paddle_query: Query<(&Player, &Transform), With<Paddle>>,
+    mut ball_query: Query<(&Player, &mut Transform), (With<StuckToPaddle>, Without<Paddle>)>,
+    arena: Res<ArenaConfig>,
+) {
+    for (player, mut ball_transform) in &mut ball_query {
+        let Some((_, paddle_transform)) = paddle_query.iter().find(|(owner, _)| *owner == player)
+        else {
+            continue;
+        };
+
+        ball_transform.translation.x = paddle_transform.translation.x;
+        ball_transform.translation.y = player.ball_starting_position(&arena).y;
+    }
+}
+
+// Launch any balls stuck to a paddle when its player asks to
+fn launch_ball(
+    mut commands: Commands,
+    mut intent: ResMut<PaddleIntent>,
+    mut player_two_intent: ResMut<PlayerTwoIntent>,
+    ball_speed: Res<BallSpeed>,
+    mut ball_query: Query<(Entity, &mut Velocity, &Player), With<StuckToPaddle>>,
+) {
+    let launches = [
+        std::mem::take(&mut intent.launch),
+        std::mem::take(&mut player_two_intent.launch),
+    ];
+
+    for (ball, mut velocity, player) in &mut ball_query {
+        if !launches[player.index()] {
+            continue;
+        }
+
+        **velocity =
+            INITIAL_BALL_DIRECTION.normalize() * Vec2::new(1.0, player.forward()) * **ball_speed;
+        commands.entity(ball).remove::<StuckToPaddle>();
+    }
+}
+
+fn check_for_ball_lost(
+    ball_query: Query<(Entity, &Transform), With<Ball>>,
+    kill_zone_query: Query<(&Transform, &Player), With<KillZone>>,
+    mut ball_lost_events: EventWriter<BallLost>,
+) {
+    for (kill_zone_transform, &player) in &kill_zone_query {
+        let kill_zone = Aabb2d::new(
+            kill_zone_transform.translation.truncate(),
+            kill_zone_transform.scale.truncate() / 2.,
+        );
+
+        for (ball, ball_transform) in &ball_query {
+            let ball_center = ball_transform.translation.truncate();
+
+            // A fast ball may have gone straight through the kill zone
+            let past_kill_zone = match player {
+                Player::One => ball_center.y < kill_zone.min.y,
+                Player::Two => ball_center.y > kill_zone.max.y,
+            };
+            if past_kill_zone
+                || BoundingCircle::new(ball_center, BALL_DIAMETER / 2.).intersects(&kill_zone)
+            {
+                ball_lost_events.write(BallLost(ball, player));
+            }
+        }
+    }
+}
+
+// Losing the last ball in play costs a life, and the game is over when there are none left
+#[allow(clippy::too_many_arguments)]
+fn lose_life(
+    mut commands: Commands,
+    mut ball_lost_events: EventReader<BallLost>,
+    mut lives: ResMut<Lives>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    mut speed_multiplier: ResMut<BallSpeedMultiplier>,
+    mut combo: ResMut<Combo>,
+    mut ball_query: Query<(Entity, &mut Transform, &mut Velocity), With<Ball>>,
+    arena: Res<ArenaConfig>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let lost_balls: Vec<Entity> = ball_lost_events
+        .read()
+        .map(|BallLost(ball, _)| *ball)
+        .collect();
+    let Some(&last_ball) = lost_balls.first() else {
+        return;
+    };
+
+    // With multi-ball, there may be other balls still in play
+    if ball_query
+        .iter()
+        .any(|(ball, ..)| !lost_balls.contains(&ball))
+    {
+        for ball in lost_balls {
+            commands.entity(ball).despawn();
+        }
+        return;
+    }
+
+    **lives = lives.saturating_sub(1);
+
+    if **lives == 0 {
+        next_state.set(GameState::GameOver);
+        return;
+    }
+
+    // Give one ball back to the player, waiting on the paddle to be launched again
+    for &ball in &lost_balls[1..] {
+        commands.entity(ball).despawn();
+    }
+    if let Ok((_, mut ball_transform, mut ball_velocity)) = ball_query.get_mut(last_ball) {
+        ball_transform.translation = arena.ball_starting_position();
+        **ball_velocity = Vec2::ZERO;
+        commands.entity(last_ball).insert(StuckToPaddle);
+    }
+
+    // Power-ups, the ball's speed-up and the combo don't carry over to the next life
+    *active_power_ups = ActivePowerUps::default();
+    speed_multiplier.reset();
+    combo.reset();
+}
+
+// The level is cleared once every brick that can be destroyed has been
+fn check_for_level_cleared(bricks: Query<&Brick>, mut next_state: ResMut<NextState<GameState>>) {
+    if bricks.iter().all(|brick| !brick.kind.is_breakable()) {
+        next_state.set(GameState::LevelCleared);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // Just enough of the game to move balls around with `check_for_collisions`
+    fn collision_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Broadphase>()
+            .add_observer(add_to_broadphase)
+            .add_observer(remove_from_broadphase)
+            .add_event::<CollisionEvent>()
+            .add_event::<BrickHit>()
+            .add_event::<BrickDestroyed>()
+            .add_systems(
+                Update,
+                (update_broadphase, check_for_collisions, damage_bricks).chain(),
+            );
+        app
+    }
+
+    fn spawn_ball(app: &mut App, position: Vec2, velocity: Vec2) -> Entity {
+        app.world_mut()
+            .spawn((
+                Ball,
+                Velocity(velocity),
+                Transform::from_translation(position.extend(1.0)),
+            ))
+            .id()
+    }
+
+    fn spawn_collider(app: &mut App, position: Vec2, size: Vec2) -> Entity {
+        app.world_mut()
+            .spawn((
+                Transform::from_translation(position.extend(0.0)).with_scale(size.extend(1.0)),
+                Collider,
+            ))
+            .id()
+    }
+
+    // Advance by a single, deliberately huge, timestep
+    fn step(app: &mut App, seconds: f32) {
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    fn ball_state(app: &mut App, ball: Entity) -> (Vec2, Vec2) {
+        let ball = app.world().entity(ball);
+        (
+            ball.get::<Transform>().unwrap().translation.truncate(),
+            **ball.get::<Velocity>().unwrap(),
+        )
+    }
+
+    // The points for every brick destroyed so far, before any combo or bonus
+    fn destroyed_score(app: &App) -> usize {
+        let events = app.world().resource::<Events<BrickDestroyed>>();
+        events
+            .get_cursor()
+            .read(events)
+            .map(|destroyed| destroyed.score)
+            .sum()
+    }
+
+    #[test]
+    fn fast_ball_does_not_tunnel_through_wall() {
+        let mut app = collision_app();
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::new(4000.0, 0.0));
+        spawn_collider(
+            &mut app,
+            Vec2::new(100.0, 0.0),
+            Vec2::new(ArenaConfig::default().wall_thickness, 200.0),
+        );
+
+        // The ball would move 400 units, far past the wall.
+        // Instead it touches the wall after 80 and travels the remaining 320 back.
+        step(&mut app, 0.1);
+
+        let (position, velocity) = ball_state(&mut app, ball);
+        assert!((position.x + 240.0).abs() < 1e-2, "{position}");
+        assert_eq!(velocity, Vec2::new(-4000.0, 0.0));
+        assert_eq!(app.world().resource::<Events<CollisionEvent>>().len(), 1);
+    }
+
+    #[test]
+    fn collision_events_say_what_was_hit_and_how_hard() {
+        let mut app = collision_app();
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::new(300.0, 400.0));
+        let brick = spawn_collider(&mut app, Vec2::new(0.0, 100.0), BRICK_SIZE);
+        app.world_mut()
+            .entity_mut(brick)
+            .insert(Brick::new(BrickKind::Unbreakable));
+
+        step(&mut app, 0.25);
+
+        let events = app.world().resource::<Events<CollisionEvent>>();
+        let collisions: Vec<CollisionEvent> = events.get_cursor().read(events).copied().collect();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].ball, ball);
+        assert_eq!(collisions[0].collider, ColliderKind::UnbreakableBrick);
+        assert_eq!(collisions[0].normal, Vec2::NEG_Y);
+        // Only the speed into the brick counts, not the speed along it
+        assert_eq!(collisions[0].impact_speed, 400.0);
+    }
+
+    #[test]
+    fn fast_ball_only_hits_the_first_brick() {
+        let mut app = collision_app();
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::new(0.0, 3000.0));
+        let near_brick = spawn_collider(&mut app, Vec2::new(0.0, 100.0), BRICK_SIZE);
+        let far_brick = spawn_collider(&mut app, Vec2::new(0.0, 135.0), BRICK_SIZE);
+        for brick in [near_brick, far_brick] {
+            app.world_mut()
+                .entity_mut(brick)
+                .insert(Brick::new(BrickKind::Normal));
+        }
+
+        step(&mut app, 0.1);
+
+        let (_, velocity) = ball_state(&mut app, ball);
+        assert!(velocity.y < 0.0);
+        assert!(app.world().get_entity(near_brick).is_err());
+        assert!(app.world().get_entity(far_brick).is_ok());
+        assert_eq!(destroyed_score(&app), 1);
+    }
+
+    #[test]
+    fn ball_bounces_several_times_in_one_step() {
+        let mut app = collision_app();
+        let ball = spawn_ball(&mut app, Vec2::ZERO, Vec2::new(1000.0, 0.0));
+        let wall_size = Vec2::new(ArenaConfig::default().wall_thickness, 200.0);
+        spawn_collider(&mut app, Vec2::new(-50.0, 0.0), wall_size);
+        spawn_collider(&mut app, Vec2::new(50.0, 0.0), wall_size);
+
+        // The ball's center can move between -30 and 30.
+        // Moving 200 units takes it right 30, left 60, right 60 and finally left 50.
+        step(&mut app, 0.2);
+
+        let (position, velocity) = ball_state(&mut app, ball);
+        assert!((position.x + 20.0).abs() < 1e-2, "{position}");
+        assert_eq!(velocity, Vec2::new(-1000.0, 0.0));
+        assert_eq!(app.world().resource::<Events<CollisionEvent>>().len(), 3);
+    }
+
+    #[test]
+    fn explosive_bricks_damage_their_neighbours() {
+        let mut app = collision_app();
+        spawn_ball(&mut app, Vec2::ZERO, Vec2::new(0.0, 400.0));
+
+        let step_x = BRICK_SIZE.x + GAP_BETWEEN_BRICKS;
+        let step_y = BRICK_SIZE.y + GAP_BETWEEN_BRICKS;
+        let spawn_brick = |app: &mut App, column: f32, row: f32, kind| {
+            let position = Vec2::new(column * step_x, 100.0 + row * step_y);
+            let brick = spawn_collider(app, position, BRICK_SIZE);
+            app.world_mut().entity_mut(brick).insert(Brick::new(kind));
+            brick
+        };
+
+        // The ball hits the explosive brick in the middle, which sets off the one to its right
+        let hit = spawn_brick(&mut app, 0.0, 0.0, BrickKind::Explosive);
+        let chained = spawn_brick(&mut app, 1.0, 0.0, BrickKind::Explosive);
+        let caught_by_chain = spawn_brick(&mut app, 2.0, 1.0, BrickKind::Normal);
+        let neighbour = spawn_brick(&mut app, -1.0, 1.0, BrickKind::Normal);
+        let tough_neighbour = spawn_brick(&mut app, -1.0, -1.0, BrickKind::MultiHit);
+        let unbreakable_neighbour = spawn_brick(&mut app, -1.0, 0.0, BrickKind::Unbreakable);
+        let out_of_range = spawn_brick(&mut app, -2.0, 0.0, BrickKind::Normal);
+
+        step(&mut app, 0.25);
+
+        let world = app.world();
+        for destroyed in [hit, chained, caught_by_chain, neighbour] {
+            assert!(world.get_entity(destroyed).is_err());
+        }
+        for survivor in [tough_neighbour, unbreakable_neighbour, out_of_range] {
+            assert!(world.get_entity(survivor).is_ok());
+        }
+        assert_eq!(
+            world.get::<Brick>(tough_neighbour).unwrap().hit_points,
+            MULTI_HIT_BRICK_HIT_POINTS - 1
+        );
+        // Two explosive bricks and two normal bricks
+        assert_eq!(destroyed_score(&app), 2 + 2 + 1 + 1);
+    }
+
+    #[test]
+    fn brick_hit_points() {
+        let mut normal = Brick::new(BrickKind::Normal);
+        assert!(normal.damage());
+
+        let mut multi_hit = Brick::new(BrickKind::MultiHit);
+        let undamaged_color = multi_hit.color();
+        for _ in 1..MULTI_HIT_BRICK_HIT_POINTS {
+            assert!(!multi_hit.damage());
+        }
+        assert_ne!(multi_hit.color(), undamaged_color);
+        assert!(multi_hit.damage());
+
+        let mut unbreakable = Brick::new(BrickKind::Unbreakable);
+        for _ in 0..10 {
+            assert!(!unbreakable.damage());
+        }
+    }
+
+    #[test]
+    fn paddle_bounce_from_center_goes_straight_up() {
+        let velocity = paddle_bounce(0.0, 400.0);
+        assert!(velocity.x.abs() < 1e-3);
+        assert!((velocity.y - 400.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn paddle_bounce_angle_follows_offset() {
+        let left = paddle_bounce(-0.5, 400.0);
+        let right = paddle_bounce(0.5, 400.0);
+        let far_right = paddle_bounce(0.9, 400.0);
+
+        assert!(left.x < 0.0 && right.x > 0.0);
+        assert!(far_right.x > right.x);
+        assert!((left.x + right.x).abs() < 1e-3);
+    }
+
+    #[test]
+    fn paddle_bounce_keeps_speed_and_minimum_vertical_component() {
+        for offset in [-3.0, -1.0, -0.3, 0.0, 0.7, 1.0, 3.0] {
+            let velocity = paddle_bounce(offset, 400.0);
+            assert!((velocity.length() - 400.0).abs() < 1e-2, "{offset}");
+            assert!(
+                velocity.y >= 400.0 * MIN_BALL_VERTICAL_SPEED_FRACTION - 1e-2,
+                "{offset}"
+            );
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! The pause menu, and the buttons that it, the settings screen and the level-select screen
+//! are made of.
+//!
+//! Menus are Bevy UI buttons. They can be clicked, or moved through with the arrow keys
+//! or a gamepad's d-pad and chosen with Enter or the launch bindings.
+//! The focused button is highlighted, and hovering over a button focuses it.
+
+use bevy::{ecs::spawn::SpawnIter, prelude::*};
+
+use crate::{
+    TEXT_COLOR,
+    editor::TestPlay,
+    input::MenuInput,
+    level::CustomLevel,
+    save_game::{can_save, save_to_slot},
+    settings::Setting,
+    state::{GameState, screen},
+    versus::GameMode,
+};
+
+const BUTTON_WIDTH: f32 = 520.0;
+const BUTTON_FONT_SIZE: f32 = 25.0;
+const BUTTON_COLOR: Color = Color::srgb(0.8, 0.8, 0.85);
+const FOCUSED_BUTTON_COLOR: Color = Color::srgb(1.0, 0.8, 0.55);
+
+/// Which screen of the pause menu is showing, while the game is paused
+#[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[source(GameState = GameState::Paused)]
+#[states(scoped_entities)]
+pub enum PauseMenu {
+    #[default]
+    Main,
+    /// Changing the volume, difficulty, paddle sensitivity and display settings
+    Settings,
+}
+
+/// Adds the pause menu, and the focus and choices of whichever menu is showing
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_sub_state::<PauseMenu>()
+            .init_resource::<MenuFocus>()
+            .add_event::<MenuChoice>()
+            .add_systems(OnEnter(PauseMenu::Main), (reset_focus, spawn_pause_menu))
+            .add_systems(OnEnter(PauseMenu::Settings), reset_focus)
+            .add_systems(OnEnter(GameState::LevelSelect), reset_focus)
+            .add_systems(
+                Update,
+                (
+                    move_focus,
+                    highlight_focus,
+                    choose_menu_item,
+                    use_pause_menu.run_if(in_state(PauseMenu::Main)),
+                    back_from_settings.run_if(in_state(PauseMenu::Settings)),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Paused).or(in_state(GameState::LevelSelect))),
+            );
+    }
+}
+
+/// What choosing a menu item does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    Resume,
+    Restart,
+    OpenSettings,
+    SaveAndQuit,
+    Quit,
+    Change(Setting),
+    /// Start a new game from this level of the campaign
+    PlayLevel(usize),
+    Back,
+}
+
+/// A button in a menu, in order from the top
+#[derive(Component, Debug)]
+pub struct MenuItem {
+    index: usize,
+    pub action: MenuAction,
+}
+
+/// Sent when a menu item is chosen, or when left or right is pressed on it
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MenuChoice {
+    pub action: MenuAction,
+    /// 0 when the item was chosen, or -1 or 1 for left or right
+    pub step: i32,
+}
+
+/// The index of the highlighted menu item
+#[derive(Resource, Default)]
+struct MenuFocus(usize);
+
+/// A column of buttons, one for each action, to go under a [`screen`]'s heading
+pub(crate) fn menu(items: Vec<(MenuAction, String)>) -> impl Bundle {
+    let buttons = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, (action, label))| {
+            (
+                Button,
+                MenuItem { index, action },
+                Node {
+                    width: Val::Px(BUTTON_WIDTH),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                BackgroundColor(BUTTON_COLOR),
+                Text::new(label),
+                TextFont {
+                    font_size: BUTTON_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                TextLayout::new_with_justify(JustifyText::Center),
+            )
+        });
+
+    (
+        Node {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(10.0),
+            ..default()
+        },
+        Children::spawn(SpawnIter(buttons)),
+    )
+}
+
+fn reset_focus(mut focus: ResMut<MenuFocus>) {
+    focus.0 = 0;
+}
+
+fn move_focus(
+    menu_input: MenuInput,
+    hovered: Query<(&Interaction, &MenuItem), Changed<Interaction>>,
+    items: Query<&MenuItem>,
+    mut focus: ResMut<MenuFocus>,
+) {
+    for (interaction, item) in &hovered {
+        if *interaction != Interaction::None {
+            focus.0 = item.index;
+        }
+    }
+
+    let count = items.iter().count() as i32;
+    let step = menu_input.vertical();
+    if step != 0 && count > 0 {
+        focus.0 = (focus.0 as i32 + step).rem_euclid(count) as usize;
+    }
+}
+
+fn highlight_focus(focus: Res<MenuFocus>, mut items: Query<(&MenuItem, &mut BackgroundColor)>) {
+    for (item, mut background) in &mut items {
+        let color = if item.index == focus.0 {
+            FOCUSED_BUTTON_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+        background.set_if_neq(BackgroundColor(color));
+    }
+}
+
+fn choose_menu_item(
+    menu_input: MenuInput,
+    focus: Res<MenuFocus>,
+    clicked: Query<(&Interaction, &MenuItem), Changed<Interaction>>,
+    items: Query<&MenuItem>,
+    mut choices: EventWriter<MenuChoice>,
+) {
+    for (interaction, item) in &clicked {
+        if *interaction == Interaction::Pressed {
+            choices.write(MenuChoice {
+                action: item.action,
+                step: 0,
+            });
+        }
+    }
+
+    let Some(focused) = items.iter().find(|item| item.index == focus.0) else {
+        return;
+    };
+    let step = if menu_input.select() {
+        0
+    } else {
+        match menu_input.horizontal() {
+            0 => return,
+            step => step,
+        }
+    };
+    choices.write(MenuChoice {
+        action: focused.action,
+        step,
+    });
+}
+
+// The pause menu's items are only chosen, not changed with left and right
+fn use_pause_menu(
+    mut commands: Commands,
+    mut choices: EventReader<MenuChoice>,
+    test_play: Option<Res<TestPlay>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu: ResMut<NextState<PauseMenu>>,
+) {
+    for choice in choices.read().filter(|choice| choice.step == 0) {
+        match choice.action {
+            MenuAction::Resume => next_state.set(GameState::Playing),
+            MenuAction::Restart => next_state.set(GameState::Restarting),
+            MenuAction::OpenSettings => next_menu.set(PauseMenu::Settings),
+            MenuAction::SaveAndQuit => {
+                commands.queue(save_to_slot);
+                next_state.set(GameState::Title);
+            }
+            // Test plays go back to the level being edited
+            MenuAction::Quit if test_play.is_some() => next_state.set(GameState::Editor),
+            MenuAction::Quit => next_state.set(GameState::Title),
+            // These are on the other screens, see `settings::change_settings`
+            // and `campaign::choose_level`
+            MenuAction::Change(_) | MenuAction::PlayLevel(_) | MenuAction::Back => {}
+        }
+    }
+}
+
+fn back_from_settings(menu_input: MenuInput, mut next_menu: ResMut<NextState<PauseMenu>>) {
+    if menu_input.back() {
+        next_menu.set(PauseMenu::Main);
+    }
+}
+
+fn spawn_pause_menu(
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    custom_level: Option<Res<CustomLevel>>,
+    test_play: Option<Res<TestPlay>>,
+) {
+    let mut items = vec![(MenuAction::Resume, "Resume".to_string())];
+    // Versus matches keep standings that the start of a level doesn't capture
+    if *game_mode == GameMode::Solo {
+        items.push((MenuAction::Restart, "Restart level".to_string()));
+    }
+    items.push((MenuAction::OpenSettings, "Settings".to_string()));
+    if can_save(game_mode, custom_level) {
+        items.push((MenuAction::SaveAndQuit, "Save and quit".to_string()));
+    }
+    let quit = if test_play.is_some() {
+        "Quit to editor"
+    } else {
+        "Quit to title screen"
+    };
+    items.push((MenuAction::Quit, quit.to_string()));
+
+    commands.spawn(screen(PauseMenu::Main, "Paused", menu(items)));
+}
@@ -0,0 +1,213 @@
+//! Obstacles that levels can place in the arena: bars and discs for the ball to bounce off,
+//! which can [`Patrol`] along a path or [`Spin`] in place. Bricks can be made to move the same way.
+//!
+//! Moving colliders are moved before the balls, so a ball is always swept against where
+//! they are this step. Anything that moves into a ball pushes it out of the way.
+
+use std::iter;
+
+use bevy::{math::bounding::BoundingCircle, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BALL_DIAMETER, BRICK_SIZE, Ball, Brick, Collider, StuckToPaddle, Velocity,
+    arena::ArenaConfig,
+    collision::{ColliderShape, Volume},
+    level::BrickKind,
+    state::InGame,
+};
+
+/// Something for the ball to bounce off, that isn't a wall, paddle or brick
+#[derive(Component, Default)]
+#[require(Transform, Collider)]
+pub struct Obstacle;
+
+/// Moves through `points` in turn at a constant speed, going back to the first after the last
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Patrol {
+    points: Vec<Vec2>,
+    /// In units per second
+    speed: f32,
+    // The index of the point being moved towards
+    next: usize,
+}
+
+impl Patrol {
+    /// Patrols `points` at `speed`, starting at the first point
+    pub fn new(points: Vec<Vec2>, speed: f32) -> Patrol {
+        Patrol {
+            next: 1 % points.len().max(1),
+            points,
+            speed,
+        }
+    }
+
+    // Move `distance` from `position` towards the next point,
+    // carrying on to the one after if it is reached partway
+    fn advance(&mut self, mut position: Vec2, mut distance: f32) -> Vec2 {
+        // Each point is passed at most once a step, however close together they are
+        for _ in 0..self.points.len() {
+            let to_next = self.points[self.next] - position;
+            let length = to_next.length();
+            if length > distance {
+                return position + to_next / length * distance;
+            }
+
+            position = self.points[self.next];
+            distance -= length;
+            self.next = (self.next + 1) % self.points.len();
+        }
+
+        position
+    }
+}
+
+/// Turns at a constant rate, in radians per second, counterclockwise
+#[derive(Component, Debug, Clone, Copy, PartialEq, Deref, Reflect)]
+#[reflect(Component)]
+pub struct Spin(pub f32);
+
+/// The shape of an obstacle in a level file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ObstacleShape {
+    /// A box this wide and tall
+    Bar(f32, f32),
+    /// A circle this wide
+    Disc(f32),
+    /// A brick of this kind, which is scored and destroyed like the bricks in the layout
+    Brick(BrickKind),
+}
+
+/// An obstacle in a level file. Positions are relative to the center of the arena.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelObstacle {
+    pub shape: ObstacleShape,
+    pub position: Vec2,
+    /// In degrees, counterclockwise
+    #[serde(default)]
+    pub angle: f32,
+    /// Points to move to in turn, before going back to `position` and starting again
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<Vec2>,
+    /// How fast to move along the path, in units per second
+    #[serde(default)]
+    pub speed: f32,
+    /// How fast to turn, in degrees per second, counterclockwise
+    #[serde(default)]
+    pub spin: f32,
+}
+
+impl LevelObstacle {
+    /// Where this obstacle starts, followed by its path, in `arena`
+    pub fn points(&self, arena: &ArenaConfig) -> impl Iterator<Item = Vec2> {
+        let center = arena.center();
+        iter::once(self.position)
+            .chain(self.path.iter().copied())
+            .map(move |point| center + point)
+    }
+
+    fn size(&self) -> Vec2 {
+        match self.shape {
+            ObstacleShape::Bar(width, height) => Vec2::new(width, height),
+            ObstacleShape::Disc(diameter) => Vec2::splat(diameter),
+            ObstacleShape::Brick(_) => BRICK_SIZE,
+        }
+    }
+}
+
+// Spawn an obstacle for the level being played
+pub(crate) fn spawn_obstacle(
+    commands: &mut Commands,
+    obstacle: &LevelObstacle,
+    arena: &ArenaConfig,
+) {
+    let points: Vec<Vec2> = obstacle.points(arena).collect();
+    let mut entity = commands.spawn((
+        Transform {
+            translation: points[0].extend(0.0),
+            rotation: Quat::from_rotation_z(obstacle.angle.to_radians()),
+            scale: obstacle.size().extend(1.0),
+        },
+        StateScoped(InGame),
+    ));
+
+    match obstacle.shape {
+        ObstacleShape::Bar(..) => entity.insert(Obstacle),
+        ObstacleShape::Disc(_) => entity.insert((Obstacle, ColliderShape::Circle)),
+        ObstacleShape::Brick(kind) => entity.insert((Brick::new(kind), Collider)),
+    };
+
+    if points.len() > 1 && obstacle.speed > 0.0 {
+        entity.insert(Patrol::new(points, obstacle.speed));
+    }
+    if obstacle.spin != 0.0 {
+        entity.insert(Spin(obstacle.spin.to_radians()));
+    }
+}
+
+pub(crate) fn patrol(mut query: Query<(&mut Transform, &mut Patrol)>, time: Res<Time>) {
+    for (mut transform, mut patrol) in &mut query {
+        let distance = patrol.speed * time.delta_secs();
+        let position = patrol.advance(transform.translation.truncate(), distance);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+pub(crate) fn spin(mut query: Query<(&mut Transform, &Spin)>, time: Res<Time>) {
+    for (mut transform, spin) in &mut query {
+        transform.rotate_z(**spin * time.delta_secs());
+    }
+}
+
+// Anything that moved into a ball pushes it out, and sends it away if it was heading further in
+#[allow(clippy::type_complexity)]
+pub(crate) fn push_balls_out_of_the_way(
+    moving_query: Query<
+        (&Transform, &ColliderShape),
+        (With<Collider>, Or<(With<Patrol>, With<Spin>)>),
+    >,
+    mut ball_query: Query<
+        (&mut Transform, &mut Velocity),
+        (With<Ball>, Without<Collider>, Without<StuckToPaddle>),
+    >,
+) {
+    for (mut ball_transform, mut ball_velocity) in &mut ball_query {
+        for (transform, shape) in &moving_query {
+            let ball =
+                BoundingCircle::new(ball_transform.translation.truncate(), BALL_DIAMETER / 2.);
+            let Some((normal, depth)) = Volume::new(transform, *shape).penetration(ball) else {
+                continue;
+            };
+
+            ball_transform.translation += (normal * depth).extend(0.0);
+            if ball_velocity.dot(normal) < 0.0 {
+                **ball_velocity = ball_velocity.reflect(normal);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patrols_loop_through_their_points() {
+        let points = vec![Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0)];
+        let mut patrol = Patrol::new(points, 15.0);
+
+        // Round the first corner
+        let position = patrol.advance(Vec2::ZERO, 15.0);
+        assert!(position.abs_diff_eq(Vec2::new(10.0, 5.0), 1e-5));
+
+        // Round the last corner, and back towards the start
+        let position = patrol.advance(position, 15.0);
+        let expected = Vec2::splat(10.0 - 10.0 * std::f32::consts::FRAC_1_SQRT_2);
+        assert!(position.abs_diff_eq(expected, 1e-4), "{position}");
+
+        // A patrol with a single point stays there
+        let mut patrol = Patrol::new(vec![Vec2::ONE], 15.0);
+        assert_eq!(patrol.advance(Vec2::ONE, 15.0), Vec2::ONE);
+    }
+}
@@ -0,0 +1,69 @@
+//! Files the game keeps between runs, such as settings and high scores.
+//!
+//! Everything is stored as RON. A file that can't be read or parsed is treated as missing,
+//! with a warning, so a corrupt file never stops the game from starting.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Serialize, de::DeserializeOwned};
+
+const DIRECTORY_NAME: &str = "breakout";
+
+/// The path of `file` in the user's config directory, if the platform has one
+pub fn config_path(file: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(DIRECTORY_NAME).join(file))
+}
+
+/// The path of `file` in the user's data directory, if the platform has one
+pub fn data_path(file: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(DIRECTORY_NAME).join(file))
+}
+
+/// Read a value from `path`, or `None` if the file is missing or can't be parsed
+pub fn load<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(error) => {
+            warn!("Could not read {}: {error}", path.display());
+            return None;
+        }
+    };
+
+    match ron::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Ignoring {}, which is corrupt: {error}", path.display());
+            None
+        }
+    }
+}
+
+/// Write `value` to `path`, creating its directory if needed. Failures are only logged.
+pub fn save<T: Serialize>(path: &Path, value: &T) {
+    let result = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(std::io::Error::other)
+        .and_then(|contents| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, contents)
+        });
+
+    if let Err(error) = result {
+        warn!("Could not save {}: {error}", path.display());
+    }
+}
+
+/// Delete the file at `path`, if there is one. Failures are only logged.
+pub fn remove(path: &Path) {
+    match fs::remove_file(path) {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+        Err(error) => warn!("Could not remove {}: {error}", path.display()),
+    }
+}
@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+
+use bevy::{math::bounding::IntersectsVolume, prelude::*};
+use rand::{Rng, seq::SliceRandom};
+
+use crate::{
+    BALL_COLOR, Ball, BallSpeed, Brick, BrickDestroyed, BrickHit, Collider, GameRng, PADDLE_COLOR,
+    PADDLE_SIZE, Paddle, StuckToPaddle, Velocity,
+    arena::ArenaConfig,
+    ball_bundle,
+    collision::{ColliderShape, Volume, bounding_box},
+    difficulty::BallSpeedMultiplier,
+    state::InGame,
+    versus::Player,
+};
+
+// Chance of a destroyed brick dropping a power-up
+const POWER_UP_DROP_CHANCE: f64 = 0.15;
+const POWER_UP_SIZE: Vec2 = Vec2::new(40.0, 15.0);
+const POWER_UP_FALL_SPEED: f32 = 150.0;
+// How long timed power-ups last, in seconds.
+// Catching the same power-up again restarts its timer.
+const POWER_UP_DURATION: f32 = 10.0;
+
+// Each multi-ball power-up splits every ball into three, spread out by this angle
+const MULTI_BALL_SPREAD: f32 = std::f32::consts::FRAC_PI_6;
+const WIDE_PADDLE_SCALE: f32 = 1.5;
+const SLOW_BALL_SCALE: f32 = 0.6;
+
+const LASER_SIZE: Vec2 = Vec2::new(4.0, 16.0);
+const LASER_SPEED: f32 = 800.0;
+// Seconds between each pair of laser shots
+const LASER_FIRE_INTERVAL: f32 = 0.4;
+
+const SLOW_BALL_COLOR: Color = Color::srgb(0.4, 0.8, 0.4);
+pub(crate) const LASER_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    MultiBall,
+    WidePaddle,
+    SlowBall,
+    Laser,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::MultiBall,
+        PowerUpKind::WidePaddle,
+        PowerUpKind::SlowBall,
+        PowerUpKind::Laser,
+    ];
+
+    pub(crate) fn color(self) -> Color {
+        match self {
+            PowerUpKind::MultiBall => BALL_COLOR,
+            PowerUpKind::WidePaddle => PADDLE_COLOR,
+            PowerUpKind::SlowBall => SLOW_BALL_COLOR,
+            PowerUpKind::Laser => LASER_COLOR,
+        }
+    }
+}
+
+/// A power-up falling towards the paddle
+#[derive(Component)]
+pub struct PowerUp(pub PowerUpKind);
+
+/// A projectile fired upwards by the paddle while the laser power-up is active
+#[derive(Component)]
+pub struct Laser;
+
+/// The timed power-ups currently in effect, and how long each has left
+#[derive(Resource)]
+pub struct ActivePowerUps {
+    timers: HashMap<PowerUpKind, Timer>,
+    laser_cooldown: Timer,
+}
+
+impl Default for ActivePowerUps {
+    fn default() -> Self {
+        ActivePowerUps {
+            timers: HashMap::new(),
+            laser_cooldown: Timer::from_seconds(LASER_FIRE_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+impl ActivePowerUps {
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.timers.contains_key(&kind)
+    }
+
+    fn activate(&mut self, kind: PowerUpKind) {
+        self.timers.insert(
+            kind,
+            Timer::from_seconds(POWER_UP_DURATION, TimerMode::Once),
+        );
+    }
+}
+
+// Destroyed bricks sometimes drop a power-up
+pub(crate) fn drop_power_ups(
+    mut commands: Commands,
+    mut destroyed_events: EventReader<BrickDestroyed>,
+    mut rng: ResMut<GameRng>,
+) {
+    for destroyed in destroyed_events.read() {
+        if !rng.gen_bool(POWER_UP_DROP_CHANCE) {
+            continue;
+        }
+
+        let kind = *PowerUpKind::ALL.choose(&mut **rng).unwrap();
+        commands.spawn((
+            Transform {
+                translation: destroyed.position.extend(0.5),
+                scale: POWER_UP_SIZE.extend(1.0),
+                ..default()
+            },
+            PowerUp(kind),
+            Velocity(Vec2::new(0.0, -POWER_UP_FALL_SPEED)),
+            StateScoped(InGame),
+        ));
+    }
+}
+
+// Power-ups that touch the paddle take effect, and ones that fall past it are gone
+pub(crate) fn catch_power_ups(
+    mut commands: Commands,
+    power_up_query: Query<(Entity, &Transform, &PowerUp)>,
+    paddle_transform: Single<&Transform, With<Paddle>>,
+    ball_query: Query<(&Transform, &Velocity, Has<StuckToPaddle>), With<Ball>>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    ball_speed: Res<BallSpeed>,
+    arena: Res<ArenaConfig>,
+) {
+    let paddle = bounding_box(&paddle_transform);
+
+    for (power_up_entity, power_up_transform, PowerUp(kind)) in &power_up_query {
+        let power_up = bounding_box(power_up_transform);
+
+        if power_up.max.y < arena.bottom {
+            commands.entity(power_up_entity).despawn();
+            continue;
+        }
+
+        if !power_up.intersects(&paddle) {
+            continue;
+        }
+
+        commands.entity(power_up_entity).despawn();
+
+        if *kind != PowerUpKind::MultiBall {
+            active_power_ups.activate(*kind);
+            continue;
+        }
+
+        // Every ball splits into three. Balls still waiting on the paddle split upwards.
+        for (ball_transform, ball_velocity, stuck) in &ball_query {
+            let velocity = if stuck {
+                Vec2::Y * **ball_speed
+            } else {
+                **ball_velocity
+            };
+
+            for angle in [-MULTI_BALL_SPREAD, MULTI_BALL_SPREAD] {
+                commands.spawn(ball_bundle(
+                    ball_transform.translation,
+                    Vec2::from_angle(angle).rotate(velocity),
+                ));
+            }
+        }
+    }
+}
+
+pub(crate) fn tick_power_ups(mut active_power_ups: ResMut<ActivePowerUps>, time: Res<Time>) {
+    active_power_ups.timers.retain(|_, timer| {
+        timer.tick(time.delta());
+        !timer.finished()
+    });
+}
+
+// Size the paddles and set the speed of the balls according to the active power-ups
+pub(crate) fn apply_power_up_effects(
+    active_power_ups: Res<ActivePowerUps>,
+    ball_speed: Res<BallSpeed>,
+    speed_multiplier: Res<BallSpeedMultiplier>,
+    mut paddle_query: Query<&mut Transform, With<Paddle>>,
+    mut ball_query: Query<&mut Velocity, (With<Ball>, Without<StuckToPaddle>)>,
+) {
+    let paddle_width = if active_power_ups.is_active(PowerUpKind::WidePaddle) {
+        PADDLE_SIZE.x * WIDE_PADDLE_SCALE
+    } else {
+        PADDLE_SIZE.x
+    };
+    for mut paddle_transform in &mut paddle_query {
+        paddle_transform.scale.x = paddle_width;
+    }
+
+    let speed = **ball_speed * speed_multiplier.get();
+    let speed = if active_power_ups.is_active(PowerUpKind::SlowBall) {
+        speed * SLOW_BALL_SCALE
+    } else {
+        speed
+    };
+    for mut velocity in &mut ball_query {
+        **velocity = velocity.normalize_or(Vec2::Y) * speed;
+    }
+}
+
+// While the laser power-up is active, the paddle fires from both ends at a steady rate
+pub(crate) fn fire_lasers(
+    mut commands: Commands,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    paddle_transform: Single<&Transform, With<Paddle>>,
+    time: Res<Time>,
+) {
+    if !active_power_ups.is_active(PowerUpKind::Laser) {
+        active_power_ups.laser_cooldown.reset();
+        return;
+    }
+
+    active_power_ups.laser_cooldown.tick(time.delta());
+    if !active_power_ups.laser_cooldown.just_finished() {
+        return;
+    }
+
+    let paddle_top = paddle_transform.translation.y + paddle_transform.scale.y / 2.0;
+    let paddle_half_width = paddle_transform.scale.x / 2.0;
+
+    for side in [-1.0, 1.0] {
+        let x = paddle_transform.translation.x + side * (paddle_half_width - LASER_SIZE.x);
+        commands.spawn((
+            Transform {
+                translation: Vec3::new(x, paddle_top + LASER_SIZE.y / 2.0, 0.5),
+                scale: LASER_SIZE.extend(1.0),
+                ..default()
+            },
+            Laser,
+            Velocity(Vec2::new(0.0, LASER_SPEED)),
+            StateScoped(InGame),
+        ));
+    }
+}
+
+// Lasers damage the first brick they touch, and stop at walls
+#[allow(clippy::type_complexity)]
+pub(crate) fn check_for_laser_hits(
+    mut commands: Commands,
+    laser_query: Query<(Entity, &Transform), With<Laser>>,
+    collider_query: Query<
+        (Entity, &Transform, &ColliderShape, Has<Brick>),
+        (With<Collider>, Without<Paddle>),
+    >,
+    mut brick_hits: EventWriter<BrickHit>,
+) {
+    for (laser_entity, laser_transform) in &laser_query {
+        let laser = bounding_box(laser_transform);
+
+        let hit = collider_query
+            .iter()
+            .find(|(_, collider_transform, shape, _)| {
+                Volume::new(collider_transform, **shape).intersects(laser)
+            });
+
+        if let Some((collider_entity, _, _, is_brick)) = hit {
+            commands.entity(laser_entity).despawn();
+            if is_brick {
+                // Only solo games have power-ups, so lasers are always player one's
+                brick_hits.write(BrickHit(collider_entity, Player::One));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // The power-up systems, run headless with a manually advanced clock
+    fn power_up_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<ArenaConfig>()
+            .init_resource::<ActivePowerUps>()
+            .init_resource::<BallSpeedMultiplier>()
+            .insert_resource(BallSpeed(400.0))
+            .add_event::<BrickHit>()
+            .add_systems(
+                Update,
+                (
+                    catch_power_ups,
+                    tick_power_ups,
+                    apply_power_up_effects,
+                    fire_lasers,
+                )
+                    .chain(),
+            );
+
+        app.world_mut()
+            .spawn((Paddle, Transform::from_scale(PADDLE_SIZE.extend(1.0))));
+        app
+    }
+
+    fn step(app: &mut App, seconds: f32) {
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    // Drop a power-up right on top of the paddle
+    fn catch(app: &mut App, kind: PowerUpKind) {
+        app.world_mut().spawn((
+            PowerUp(kind),
+            Transform::from_scale(POWER_UP_SIZE.extend(1.0)),
+        ));
+        step(app, 0.0);
+    }
+
+    fn paddle_width(app: &mut App) -> f32 {
+        app.world_mut()
+            .query_filtered::<&Transform, With<Paddle>>()
+            .single(app.world())
+            .unwrap()
+            .scale
+            .x
+    }
+
+    #[test]
+    fn wide_paddle_expires() {
+        let mut app = power_up_app();
+        catch(&mut app, PowerUpKind::WidePaddle);
+        assert_eq!(paddle_width(&mut app), PADDLE_SIZE.x * WIDE_PADDLE_SCALE);
+
+        step(&mut app, POWER_UP_DURATION - 1.0);
+        assert_eq!(paddle_width(&mut app), PADDLE_SIZE.x * WIDE_PADDLE_SCALE);
+
+        step(&mut app, 1.0);
+        assert_eq!(paddle_width(&mut app), PADDLE_SIZE.x);
+        assert!(
+            !app.world()
+                .resource::<ActivePowerUps>()
+                .is_active(PowerUpKind::WidePaddle)
+        );
+    }
+
+    #[test]
+    fn slow_ball_expires() {
+        let mut app = power_up_app();
+        let ball = app
+            .world_mut()
+            .spawn((
+                Ball,
+                Transform::default(),
+                Velocity(Vec2::new(300.0, 400.0)),
+            ))
+            .id();
+        let speed = |app: &App| app.world().get::<Velocity>(ball).unwrap().length();
+
+        catch(&mut app, PowerUpKind::SlowBall);
+        assert!((speed(&app) - 400.0 * SLOW_BALL_SCALE).abs() < 1e-3);
+
+        step(&mut app, POWER_UP_DURATION);
+        assert!((speed(&app) - 400.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn multi_ball_splits_every_ball() {
+        let mut app = power_up_app();
+        app.world_mut().spawn((
+            Ball,
+            Transform::from_xyz(0.0, 100.0, 1.0),
+            Velocity(Vec2::new(0.0, 400.0)),
+        ));
+
+        catch(&mut app, PowerUpKind::MultiBall);
+
+        let mut balls = app
+            .world_mut()
+            .query_filtered::<(&Transform, &Velocity), With<Ball>>();
+        let balls: Vec<_> = balls.iter(app.world()).collect();
+        assert_eq!(balls.len(), 3);
+        for (transform, velocity) in balls {
+            assert_eq!(transform.translation.y, 100.0);
+            assert!((velocity.length() - 400.0).abs() < 1e-3);
+        }
+        // Multi-ball is instant, so there is nothing to expire
+        assert!(
+            !app.world()
+                .resource::<ActivePowerUps>()
+                .is_active(PowerUpKind::MultiBall)
+        );
+    }
+
+    #[test]
+    fn laser_fires_until_it_expires() {
+        let mut app = power_up_app();
+        catch(&mut app, PowerUpKind::Laser);
+
+        let count_lasers = |app: &mut App| {
+            app.world_mut()
+                .query_filtered::<(), With<Laser>>()
+                .iter(app.world())
+                .count()
+        };
+
+        step(&mut app, LASER_FIRE_INTERVAL);
+        assert_eq!(count_lasers(&mut app), 2);
+        step(&mut app, LASER_FIRE_INTERVAL);
+        assert_eq!(count_lasers(&mut app), 4);
+
+        step(&mut app, POWER_UP_DURATION);
+        let fired = count_lasers(&mut app);
+        step(&mut app, LASER_FIRE_INTERVAL);
+        assert_eq!(count_lasers(&mut app), fired);
+    }
+
+    #[test]
+    fn missed_power_ups_are_removed() {
+        let mut app = power_up_app();
+        let power_up = app
+            .world_mut()
+            .spawn((
+                PowerUp(PowerUpKind::WidePaddle),
+                Transform::from_xyz(0.0, ArenaConfig::default().bottom - 50.0, 0.0)
+                    .with_scale(POWER_UP_SIZE.extend(1.0)),
+            ))
+            .id();
+
+        step(&mut app, 0.0);
+
+        assert!(app.world().get_entity(power_up).is_err());
+        assert!(
+            !app.world()
+                .resource::<ActivePowerUps>()
+                .is_active(PowerUpKind::WidePaddle)
+        );
+    }
+
+    #[test]
+    fn lasers_pass_the_corners_of_turned_and_round_colliders() {
+        let mut app = App::new();
+        app.add_event::<BrickHit>()
+            .add_systems(Update, check_for_laser_hits);
+
+        // A bar turned 45 degrees and a disc, whose unturned boxes reach well past their shapes
+        app.world_mut().spawn((
+            Collider,
+            Transform {
+                scale: Vec3::new(200.0, 16.0, 1.0),
+                rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+                ..default()
+            },
+        ));
+        app.world_mut().spawn((
+            Collider,
+            ColliderShape::Circle,
+            Transform::from_xyz(300.0, 0.0, 0.0).with_scale(Vec3::splat(100.0)),
+        ));
+        let mut laser = |x: f32, y: f32| {
+            app.world_mut()
+                .spawn((
+                    Laser,
+                    Transform::from_xyz(x, y, 0.5).with_scale(LASER_SIZE.extend(1.0)),
+                ))
+                .id()
+        };
+        let past_bar = laser(80.0, 0.0);
+        let past_disc = laser(345.0, -45.0);
+        let on_bar = laser(20.0, 20.0);
+        let on_disc = laser(300.0, 40.0);
+
+        app.update();
+
+        assert!(app.world().get_entity(past_bar).is_ok());
+        assert!(app.world().get_entity(past_disc).is_ok());
+        assert!(app.world().get_entity(on_bar).is_err());
+        assert!(app.world().get_entity(on_disc).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+use bevy::{
+    prelude::*,
+    render::camera::{ScalingMode, Viewport},
+    window::PrimaryWindow,
+};
+
+use crate::{
+    BACKGROUND_COLOR, BALL_COLOR, Ball, Brick, ColliderShape, Lives, PADDLE_COLOR, Paddle,
+    SCORE_COLOR, SCOREBOARD_FONT_SIZE, SCOREBOARD_TEXT_PADDING, Score, TEXT_COLOR, WALL_COLOR,
+    Wall,
+    arena::ArenaConfig,
+    attract::AttractModePlugin,
+    campaign::CampaignPlugin,
+    difficulty::BallSpeedMultiplier,
+    editor::EditorPlugin,
+    effects::{CameraShake, EffectsPlugin},
+    high_score::HighScorePlugin,
+    input::PaddleInputPlugin,
+    menu::MenuPlugin,
+    obstacle::Obstacle,
+    power_up::{LASER_COLOR, Laser, PowerUp},
+    save_game::SaveFilePlugin,
+    scoring::{ScoreEvent, ScoreReason, add_score},
+    settings::SettingsPlugin,
+    sound::SoundPlugin,
+    state::{InGame, ScreensPlugin},
+    versus::{Player, Standing},
+};
+
+const FLOATING_SCORE_FONT_SIZE: f32 = 24.0;
+// Each step of the combo multiplier makes the text this much bigger
+const FLOATING_SCORE_COMBO_FONT_SIZE: f32 = 6.0;
+const FLOATING_SCORE_LIFETIME: f32 = 0.8;
+const FLOATING_SCORE_RISE_SPEED: f32 = 60.0;
+const ROW_BONUS_COLOR: Color = Color::srgb(1.0, 0.75, 0.2);
+const PLAYER_TWO_PADDLE_COLOR: Color = Color::srgb(0.7, 0.3, 0.3);
+const OBSTACLE_COLOR: Color = Color::srgb(0.55, 0.45, 0.7);
+
+// Fills the window around the view when its shape doesn't match
+const LETTERBOX_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+// The background reaches this far past the edges of the view, so the camera shake never shows
+// the letterbox color inside the view
+const BACKGROUND_MARGIN: f32 = 64.0;
+
+/// Everything the player sees, hears and touches: sprites, the scoreboard, sounds,
+/// the screens between levels, the attract-mode demo, the pause menu and settings, input,
+/// high scores, campaign progress and the saved game.
+///
+/// Needs [`BreakoutSimPlugin`](crate::BreakoutSimPlugin) and `DefaultPlugins`.
+/// Entities spawned by the simulation are given their sprites as they are added.
+pub struct BreakoutPresentationPlugin;
+
+impl Plugin for BreakoutPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ScreensPlugin,
+            PaddleInputPlugin,
+            HighScorePlugin,
+            AttractModePlugin,
+            EffectsPlugin,
+            SoundPlugin,
+            EditorPlugin,
+            SaveFilePlugin,
+            MenuPlugin,
+            SettingsPlugin,
+            CampaignPlugin,
+        ))
+        .insert_resource(ClearColor(LETTERBOX_COLOR))
+        .add_systems(Startup, setup)
+        .add_observer(add_wall_sprite)
+        .add_observer(add_paddle_sprite)
+        .add_observer(add_ball_mesh)
+        .add_observer(add_brick_sprite)
+        .add_observer(add_power_up_sprite)
+        .add_observer(add_laser_sprite)
+        .add_observer(add_obstacle_sprite)
+        .add_systems(FixedUpdate, spawn_floating_scores.after(add_score))
+        .add_systems(
+            Update,
+            (
+                update_scoreboard,
+                update_brick_colors,
+                animate_floating_scores,
+                fit_camera_to_window,
+            ),
+        );
+    }
+}
+
+// Every ball shares the same mesh and material, and disc obstacles share the mesh
+#[derive(Resource, Default)]
+struct BallAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+}
+
+#[derive(Component)]
+struct ScoreboardUi;
+
+// The "+N" that rises from where points were earned, fading out until the timer finishes
+#[derive(Component, Deref, DerefMut)]
+struct FloatingScore(Timer);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    arena: Res<ArenaConfig>,
+) {
+    // Camera
+    // It always shows the same part of the world, whatever the window's size;
+    // `fit_camera_to_window` letterboxes it.
+    commands.spawn((
+        Camera2d,
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::Fixed {
+                width: arena.view_size.x,
+                height: arena.view_size.y,
+            },
+            ..OrthographicProjection::default_2d()
+        }),
+        CameraShake::default(),
+    ));
+
+    // Background
+    // Behind everything, and only inside the letterbox
+    commands.spawn((
+        Sprite::from_color(
+            BACKGROUND_COLOR,
+            arena.view_size + Vec2::splat(2.0 * BACKGROUND_MARGIN),
+        ),
+        Transform::from_xyz(0.0, 0.0, -10.0),
+    ));
+
+    // Ball
+    // Balls are spawned with each level, and by power-ups
+    commands.insert_resource(BallAssets {
+        mesh: meshes.add(Circle::default()),
+        material: materials.add(BALL_COLOR),
+    });
+
+    // Scoreboard
+    commands.spawn((
+        Text::new("Score: "),
+        TextFont {
+            font_size: SCOREBOARD_FONT_SIZE,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+        ScoreboardUi,
+        Node {
+            position_type: PositionType::Absolute,
+            top: SCOREBOARD_TEXT_PADDING,
+            left: SCOREBOARD_TEXT_PADDING,
+            ..default()
+        },
+        children![
+            (
+                TextSpan::default(),
+                TextFont {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(SCORE_COLOR),
+            ),
+            (
+                TextSpan::new("  Lives: "),
+                TextFont {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ),
+            (
+                TextSpan::default(),
+                TextFont {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(SCORE_COLOR),
+            ),
+            (
+                TextSpan::new("  Speed: "),
+                TextFont {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ),
+            (
+                TextSpan::default(),
+                TextFont {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(SCORE_COLOR),
+            ),
+        ],
+    ));
+}
+
+// The simulation only gives entities a transform, so give each one something to look like.
+// Sprites are one unit across, and are sized by the entity's `Transform::scale`.
+fn add_wall_sprite(trigger: Trigger<OnAdd, Wall>, mut commands: Commands) {
+    commands
+        .entity(trigger.target())
+        .insert(Sprite::from_color(WALL_COLOR, Vec2::ONE));
+}
+
+fn add_paddle_sprite(
+    trigger: Trigger<OnAdd, Paddle>,
+    mut commands: Commands,
+    paddles: Query<&Player>,
+) {
+    let color = match paddles.get(trigger.target()) {
+        Ok(Player::Two) => PLAYER_TWO_PADDLE_COLOR,
+        _ => PADDLE_COLOR,
+    };
+
+    commands
+        .entity(trigger.target())
+        .insert(Sprite::from_color(color, Vec2::ONE));
+}
+
+fn add_ball_mesh(
+    trigger: Trigger<OnAdd, Ball>,
+    mut commands: Commands,
+    ball_assets: Res<BallAssets>,
+) {
+    // On a child, so that the ball's visuals can be squashed without changing the ball
+    commands.entity(trigger.target()).with_child((
+        Mesh2d(ball_assets.mesh.clone()),
+        MeshMaterial2d(ball_assets.material.clone()),
+    ));
+}
+
+fn add_brick_sprite(trigger: Trigger<OnAdd, Brick>, mut commands: Commands, bricks: Query<&Brick>) {
+    let Ok(brick) = bricks.get(trigger.target()) else {
+        return;
+    };
+
+    commands
+        .entity(trigger.target())
+        .insert(Sprite::from_color(brick.color(), Vec2::ONE));
+}
+
+fn add_obstacle_sprite(
+    trigger: Trigger<OnAdd, Obstacle>,
+    mut commands: Commands,
+    obstacles: Query<&ColliderShape>,
+    ball_assets: Res<BallAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut obstacle = commands.entity(trigger.target());
+    match obstacles.get(trigger.target()) {
+        Ok(ColliderShape::Circle) => obstacle.insert((
+            Mesh2d(ball_assets.mesh.clone()),
+            MeshMaterial2d(materials.add(OBSTACLE_COLOR)),
+        )),
+        _ => obstacle.insert(Sprite::from_color(OBSTACLE_COLOR, Vec2::ONE)),
+    };
+}
+
+fn add_power_up_sprite(
+    trigger: Trigger<OnAdd, PowerUp>,
+    mut commands: Commands,
+    power_ups: Query<&PowerUp>,
+) {
+    let Ok(PowerUp(kind)) = power_ups.get(trigger.target()) else {
+        return;
+    };
+
+    commands
+        .entity(trigger.target())
+        .insert(Sprite::from_color(kind.color(), Vec2::ONE));
+}
+
+fn add_laser_sprite(trigger: Trigger<OnAdd, Laser>, mut commands: Commands) {
+    commands
+        .entity(trigger.target())
+        .insert(Sprite::from_color(LASER_COLOR, Vec2::ONE));
+}
+
+// In versus matches, both players' scores and lives are shown, player one's first
+fn update_scoreboard(
+    score: Res<Score>,
+    lives: Res<Lives>,
+    speed_multiplier: Res<BallSpeedMultiplier>,
+    standings: Query<(&Player, &Standing)>,
+    score_root: Single<Entity, (With<ScoreboardUi>, With<Text>)>,
+    mut writer: TextUiWriter,
+) {
+    let mut standings: Vec<(&Player, &Standing)> = standings.iter().collect();
+    standings.sort_by_key(|(player, _)| player.index());
+
+    if standings.is_empty() {
+        *writer.text(*score_root, 1) = score.to_string();
+        *writer.text(*score_root, 3) = lives.to_string();
+    } else {
+        let join = |field: fn(&Standing) -> String| {
+            standings
+                .iter()
+                .map(|(_, standing)| field(standing))
+                .collect::<Vec<_>>()
+                .join(" - ")
+        };
+        *writer.text(*score_root, 1) = join(|standing| standing.score.to_string());
+        *writer.text(*score_root, 3) = join(|standing| standing.lives.to_string());
+    }
+    *writer.text(*score_root, 5) = format!("x{:.2}", speed_multiplier.get());
+}
+
+// Keep the view's shape whatever the window's shape, by drawing only to the largest part of the
+// window with the right shape. The UI is laid out in that part too, scaled so it stays the same
+// size relative to the arena.
+fn fit_camera_to_window(
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&mut Camera, &mut Projection)>,
+    arena: Res<ArenaConfig>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let (mut camera, mut projection) = camera.into_inner();
+    let window_size = window.physical_size();
+    if window_size.cmpeq(UVec2::ZERO).any() {
+        // Minimized
+        return;
+    }
+
+    let letterbox = arena.letterbox(window_size.as_vec2());
+    let physical_position = letterbox.min.round().as_uvec2();
+    let physical_size = letterbox.size().round().as_uvec2().max(UVec2::ONE);
+    let needs_update = camera.viewport.as_ref().is_none_or(|viewport| {
+        viewport.physical_position != physical_position || viewport.physical_size != physical_size
+    });
+    if needs_update {
+        camera.viewport = Some(Viewport {
+            physical_position,
+            physical_size,
+            ..default()
+        });
+    }
+
+    let scale = letterbox.width() / (arena.view_size.x * window.scale_factor());
+    if ui_scale.0 != scale {
+        ui_scale.0 = scale;
+    }
+
+    if arena.is_changed()
+        && let Projection::Orthographic(orthographic) = &mut *projection
+    {
+        orthographic.scaling_mode = ScalingMode::Fixed {
+            width: arena.view_size.x,
+            height: arena.view_size.y,
+        };
+    }
+}
+
+// Show how much damage multi-hit bricks have taken
+fn update_brick_colors(mut brick_query: Query<(&Brick, &mut Sprite), Changed<Brick>>) {
+    for (brick, mut sprite) in &mut brick_query {
+        sprite.color = brick.color();
+    }
+}
+
+fn spawn_floating_scores(mut commands: Commands, mut score_events: EventReader<ScoreEvent>) {
+    for event in score_events.read() {
+        let (text, color, multiplier) = match event.reason {
+            ScoreReason::Brick { multiplier } => {
+                (format!("+{}", event.points), SCORE_COLOR, multiplier)
+            }
+            ScoreReason::RowCleared => (format!("Row +{}", event.points), ROW_BONUS_COLOR, 1),
+        };
+
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font_size: FLOATING_SCORE_FONT_SIZE
+                    + FLOATING_SCORE_COMBO_FONT_SIZE * multiplier.saturating_sub(1) as f32,
+                ..default()
+            },
+            TextColor(color),
+            // In front of the bricks and the ball
+            Transform::from_translation(event.position.extend(2.0)),
+            FloatingScore(Timer::from_seconds(
+                FLOATING_SCORE_LIFETIME,
+                TimerMode::Once,
+            )),
+            StateScoped(InGame),
+        ));
+    }
+}
+
+fn animate_floating_scores(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut FloatingScore, &mut Transform, &mut TextColor)>,
+    time: Res<Time>,
+) {
+    for (entity, mut floating_score, mut transform, mut color) in &mut query {
+        floating_score.tick(time.delta());
+        if floating_score.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation.y += FLOATING_SCORE_RISE_SPEED * time.delta_secs();
+        color.set_alpha(floating_score.fraction_remaining());
+    }
+}
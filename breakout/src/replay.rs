@@ -0,0 +1,634 @@
+//! Recording the paddle input for every fixed timestep of a level, and playing it back.
+//!
+//! The simulation in `FixedUpdate` only depends on the [`PaddleIntent`] for each timestep
+//! and the level's random seed, so a [`Recording`] of those is enough to play a level again
+//! and check that it ends with the same score and the same bricks left.
+//!
+//! Run the game with `--record <directory>` to save a recording of every bundled level played,
+//! and with `--replay <file>` to play one back and exit with an error if the result differs.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::{
+    Brick, CurrentLevel, LevelSeed, Levels, Lives, Score,
+    attract::AttractMode,
+    check_for_level_cleared,
+    difficulty::Difficulty,
+    input::PaddleIntent,
+    level::{CustomLevel, LEVELS},
+    move_paddle,
+    save_game::ContinuedLevel,
+    state::{GameState, InGame},
+    versus::{GameMode, is_versus},
+};
+
+/// Records levels and plays back recordings, depending on the command line arguments
+#[derive(Default)]
+pub struct ReplayPlugin {
+    /// Save a recording of every level played to this directory
+    pub record_to: Option<PathBuf>,
+    /// Play back this recording instead of reading the player's input
+    pub replay: Option<PathBuf>,
+}
+
+impl ReplayPlugin {
+    /// Reads `--record <directory>` and `--replay <file>` from the command line
+    pub fn from_args() -> ReplayPlugin {
+        let mut plugin = ReplayPlugin::default();
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => plugin.record_to = args.next().map(PathBuf::from),
+                "--replay" => plugin.replay = args.next().map(PathBuf::from),
+                _ => {}
+            }
+        }
+
+        plugin
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(directory) = &self.record_to {
+            app.insert_resource(RecordTo(directory.clone()));
+        }
+
+        if let Some(path) = &self.replay {
+            match Recording::load(path) {
+                Ok(recording) => {
+                    app.insert_resource(Replay::new(recording));
+                }
+                Err(error) => error!("Could not load replay {}: {error}", path.display()),
+            }
+        }
+
+        app.add_systems(
+            OnEnter(InGame),
+            // Replays only record player one's own games (never the demo), only know
+            // the bundled levels, and always start them from the beginning
+            start_recording.run_if(
+                resource_exists::<RecordTo>
+                    .and(not(resource_exists::<Replay>))
+                    .and(not(resource_exists::<CustomLevel>))
+                    .and(not(resource_exists::<ContinuedLevel>))
+                    .and(not(resource_exists::<AttractMode>))
+                    .and(not(is_versus)),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                start_replay.run_if(in_state(GameState::Title)),
+                report_replay.run_if(resource_added::<ReplayResult>),
+            )
+                .run_if(resource_exists::<Replay>),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                (
+                    play_back_input.run_if(resource_exists::<Replay>),
+                    record_input.run_if(resource_exists::<Recorder>),
+                )
+                    .chain()
+                    .before(move_paddle),
+                (
+                    finish_replay.run_if(resource_exists::<Replay>),
+                    finish_recording.run_if(resource_exists::<Recorder>),
+                )
+                    .after(check_for_level_cleared),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// How a level ended: the score, and how many bricks were left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayOutcome {
+    pub score: usize,
+    pub bricks: usize,
+}
+
+/// Everything needed to play a level again exactly as it was played before
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    /// Index into `LEVELS`
+    pub level: usize,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    /// The lives and score at the start of the level
+    pub lives: u32,
+    pub score: usize,
+    /// The input for each fixed timestep, as runs of identical input:
+    /// how many timesteps in a row, and the input for each of them
+    pub inputs: Vec<(u32, PaddleIntent)>,
+    pub outcome: ReplayOutcome,
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("could not read replay file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a replay file, or made by an incompatible version of the game")]
+    NotAReplay,
+    #[error("replay file is truncated")]
+    Truncated,
+    #[error("replay file has a value that is out of range")]
+    OutOfRange,
+}
+
+// Replay files start with this, followed by a version number
+const MAGIC: &[u8] = b"BREAKOUT-REPLAY";
+const VERSION: u8 = 2;
+
+// Each recorded input starts with these flags, saying which fields follow
+const LAUNCH: u8 = 1 << 0;
+const HAS_DIRECTION: u8 = 1 << 1;
+const HAS_TARGET: u8 = 1 << 2;
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, ReplayError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(ReplayError::Truncated)?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(ReplayError::OutOfRange)
+}
+
+fn read_byte(bytes: &mut &[u8]) -> Result<u8, ReplayError> {
+    let (&byte, rest) = bytes.split_first().ok_or(ReplayError::Truncated)?;
+    *bytes = rest;
+    Ok(byte)
+}
+
+fn read_f32(bytes: &mut &[u8]) -> Result<f32, ReplayError> {
+    let (value, rest) = bytes.split_first_chunk().ok_or(ReplayError::Truncated)?;
+    *bytes = rest;
+    Ok(f32::from_le_bytes(*value))
+}
+
+fn read_usize(bytes: &mut &[u8]) -> Result<usize, ReplayError> {
+    read_varint(bytes)?
+        .try_into()
+        .map_err(|_| ReplayError::OutOfRange)
+}
+
+fn read_u32(bytes: &mut &[u8]) -> Result<u32, ReplayError> {
+    read_varint(bytes)?
+        .try_into()
+        .map_err(|_| ReplayError::OutOfRange)
+}
+
+impl Recording {
+    fn new(level: usize, seed: u64, difficulty: Difficulty, lives: u32, score: usize) -> Recording {
+        Recording {
+            level,
+            seed,
+            difficulty,
+            lives,
+            score,
+            inputs: Vec::new(),
+            outcome: ReplayOutcome { score, bricks: 0 },
+        }
+    }
+
+    /// Add the input for the next fixed timestep
+    pub fn push(&mut self, input: PaddleIntent) {
+        match self.inputs.last_mut() {
+            Some((count, last)) if *last == input => *count += 1,
+            _ => self.inputs.push((1, input)),
+        }
+    }
+
+    /// A compact binary encoding, since there can be an input for every fixed timestep.
+    /// Numbers are stored as varints, and inputs only store the fields that are in use.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+
+        for value in [
+            self.level as u64,
+            self.seed,
+            Difficulty::ALL
+                .iter()
+                .position(|&difficulty| difficulty == self.difficulty)
+                .unwrap() as u64,
+            u64::from(self.lives),
+            self.score as u64,
+            self.outcome.score as u64,
+            self.outcome.bricks as u64,
+            self.inputs.len() as u64,
+        ] {
+            write_varint(&mut bytes, value);
+        }
+
+        for &(count, input) in &self.inputs {
+            write_varint(&mut bytes, u64::from(count));
+
+            let mut flags = 0;
+            if input.launch {
+                flags |= LAUNCH;
+            }
+            if input.direction != 0.0 {
+                flags |= HAS_DIRECTION;
+            }
+            if input.target_x.is_some() {
+                flags |= HAS_TARGET;
+            }
+            bytes.push(flags);
+
+            if input.direction != 0.0 {
+                bytes.extend(input.direction.to_le_bytes());
+            }
+            if let Some(target_x) = input.target_x {
+                bytes.extend(target_x.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Recording, ReplayError> {
+        let bytes = &mut bytes;
+
+        let header = bytes
+            .split_off(..MAGIC.len() + 1)
+            .ok_or(ReplayError::NotAReplay)?;
+        if header != [MAGIC, &[VERSION]].concat() {
+            return Err(ReplayError::NotAReplay);
+        }
+
+        let level = read_usize(bytes)?;
+        if level >= LEVELS.len() {
+            return Err(ReplayError::OutOfRange);
+        }
+        let seed = read_varint(bytes)?;
+        let difficulty = *Difficulty::ALL
+            .get(read_usize(bytes)?)
+            .ok_or(ReplayError::OutOfRange)?;
+        let lives = read_u32(bytes)?;
+        let score = read_usize(bytes)?;
+        let outcome = ReplayOutcome {
+            score: read_usize(bytes)?,
+            bricks: read_usize(bytes)?,
+        };
+        let runs = read_usize(bytes)?;
+
+        // Don't trust the length to preallocate, but each run needs at least two bytes
+        let mut inputs = Vec::with_capacity(runs.min(bytes.len() / 2));
+        for _ in 0..runs {
+            let count = read_u32(bytes)?;
+            let flags = read_byte(bytes)?;
+            let direction = if flags & HAS_DIRECTION != 0 {
+                read_f32(bytes)?
+            } else {
+                0.0
+            };
+            let target_x = if flags & HAS_TARGET != 0 {
+                Some(read_f32(bytes)?)
+            } else {
+                None
+            };
+
+            inputs.push((
+                count,
+                PaddleIntent {
+                    direction,
+                    target_x,
+                    launch: flags & LAUNCH != 0,
+                },
+            ));
+        }
+
+        Ok(Recording {
+            level,
+            seed,
+            difficulty,
+            lives,
+            score,
+            inputs,
+            outcome,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Recording, ReplayError> {
+        Recording::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+}
+
+/// The directory to save recordings to
+#[derive(Resource)]
+struct RecordTo(PathBuf);
+
+/// The level currently being recorded
+#[derive(Resource)]
+struct Recorder(Recording);
+
+/// A recording being played back
+#[derive(Resource)]
+pub struct Replay {
+    recording: Recording,
+    started: bool,
+    // Position in `recording.inputs`
+    run: usize,
+    tick_in_run: u32,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Replay {
+        Replay {
+            recording,
+            started: false,
+            run: 0,
+            tick_in_run: 0,
+        }
+    }
+
+    fn next_input(&mut self) -> Option<PaddleIntent> {
+        let &(count, input) = self.recording.inputs.get(self.run)?;
+
+        self.tick_in_run += 1;
+        if self.tick_in_run == count {
+            self.run += 1;
+            self.tick_in_run = 0;
+        }
+
+        Some(input)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.run >= self.recording.inputs.len()
+    }
+}
+
+/// Inserted when a replay finishes, to compare how the level ended with the recording
+#[derive(Resource, Debug)]
+pub struct ReplayResult {
+    pub expected: ReplayOutcome,
+    pub actual: ReplayOutcome,
+}
+
+impl ReplayResult {
+    pub fn matches(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+// Levels end when the simulation asks to leave the `Playing` state
+fn level_ending(next_state: &NextState<GameState>) -> bool {
+    matches!(next_state, NextState::Pending(_))
+}
+
+fn start_recording(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    level_seed: Res<LevelSeed>,
+    difficulty: Res<Difficulty>,
+    lives: Res<Lives>,
+    score: Res<Score>,
+) {
+    commands.insert_resource(Recorder(Recording::new(
+        **current_level,
+        **level_seed,
+        *difficulty,
+        **lives,
+        **score,
+    )));
+}
+
+fn record_input(mut recorder: ResMut<Recorder>, intent: Res<PaddleIntent>) {
+    recorder.0.push(*intent);
+}
+
+// Save the recording once the level is over
+fn finish_recording(
+    mut commands: Commands,
+    mut recorder: ResMut<Recorder>,
+    record_to: Res<RecordTo>,
+    score: Res<Score>,
+    bricks: Query<(), With<Brick>>,
+    next_state: Res<NextState<GameState>>,
+) {
+    if !level_ending(&next_state) {
+        return;
+    }
+
+    let recording = &mut recorder.0;
+    recording.outcome = ReplayOutcome {
+        score: **score,
+        bricks: bricks.iter().count(),
+    };
+
+    let path = record_to.0.join(format!(
+        "level-{:02}-{}.replay",
+        recording.level + 1,
+        recording.seed
+    ));
+    match recording.save(&path) {
+        Ok(()) => info!("Saved replay to {}", path.display()),
+        Err(error) => warn!("Could not save replay to {}: {error}", path.display()),
+    }
+
+    commands.remove_resource::<Recorder>();
+}
+
+// Set up the recorded level once the levels have loaded, and start playing it
+fn start_replay(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    asset_server: Res<AssetServer>,
+    levels: Res<Levels>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if replay.started
+        || !levels
+            .0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    {
+        return;
+    }
+
+    let recording = &replay.recording;
+    if recording.level >= levels.0.len() {
+        error!("Could not play replay: {}", ReplayError::OutOfRange);
+        commands.remove_resource::<Replay>();
+        exit.write(AppExit::error());
+        return;
+    }
+
+    // Recordings are always of a bundled level, even when a level file was given
+    commands.remove_resource::<CustomLevel>();
+    commands.insert_resource(CurrentLevel(recording.level));
+    commands.insert_resource(LevelSeed(recording.seed));
+    commands.insert_resource(recording.difficulty);
+    commands.insert_resource(GameMode::Solo);
+    commands.insert_resource(Lives(recording.lives));
+    commands.insert_resource(Score(recording.score));
+    replay.started = true;
+    next_state.set(GameState::Playing);
+}
+
+// Replace the player's input with the recorded input
+fn play_back_input(mut replay: ResMut<Replay>, mut intent: ResMut<PaddleIntent>) {
+    *intent = replay.next_input().unwrap_or_default();
+}
+
+fn finish_replay(
+    mut commands: Commands,
+    replay: Res<Replay>,
+    score: Res<Score>,
+    bricks: Query<(), With<Brick>>,
+    next_state: Res<NextState<GameState>>,
+    result: Option<Res<ReplayResult>>,
+) {
+    if result.is_some() || !(replay.is_finished() || level_ending(&next_state)) {
+        return;
+    }
+
+    commands.insert_resource(ReplayResult {
+        expected: replay.recording.outcome,
+        actual: ReplayOutcome {
+            score: **score,
+            bricks: bricks.iter().count(),
+        },
+    });
+}
+
+fn report_replay(result: Res<ReplayResult>, mut exit: EventWriter<AppExit>) {
+    if result.matches() {
+        info!("Replay finished as recorded: {:?}", result.actual);
+        exit.write(AppExit::Success);
+    } else {
+        error!(
+            "Replay did not match the recording: expected {:?}, got {:?}",
+            result.expected, result.actual
+        );
+        exit.write(AppExit::error());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intent(direction: f32, launch: bool) -> PaddleIntent {
+        PaddleIntent {
+            direction,
+            target_x: None,
+            launch,
+        }
+    }
+
+    #[test]
+    fn identical_inputs_are_run_length_encoded() {
+        let mut recording = Recording::new(0, 0, Difficulty::Normal, 3, 0);
+        recording.push(intent(0.0, true));
+        recording.push(intent(1.0, false));
+        recording.push(intent(1.0, false));
+        recording.push(intent(1.0, false));
+        recording.push(intent(0.0, false));
+
+        assert_eq!(
+            recording.inputs,
+            vec![
+                (1, intent(0.0, true)),
+                (3, intent(1.0, false)),
+                (1, intent(0.0, false)),
+            ]
+        );
+
+        let mut replay = Replay::new(recording);
+        let played: Vec<_> = std::iter::from_fn(|| replay.next_input()).collect();
+        assert_eq!(
+            played,
+            vec![
+                intent(0.0, true),
+                intent(1.0, false),
+                intent(1.0, false),
+                intent(1.0, false),
+                intent(0.0, false),
+            ]
+        );
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let mut recording = Recording::new(3, u64::MAX, Difficulty::Hard, 2, 1234);
+        recording.push(intent(0.0, true));
+        recording.push(intent(-0.25, false));
+        for x in 0..300 {
+            recording.push(PaddleIntent {
+                direction: 0.0,
+                target_x: Some(x as f32 * 0.7),
+                launch: false,
+            });
+        }
+        recording.outcome = ReplayOutcome {
+            score: 99_999,
+            bricks: 3,
+        };
+
+        let bytes = recording.to_bytes();
+        assert_eq!(Recording::from_bytes(&bytes).unwrap(), recording);
+        // A header, then at most six bytes for each of these inputs
+        assert!(
+            bytes.len() < 40 + 6 * recording.inputs.len(),
+            "{}",
+            bytes.len()
+        );
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(matches!(
+            Recording::from_bytes(b"(level: 0)"),
+            Err(ReplayError::NotAReplay)
+        ));
+
+        let mut recording = Recording::new(0, 0, Difficulty::Normal, 3, 0);
+        recording.push(intent(1.0, false));
+        let bytes = recording.to_bytes();
+        assert!(matches!(
+            Recording::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ReplayError::Truncated)
+        ));
+
+        let recording = Recording::new(LEVELS.len(), 0, Difficulty::Normal, 3, 0);
+        assert!(matches!(
+            Recording::from_bytes(&recording.to_bytes()),
+            Err(ReplayError::OutOfRange)
+        ));
+    }
+}
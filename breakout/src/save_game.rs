@@ -0,0 +1,310 @@
+//! Saving a solo game part way through a level, and continuing it later from the title screen.
+//!
+//! A [`SavedGame`] is a Bevy scene: the bricks that are left, including where moving bricks
+//! are along their paths, the balls and the paddle are saved as entities, and the score, lives and level as resources, along with the score
+//! and lives the level started with and how long it has been played for.
+//! Everything else, such as power-ups and how much the ball has sped up,
+//! starts afresh when the game is continued.
+
+use std::{
+    any::TypeId,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    ecs::entity::EntityHashMap,
+    prelude::*,
+    reflect::TypeRegistry,
+    scene::{DynamicScene, DynamicSceneBuilder, SceneSpawnError, serde::SceneDeserializer},
+};
+use serde::de::DeserializeSeed;
+use thiserror::Error;
+
+use crate::{
+    Ball, Brick, Collider, CurrentLevel, Levels, Lives, Paddle, Score, StuckToPaddle, Velocity,
+    level::CustomLevel,
+    obstacle::{Patrol, Spin},
+    persist, spawn_level,
+    state::{GameState, InGame, LevelClock, LevelStart, remember_level_start, reset_level_clock},
+    versus::{GameMode, Player},
+};
+
+const SAVED_GAME_FILE: &str = "saved_game.scn.ron";
+
+/// Registers everything that goes into a [`SavedGame`],
+/// and swaps in its paddle, balls and bricks when a continued game's level starts
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        // Transform is registered by `TransformPlugin`, which headless apps may not have
+        app.register_type::<Transform>()
+            .register_type::<Score>()
+            .register_type::<Lives>()
+            .register_type::<CurrentLevel>()
+            .register_type::<LevelStart>()
+            .register_type::<LevelClock>()
+            .register_type::<Paddle>()
+            .register_type::<Player>()
+            .register_type::<Ball>()
+            .register_type::<Velocity>()
+            .register_type::<StuckToPaddle>()
+            .register_type::<Brick>()
+            .register_type::<Patrol>()
+            .register_type::<Spin>()
+            .add_systems(OnExit(InGame), |mut commands: Commands| {
+                commands.remove_resource::<ContinuedLevel>();
+            })
+            .add_systems(
+                OnEnter(InGame),
+                restore_saved_entities
+                    .after(spawn_level)
+                    .after(remember_level_start)
+                    .after(reset_level_clock)
+                    .run_if(resource_exists::<ResumedGame>),
+            );
+    }
+}
+
+/// Keeps a saved game in the user's data directory,
+/// saved from the pause menu and continued from the title screen
+pub struct SaveFilePlugin;
+
+impl Plugin for SaveFilePlugin {
+    fn build(&self, app: &mut App) {
+        let path = persist::data_path(SAVED_GAME_FILE);
+        let occupied = path.as_deref().is_some_and(Path::exists);
+
+        app.insert_resource(SaveSlot { path, occupied })
+            .add_systems(Update, continue_game.run_if(in_state(GameState::Title)));
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SaveGameError {
+    #[error("could not read or write saved game: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse saved game: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("could not serialize or deserialize saved game: {0}")]
+    Scene(#[from] ron::Error),
+    #[error("could not restore saved game: {0}")]
+    Spawn(#[from] SceneSpawnError),
+    #[error("saved game is on level {0}, which does not exist")]
+    NoSuchLevel(usize),
+}
+
+/// A solo game in progress, as a scene of the entities and resources needed to pick it up again
+pub struct SavedGame(pub DynamicScene);
+
+impl SavedGame {
+    /// Save the level being played in `world`
+    pub fn capture(world: &mut World) -> SavedGame {
+        let entities: Vec<Entity> = world
+            .query_filtered::<Entity, Or<(With<Paddle>, With<Ball>, With<Brick>)>>()
+            .iter(world)
+            .collect();
+
+        let scene = DynamicSceneBuilder::from_world(world)
+            .allow_component::<Transform>()
+            .allow_component::<Paddle>()
+            .allow_component::<Player>()
+            .allow_component::<Ball>()
+            .allow_component::<Velocity>()
+            .allow_component::<StuckToPaddle>()
+            .allow_component::<Brick>()
+            .allow_component::<Patrol>()
+            .allow_component::<Spin>()
+            .allow_resource::<Score>()
+            .allow_resource::<Lives>()
+            .allow_resource::<CurrentLevel>()
+            .allow_resource::<LevelStart>()
+            .allow_resource::<LevelClock>()
+            .extract_entities(entities.into_iter())
+            .extract_resources()
+            .build();
+
+        SavedGame(scene)
+    }
+
+    /// The contents of a saved game file, which `parse` reads back
+    pub fn to_ron(&self, type_registry: &TypeRegistry) -> Result<String, SaveGameError> {
+        Ok(self.0.serialize(type_registry)?)
+    }
+
+    pub fn parse(ron: &str, type_registry: &TypeRegistry) -> Result<SavedGame, SaveGameError> {
+        let mut deserializer = ron::de::Deserializer::from_str(ron)?;
+        let scene = SceneDeserializer { type_registry }.deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(SavedGame(scene))
+    }
+
+    /// Read and [`parse`](SavedGame::parse) a saved game file
+    pub fn load(path: &Path, type_registry: &TypeRegistry) -> Result<SavedGame, SaveGameError> {
+        SavedGame::parse(&std::fs::read_to_string(path)?, type_registry)
+    }
+
+    /// Write this game to `path`, creating its directory if needed
+    pub fn save(&self, path: &Path, type_registry: &TypeRegistry) -> Result<(), SaveGameError> {
+        let contents = self.to_ron(type_registry)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Continue this game in `world`. Its level starts paused, on the next update,
+    /// so that the player can get ready.
+    pub fn resume(self, world: &mut World) -> Result<(), SaveGameError> {
+        let DynamicScene {
+            resources,
+            entities,
+        } = self.0;
+
+        // The level is started from the saved score, lives and level,
+        // then its paddle, balls and bricks are swapped for the saved ones.
+        // Starting the level resets its start and clock, so those are put back along with them.
+        let (level_progress, resources) = resources.into_iter().partition(|resource| {
+            resource.get_represented_type_info().is_some_and(|info| {
+                [TypeId::of::<LevelStart>(), TypeId::of::<LevelClock>()].contains(&info.type_id())
+            })
+        });
+        let resources = DynamicScene {
+            resources,
+            entities: Vec::new(),
+        };
+        resources.write_to_world(world, &mut EntityHashMap::default())?;
+
+        let current_level = **world.resource::<CurrentLevel>();
+        if current_level >= world.resource::<Levels>().0.len() {
+            return Err(SaveGameError::NoSuchLevel(current_level));
+        }
+
+        world.insert_resource(ResumedGame(DynamicScene {
+            resources: level_progress,
+            entities,
+        }));
+        world.insert_resource(ContinuedLevel);
+        world.insert_resource(GameMode::Solo);
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Paused);
+        Ok(())
+    }
+}
+
+/// Whether the game being played can be saved.
+/// Continued games always play the bundled levels, so only solo games of those can be.
+pub(crate) fn can_save(game_mode: Res<GameMode>, custom_level: Option<Res<CustomLevel>>) -> bool {
+    *game_mode == GameMode::Solo && custom_level.is_none()
+}
+
+/// Where the game is saved, if the platform has a data directory,
+/// and whether there is a saved game there to continue
+#[derive(Resource)]
+pub(crate) struct SaveSlot {
+    path: Option<PathBuf>,
+    occupied: bool,
+}
+
+impl SaveSlot {
+    pub(crate) fn can_continue(&self) -> bool {
+        self.occupied
+    }
+}
+
+/// Present while playing a level that was continued from a saved game, rather than started afresh
+#[derive(Resource)]
+pub(crate) struct ContinuedLevel;
+
+// The saved paddle, balls and bricks of a game that is being continued,
+// and how far through its level it was
+#[derive(Resource)]
+struct ResumedGame(DynamicScene);
+
+// Swap the paddle, balls and bricks that the level started with for the saved ones,
+// and put back the level's start and clock
+fn restore_saved_entities(world: &mut World) {
+    let Some(ResumedGame(scene)) = world.remove_resource::<ResumedGame>() else {
+        return;
+    };
+
+    let spawned: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<Paddle>, With<Ball>, With<Brick>)>>()
+        .iter(world)
+        .collect();
+    for entity in spawned {
+        world.despawn(entity);
+    }
+
+    let mut entities = EntityHashMap::default();
+    if let Err(error) = scene.write_to_world(world, &mut entities) {
+        error!("Could not restore the saved game: {error}");
+        return;
+    }
+
+    // Like the level's own entities, these only last as long as the level
+    for &entity in entities.values() {
+        let mut entity = world.entity_mut(entity);
+        if !entity.contains::<Ball>() {
+            entity.insert(Collider);
+        }
+        entity.insert(StateScoped(InGame));
+    }
+}
+
+/// Save the game being played to the save slot, where it can be continued from the title screen
+pub(crate) fn save_to_slot(world: &mut World) {
+    let Some(path) = world.resource::<SaveSlot>().path.clone() else {
+        warn!("There is nowhere to save the game on this platform");
+        return;
+    };
+
+    let saved_game = SavedGame::capture(world);
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let result = saved_game.save(&path, &type_registry.read());
+
+    if let Err(error) = &result {
+        warn!("Could not save the game to {}: {error}", path.display());
+    }
+    world.resource_mut::<SaveSlot>().occupied = result.is_ok();
+}
+
+// A saved game can be continued once, so the file is removed as it is read
+fn continue_game(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    levels: Res<Levels>,
+    custom_level: Option<Res<CustomLevel>>,
+    mut save_slot: ResMut<SaveSlot>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyL)
+        || !save_slot.can_continue()
+        || custom_level.is_some()
+    {
+        return;
+    }
+    if !levels
+        .0
+        .iter()
+        .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    {
+        return;
+    }
+    let Some(path) = save_slot.path.clone() else {
+        return;
+    };
+
+    save_slot.occupied = false;
+    commands.queue(move |world: &mut World| {
+        let type_registry = world.resource::<AppTypeRegistry>().clone();
+        let saved_game = SavedGame::load(&path, &type_registry.read());
+        persist::remove(&path);
+
+        if let Err(error) = saved_game.and_then(|saved_game| saved_game.resume(world)) {
+            warn!("Could not continue the saved game: {error}");
+        }
+    });
+}
@@ -0,0 +1,246 @@
+//! How many points the player gets for what.
+//!
+//! The collision systems only report which bricks were hit and destroyed.
+//! The rules here turn those into [`ScoreEvent`]s, which are then added to the [`Score`]:
+//! bricks hit one after another without the ball touching the paddle build up a combo
+//! that multiplies the points for each brick, and destroying the last brick in a row
+//! is worth a bonus.
+
+use bevy::prelude::*;
+
+use crate::{
+    Brick, BrickDestroyed, BrickHit, ColliderKind, CollisionEvent, Score,
+    versus::{Player, Standing},
+};
+
+/// Points for destroying the last breakable brick in a row
+pub const ROW_CLEAR_BONUS: usize = 10;
+// Every this many bricks hit in a row adds one to the combo multiplier
+const COMBO_HITS_PER_STEP: u32 = 3;
+const MAX_COMBO_MULTIPLIER: u32 = 5;
+// Bricks whose centers are this close vertically are in the same row
+const ROW_TOLERANCE: f32 = 1.0;
+
+/// Sent for every award of points, which the [`Score`] is the total of.
+/// In versus matches, each player's [`Standing`] totals their own points instead.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ScoreEvent {
+    pub points: usize,
+    pub player: Player,
+    /// Where in the arena the points were earned
+    pub position: Vec2,
+    pub reason: ScoreReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreReason {
+    /// A brick was destroyed, with this combo multiplier applied to its points
+    Brick {
+        multiplier: u32,
+    },
+    RowCleared,
+}
+
+/// The number of bricks hit since a ball last bounced off the paddle
+#[derive(Resource, Debug, Default)]
+pub struct Combo {
+    hits: u32,
+}
+
+impl Combo {
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// How many times their usual points bricks are worth
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.hits / COMBO_HITS_PER_STEP).min(MAX_COMBO_MULTIPLIER)
+    }
+
+    pub fn reset(&mut self) {
+        self.hits = 0;
+    }
+}
+
+// Turn this step's brick hits and destroyed bricks into points.
+// Bricks destroyed by the hit that completed a combo step already get the higher multiplier.
+pub(crate) fn award_points(
+    mut combo: ResMut<Combo>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut brick_hits: EventReader<BrickHit>,
+    mut destroyed_events: EventReader<BrickDestroyed>,
+    brick_query: Query<(&Transform, &Brick)>,
+    mut score_events: EventWriter<ScoreEvent>,
+) {
+    if collision_events
+        .read()
+        .filter(|collision| collision.collider == ColliderKind::Paddle)
+        .count()
+        > 0
+    {
+        combo.reset();
+    }
+    combo.hits += brick_hits.read().count() as u32;
+
+    let multiplier = combo.multiplier();
+    let mut cleared_rows: Vec<f32> = Vec::new();
+    for destroyed in destroyed_events.read() {
+        score_events.write(ScoreEvent {
+            points: destroyed.score * multiplier as usize,
+            player: destroyed.player,
+            position: destroyed.position,
+            reason: ScoreReason::Brick { multiplier },
+        });
+
+        // The destroyed bricks have already been despawned,
+        // so the row is clear if no breakable brick is left at its height
+        let row = destroyed.position.y;
+        let in_row = |y: f32| (y - row).abs() < ROW_TOLERANCE;
+        if cleared_rows.iter().any(|&y| in_row(y))
+            || brick_query.iter().any(|(transform, brick)| {
+                brick.kind.is_breakable() && in_row(transform.translation.y)
+            })
+        {
+            continue;
+        }
+
+        cleared_rows.push(row);
+        score_events.write(ScoreEvent {
+            points: ROW_CLEAR_BONUS,
+            player: destroyed.player,
+            position: destroyed.position,
+            reason: ScoreReason::RowCleared,
+        });
+    }
+}
+
+pub(crate) fn add_score(
+    mut score_events: EventReader<ScoreEvent>,
+    mut score: ResMut<Score>,
+    mut standings: Query<(&Player, &mut Standing)>,
+) {
+    for event in score_events.read() {
+        match standings
+            .iter_mut()
+            .find(|(player, _)| **player == event.player)
+        {
+            Some((_, mut standing)) => standing.score += event.points,
+            None => **score += event.points,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::BrickKind;
+
+    fn scoring_app() -> App {
+        let mut app = App::new();
+        app.insert_resource(Score(0))
+            .init_resource::<Combo>()
+            .add_event::<CollisionEvent>()
+            .add_event::<BrickHit>()
+            .add_event::<BrickDestroyed>()
+            .add_event::<ScoreEvent>()
+            .add_systems(Update, (award_points, add_score).chain());
+        app
+    }
+
+    fn spawn_brick(app: &mut App, position: Vec2) -> Entity {
+        app.world_mut()
+            .spawn((
+                Transform::from_translation(position.extend(0.0)),
+                Brick::new(BrickKind::Normal),
+            ))
+            .id()
+    }
+
+    // Hit and destroy a brick, as `damage_bricks` would
+    fn destroy_brick(app: &mut App, brick: Entity) {
+        let world = app.world_mut();
+        let position = world
+            .get::<Transform>(brick)
+            .unwrap()
+            .translation
+            .truncate();
+        let &Brick { kind, score, .. } = world.get::<Brick>(brick).unwrap();
+        world.despawn(brick);
+        world.send_event(BrickHit(brick, Player::One));
+        world.send_event(BrickDestroyed {
+            position,
+            kind,
+            score,
+            player: Player::One,
+        });
+        app.update();
+    }
+
+    fn score(app: &App) -> usize {
+        **app.world().resource::<Score>()
+    }
+
+    #[test]
+    fn combo_multiplies_points_until_the_paddle_is_touched() {
+        let mut app = scoring_app();
+        // A brick in every row, with another brick kept in each row so no bonus is given
+        let bricks: Vec<Entity> = (0..8)
+            .map(|row| {
+                let position = Vec2::new(0.0, row as f32 * 50.0);
+                spawn_brick(&mut app, position + Vec2::X * 100.0);
+                spawn_brick(&mut app, position)
+            })
+            .collect();
+
+        for &brick in &bricks[..7] {
+            destroy_brick(&mut app, brick);
+        }
+        // Two hits at x1, three at x2 and two at x3
+        assert_eq!(score(&app), 2 + 3 * 2 + 2 * 3);
+        assert_eq!(app.world().resource::<Combo>().multiplier(), 3);
+
+        app.world_mut().send_event(CollisionEvent {
+            ball: Entity::PLACEHOLDER,
+            collider: ColliderKind::Paddle,
+            normal: Vec2::Y,
+            impact_speed: 400.0,
+        });
+        destroy_brick(&mut app, bricks[7]);
+        assert_eq!(score(&app), 2 + 3 * 2 + 2 * 3 + 1);
+        assert_eq!(app.world().resource::<Combo>().hits(), 1);
+    }
+
+    #[test]
+    fn combo_multiplier_is_capped() {
+        let mut combo = Combo {
+            hits: COMBO_HITS_PER_STEP * 100,
+        };
+        assert_eq!(combo.multiplier(), MAX_COMBO_MULTIPLIER);
+
+        combo.reset();
+        assert_eq!(combo.multiplier(), 1);
+    }
+
+    #[test]
+    fn clearing_a_row_earns_a_bonus() {
+        let mut app = scoring_app();
+        let first = spawn_brick(&mut app, Vec2::new(0.0, 100.0));
+        let last = spawn_brick(&mut app, Vec2::new(100.0, 100.0));
+        // Neither bricks in other rows nor unbreakable bricks keep the row from being cleared
+        spawn_brick(&mut app, Vec2::new(0.0, 150.0));
+        app.world_mut().spawn((
+            Transform::from_xyz(200.0, 100.0, 0.0),
+            Brick::new(BrickKind::Unbreakable),
+        ));
+
+        destroy_brick(&mut app, first);
+        assert_eq!(score(&app), 1);
+        destroy_brick(&mut app, last);
+        assert_eq!(score(&app), 1 + 1 + ROW_CLEAR_BONUS);
+
+        let events = app.world().resource::<Events<ScoreEvent>>();
+        let mut reader = events.get_cursor();
+        let reasons: Vec<ScoreReason> = reader.read(events).map(|event| event.reason).collect();
+        assert!(reasons.contains(&ScoreReason::RowCleared));
+    }
+}
@@ -0,0 +1,292 @@
+//! The settings screen, reached from the pause menu, and the settings only it changes.
+//!
+//! Each setting is saved to the user's config directory as soon as it changes,
+//! in the file of the plugin that owns it: the [`Mixer`] in `mixer.ron`,
+//! the paddle sensitivity in `input.ron` and reduce motion in `accessibility.ron`.
+//! The difficulty and [`WindowSettings`] are kept here.
+
+use std::path::PathBuf;
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    window::{MonitorSelection, PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    difficulty::Difficulty,
+    effects::Accessibility,
+    input::{InputBindings, MAX_PADDLE_SENSITIVITY, MIN_PADDLE_SENSITIVITY},
+    menu::{MenuAction, MenuChoice, MenuItem, PauseMenu, menu},
+    persist,
+    sound::Mixer,
+    state::screen,
+};
+
+const DIFFICULTY_CONFIG_FILE: &str = "difficulty.ron";
+const WINDOW_CONFIG_FILE: &str = "window.ron";
+
+const VOLUME_INCREMENT: f32 = 0.1;
+const PADDLE_SENSITIVITY_INCREMENT: f32 = 0.1;
+
+/// Loads the saved difficulty and [`WindowSettings`], and adds the settings screen
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let difficulty_path = persist::config_path(DIFFICULTY_CONFIG_FILE);
+        let difficulty: Difficulty = difficulty_path
+            .as_deref()
+            .and_then(persist::load)
+            .unwrap_or_default();
+        let window_path = persist::config_path(WINDOW_CONFIG_FILE);
+        let window_settings: WindowSettings = window_path
+            .as_deref()
+            .and_then(persist::load)
+            .unwrap_or_default();
+
+        app.insert_resource(difficulty)
+            .insert_resource(DifficultyConfigPath(difficulty_path))
+            .insert_resource(window_settings)
+            .insert_resource(WindowConfigPath(window_path))
+            .add_systems(OnEnter(PauseMenu::Settings), spawn_settings_screen)
+            .add_systems(
+                Update,
+                (change_settings, update_setting_labels)
+                    .chain()
+                    .run_if(in_state(PauseMenu::Settings)),
+            )
+            .add_systems(
+                Update,
+                apply_window_settings.run_if(resource_changed::<WindowSettings>),
+            )
+            .add_systems(
+                Update,
+                save_difficulty
+                    .run_if(resource_changed::<Difficulty>.and(not(resource_added::<Difficulty>))),
+            )
+            .add_systems(
+                Update,
+                save_window_settings.run_if(
+                    resource_changed::<WindowSettings>.and(not(resource_added::<WindowSettings>)),
+                ),
+            );
+    }
+}
+
+/// How the game's window is shown.
+///
+/// Saved as `window.ron` in the user's config directory. Missing fields use their defaults.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    /// Fill the monitor the window is on, without borders
+    pub fullscreen: bool,
+}
+
+/// Where the difficulty is saved, if the platform has a config directory
+#[derive(Resource)]
+struct DifficultyConfigPath(Option<PathBuf>);
+
+/// Where the window settings are saved, if the platform has a config directory
+#[derive(Resource)]
+struct WindowConfigPath(Option<PathBuf>);
+
+/// The things that can be changed on the settings screen, in the order they are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    MasterVolume,
+    EffectsVolume,
+    MusicVolume,
+    Difficulty,
+    PaddleSensitivity,
+    Fullscreen,
+    ReduceMotion,
+}
+
+impl Setting {
+    const ALL: [Setting; 7] = [
+        Setting::MasterVolume,
+        Setting::EffectsVolume,
+        Setting::MusicVolume,
+        Setting::Difficulty,
+        Setting::PaddleSensitivity,
+        Setting::Fullscreen,
+        Setting::ReduceMotion,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Setting::MasterVolume => "Master volume",
+            Setting::EffectsVolume => "Effects volume",
+            Setting::MusicVolume => "Music volume",
+            Setting::Difficulty => "Difficulty",
+            Setting::PaddleSensitivity => "Paddle sensitivity",
+            Setting::Fullscreen => "Fullscreen",
+            Setting::ReduceMotion => "Reduce motion",
+        }
+    }
+}
+
+/// Every resource that the settings screen changes
+#[derive(SystemParam)]
+struct Settings<'w> {
+    mixer: ResMut<'w, Mixer>,
+    difficulty: ResMut<'w, Difficulty>,
+    bindings: ResMut<'w, InputBindings>,
+    window: ResMut<'w, WindowSettings>,
+    accessibility: ResMut<'w, Accessibility>,
+}
+
+impl Settings<'_> {
+    /// The label of `setting`'s menu item, with its current value
+    fn describe(&self, setting: Setting) -> String {
+        let on_off = |on: bool| if on { "On" } else { "Off" };
+        let percent = |volume: f32| format!("{:.0}%", volume * 100.0);
+
+        let value = match setting {
+            Setting::MasterVolume => percent(self.mixer.master),
+            Setting::EffectsVolume => percent(self.mixer.sfx),
+            Setting::MusicVolume => percent(self.mixer.music),
+            Setting::Difficulty => self.difficulty.name().to_string(),
+            Setting::PaddleSensitivity => format!("{:.1}x", self.bindings.sensitivity()),
+            Setting::Fullscreen => on_off(self.window.fullscreen).to_string(),
+            Setting::ReduceMotion => on_off(self.accessibility.reduce_motion).to_string(),
+        };
+        format!("{}: {value}", setting.name())
+    }
+
+    /// Change `setting` by `step`, see [`MenuChoice::step`]
+    fn change(&mut self, setting: Setting, step: i32) {
+        match setting {
+            Setting::MasterVolume => {
+                self.mixer.master = step_value(self.mixer.master, step, VOLUME_INCREMENT, 0.0, 1.0);
+            }
+            Setting::EffectsVolume => {
+                self.mixer.sfx = step_value(self.mixer.sfx, step, VOLUME_INCREMENT, 0.0, 1.0);
+            }
+            Setting::MusicVolume => {
+                self.mixer.music = step_value(self.mixer.music, step, VOLUME_INCREMENT, 0.0, 1.0);
+            }
+            Setting::Difficulty if step < 0 => *self.difficulty = self.difficulty.previous(),
+            Setting::Difficulty => *self.difficulty = self.difficulty.next(),
+            Setting::PaddleSensitivity => {
+                self.bindings.paddle_sensitivity = step_value(
+                    self.bindings.sensitivity(),
+                    step,
+                    PADDLE_SENSITIVITY_INCREMENT,
+                    MIN_PADDLE_SENSITIVITY,
+                    MAX_PADDLE_SENSITIVITY,
+                );
+            }
+            Setting::Fullscreen => self.window.fullscreen = !self.window.fullscreen,
+            Setting::ReduceMotion => {
+                self.accessibility.reduce_motion = !self.accessibility.reduce_motion;
+            }
+        }
+    }
+}
+
+/// Move `value` by `increment` in the direction of `step`, staying between `min` and `max`.
+/// Choosing the item (a `step` of 0) goes up instead, wrapping around to `min` after `max`.
+fn step_value(value: f32, step: i32, increment: f32, min: f32, max: f32) -> f32 {
+    let stepped = if step == 0 {
+        if value + increment > max + increment / 2.0 {
+            min
+        } else {
+            value + increment
+        }
+    } else {
+        value + step.signum() as f32 * increment
+    };
+
+    // Snap to a whole number of increments, so repeated steps don't drift
+    ((stepped / increment).round() * increment).clamp(min, max)
+}
+
+fn spawn_settings_screen(mut commands: Commands, settings: Settings) {
+    let mut items: Vec<(MenuAction, String)> = Setting::ALL
+        .iter()
+        .map(|&setting| (MenuAction::Change(setting), settings.describe(setting)))
+        .collect();
+    items.push((MenuAction::Back, "Back".to_string()));
+
+    commands.spawn(screen(PauseMenu::Settings, "Settings", menu(items)));
+}
+
+fn change_settings(
+    mut choices: EventReader<MenuChoice>,
+    mut settings: Settings,
+    mut next_menu: ResMut<NextState<PauseMenu>>,
+) {
+    for choice in choices.read() {
+        match choice.action {
+            MenuAction::Change(setting) => settings.change(setting, choice.step),
+            MenuAction::Back if choice.step == 0 => next_menu.set(PauseMenu::Main),
+            _ => {}
+        }
+    }
+}
+
+fn update_setting_labels(settings: Settings, mut items: Query<(&MenuItem, &mut Text)>) {
+    for (item, mut text) in &mut items {
+        if let MenuAction::Change(setting) = item.action {
+            let label = settings.describe(setting);
+            if text.0 != label {
+                text.0 = label;
+            }
+        }
+    }
+}
+
+fn apply_window_settings(
+    window_settings: Res<WindowSettings>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+) {
+    window.mode = if window_settings.fullscreen {
+        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+    } else {
+        WindowMode::Windowed
+    };
+}
+
+fn save_difficulty(difficulty: Res<Difficulty>, config_path: Res<DifficultyConfigPath>) {
+    if let Some(path) = &config_path.0 {
+        persist::save(path, &*difficulty);
+    }
+}
+
+fn save_window_settings(window_settings: Res<WindowSettings>, config_path: Res<WindowConfigPath>) {
+    if let Some(path) = &config_path.0 {
+        persist::save(path, &*window_settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows_stop_at_the_ends_and_choosing_wraps_around() {
+        assert_eq!(step_value(0.5, 1, 0.1, 0.0, 1.0), 0.6);
+        assert_eq!(step_value(0.5, -1, 0.1, 0.0, 1.0), 0.4);
+        assert_eq!(step_value(1.0, 1, 0.1, 0.0, 1.0), 1.0);
+        assert_eq!(step_value(0.0, -1, 0.1, 0.0, 1.0), 0.0);
+
+        assert_eq!(step_value(0.9, 0, 0.1, 0.0, 1.0), 1.0);
+        assert_eq!(step_value(1.0, 0, 0.1, 0.0, 1.0), 0.0);
+        assert_eq!(step_value(1.5, 0, 0.1, 0.5, 1.5), 0.5);
+    }
+
+    #[test]
+    fn steps_snap_to_whole_increments() {
+        let mut volume = 0.0;
+        for _ in 0..10 {
+            volume = step_value(volume, 1, 0.1, 0.0, 1.0);
+        }
+        assert_eq!(volume, 1.0);
+        // A hand-edited value lands back on the grid
+        assert_eq!(step_value(0.43, 1, 0.1, 0.0, 1.0), 0.5);
+    }
+}
@@ -0,0 +1,204 @@
+//! Sound effects for every kind of bounce, background music, and the volume [`Mixer`].
+//!
+//! Bricks use the game's collision sound, rising in pitch as the combo builds.
+//! The other bounces and the music are played by the [`synth`](crate::synth).
+
+use std::{path::PathBuf, sync::Arc};
+
+use bevy::{
+    audio::{AddAudioSource, Volume},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BALL_SPEED, ColliderKind, CollisionEvent, persist,
+    scoring::{Combo, award_points},
+    synth::{Note, Tune, Waveform},
+};
+
+const MIXER_CONFIG_FILE: &str = "mixer.ron";
+
+// Each brick hit in a combo raises the brick sound by a semitone, up to an octave
+const MAX_COMBO_SEMITONES: u32 = 12;
+// Bounces this fast or faster play at full volume, and slower ones more quietly, down to this
+const FULL_VOLUME_IMPACT_SPEED: f32 = BALL_SPEED;
+const MIN_IMPACT_VOLUME: f32 = 0.3;
+
+// The music is an arpeggio over A minor, F, C and G, one bar each, in semitones from A4
+const MUSIC_NOTE_LENGTH: f32 = 0.16;
+const MUSIC_ARPEGGIOS: [[i32; 3]; 4] =
+    [[-12, -9, -5], [-16, -12, -9], [-9, -5, -2], [-14, -10, -7]];
+
+/// Plays a sound for every bounce and loops the music, at the volumes in the [`Mixer`]
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        let config_path = persist::config_path(MIXER_CONFIG_FILE);
+        let mixer = config_path
+            .as_deref()
+            .and_then(persist::load)
+            .unwrap_or_default();
+
+        app.add_audio_source::<Tune>()
+            .insert_resource::<Mixer>(mixer)
+            .insert_resource(MixerConfigPath(config_path))
+            .add_systems(Startup, (load_sounds, start_music).chain())
+            .add_systems(FixedUpdate, play_collision_sounds.after(award_points))
+            .add_systems(
+                Update,
+                (update_music_volume, save_mixer)
+                    .run_if(resource_changed::<Mixer>.and(not(resource_added::<Mixer>))),
+            );
+    }
+}
+
+/// How loud each kind of sound is, from 0.0 for silent to 1.0 for full volume
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mixer {
+    /// Scales both of the others
+    pub master: f32,
+    pub sfx: f32,
+    pub music: f32,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Mixer {
+            master: 1.0,
+            sfx: 1.0,
+            music: 0.4,
+        }
+    }
+}
+
+impl Mixer {
+    pub fn sfx_volume(&self) -> Volume {
+        Volume::Linear(self.master * self.sfx)
+    }
+
+    pub fn music_volume(&self) -> Volume {
+        Volume::Linear(self.master * self.music)
+    }
+}
+
+/// Where the mixer settings are saved, if the platform has a config directory
+#[derive(Resource)]
+struct MixerConfigPath(Option<PathBuf>);
+
+#[derive(Resource)]
+struct CollisionSounds {
+    wall: Handle<Tune>,
+    paddle: Handle<Tune>,
+    brick: Handle<AudioSource>,
+    unbreakable_brick: Handle<Tune>,
+}
+
+#[derive(Resource, Deref)]
+struct MusicTune(Handle<Tune>);
+
+#[derive(Component)]
+struct Music;
+
+fn blip(waveform: Waveform, from: f32, to: f32, duration: f32) -> Tune {
+    Tune {
+        waveform,
+        decay: 0.999,
+        notes: Arc::new([Note::slide(from, to, duration)]),
+    }
+}
+
+fn load_sounds(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut tunes: ResMut<Assets<Tune>>,
+) {
+    commands.insert_resource(CollisionSounds {
+        wall: tunes.add(blip(Waveform::Sine, 520.0, 480.0, 0.06)),
+        paddle: tunes.add(blip(Waveform::Square, 300.0, 420.0, 0.09)),
+        brick: asset_server.load("sounds/breakout_collision.ogg"),
+        // A dull clunk, so it's clear the brick didn't break
+        unbreakable_brick: tunes.add(blip(Waveform::Square, 140.0, 110.0, 0.12)),
+    });
+
+    let notes: Vec<Note> = MUSIC_ARPEGGIOS
+        .iter()
+        .flat_map(|&[root, third, fifth]| {
+            // Seven notes and a rest to the bar
+            [root, third, fifth, third, root + 12, fifth, third]
+                .map(|semitones| Note::semitones(semitones, MUSIC_NOTE_LENGTH))
+                .into_iter()
+                .chain([Note::rest(MUSIC_NOTE_LENGTH)])
+        })
+        .collect();
+    commands.insert_resource(MusicTune(tunes.add(Tune {
+        waveform: Waveform::Triangle,
+        decay: 0.95,
+        notes: notes.into(),
+    })));
+}
+
+fn start_music(mut commands: Commands, music: Res<MusicTune>, mixer: Res<Mixer>) {
+    commands.spawn((
+        AudioPlayer(music.clone()),
+        PlaybackSettings::LOOP.with_volume(mixer.music_volume()),
+        Music,
+    ));
+}
+
+// Play one sound for each kind of collider bounced off this step, as loud as the hardest bounce
+fn play_collision_sounds(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    sounds: Res<CollisionSounds>,
+    combo: Res<Combo>,
+    mixer: Res<Mixer>,
+) {
+    let mut impacts: Vec<(ColliderKind, f32)> = Vec::new();
+    for collision in collision_events.read() {
+        match impacts
+            .iter_mut()
+            .find(|(collider, _)| *collider == collision.collider)
+        {
+            Some((_, speed)) => *speed = speed.max(collision.impact_speed),
+            None => impacts.push((collision.collider, collision.impact_speed)),
+        }
+    }
+
+    for (collider, impact_speed) in impacts {
+        let loudness = (impact_speed / FULL_VOLUME_IMPACT_SPEED).clamp(MIN_IMPACT_VOLUME, 1.0);
+        let settings =
+            PlaybackSettings::DESPAWN.with_volume(mixer.sfx_volume() * Volume::Linear(loudness));
+
+        match collider {
+            ColliderKind::Wall | ColliderKind::Obstacle => {
+                commands.spawn((AudioPlayer(sounds.wall.clone()), settings))
+            }
+            ColliderKind::Paddle => commands.spawn((AudioPlayer(sounds.paddle.clone()), settings)),
+            ColliderKind::Brick => {
+                let semitones = combo.hits().saturating_sub(1).min(MAX_COMBO_SEMITONES);
+                commands.spawn((
+                    AudioPlayer(sounds.brick.clone()),
+                    settings.with_speed(2f32.powf(semitones as f32 / 12.0)),
+                ))
+            }
+            ColliderKind::UnbreakableBrick => {
+                commands.spawn((AudioPlayer(sounds.unbreakable_brick.clone()), settings))
+            }
+        };
+    }
+}
+
+fn update_music_volume(mixer: Res<Mixer>, mut music: Query<&mut AudioSink, With<Music>>) {
+    for mut sink in &mut music {
+        sink.set_volume(mixer.music_volume());
+    }
+}
+
+fn save_mixer(mixer: Res<Mixer>, config_path: Res<MixerConfigPath>) {
+    if let Some(path) = &config_path.0 {
+        persist::save(path, &*mixer);
+    }
+}
@@ -0,0 +1,432 @@
+use std::time::Duration;
+
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{
+    CurrentLevel, Level, Levels, Lives, STARTING_LIVES, Score, TEXT_COLOR,
+    campaign::{LevelResult, star_text, time_text},
+    difficulty::Difficulty,
+    editor::is_test_playing,
+    effects::Accessibility,
+    input::MenuInput,
+    level::CustomLevel,
+    menu::PauseMenu,
+    save_game::SaveSlot,
+    versus::{GameMode, MatchResult, Player, is_versus},
+};
+
+const SCREEN_TITLE_FONT_SIZE: f32 = 60.0;
+const SCREEN_PROMPT_FONT_SIZE: f32 = 25.0;
+const SCREEN_BACKGROUND_COLOR: Color = Color::srgba(0.9, 0.9, 0.9, 0.8);
+
+/// The overall flow of the game
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[states(scoped_entities)]
+pub enum GameState {
+    #[default]
+    Title,
+    /// Rebinding the paddle controls, reached from the title screen
+    Controls,
+    /// Designing a level in the level editor, also reached from the title screen
+    Editor,
+    /// Choosing a level of the campaign to play, also reached from the title screen
+    LevelSelect,
+    Playing,
+    /// Showing the pause menu, see [`PauseMenu`]
+    Paused,
+    /// Passed through on the way from the pause menu back to the start of the level,
+    /// so that the level is spawned afresh
+    Restarting,
+    LevelCleared,
+    GameOver,
+}
+
+/// Active while a level is on screen, whether or not it is paused.
+///
+/// The paddle, ball and bricks are scoped to this state,
+/// so pausing keeps them around but clearing the level or losing despawns them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = GameState;
+
+    fn compute(sources: GameState) -> Option<Self> {
+        match sources {
+            GameState::Playing | GameState::Paused => Some(InGame),
+            _ => None,
+        }
+    }
+}
+
+/// Adds the game states, which the simulation needs to know when a level is being played
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .add_computed_state::<InGame>()
+            .enable_state_scoped_entities::<InGame>()
+            .init_resource::<LevelClock>()
+            .add_systems(OnEnter(InGame), (remember_level_start, reset_level_clock))
+            .add_systems(
+                FixedUpdate,
+                tick_level_clock.run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::Restarting), restart_level);
+    }
+}
+
+/// The score and lives when the level started, which restarting it goes back to
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub(crate) struct LevelStart {
+    pub(crate) score: usize,
+    pub(crate) lives: u32,
+}
+
+/// How long the current level has been played for, not counting time spent paused
+#[derive(Resource, Default, Deref, Reflect)]
+#[reflect(Resource)]
+pub struct LevelClock(Duration);
+
+pub(crate) fn remember_level_start(mut commands: Commands, score: Res<Score>, lives: Res<Lives>) {
+    commands.insert_resource(LevelStart {
+        score: **score,
+        lives: **lives,
+    });
+}
+
+pub(crate) fn reset_level_clock(mut clock: ResMut<LevelClock>) {
+    clock.0 = Duration::ZERO;
+}
+
+fn tick_level_clock(mut clock: ResMut<LevelClock>, time: Res<Time>) {
+    clock.0 += time.delta();
+}
+
+fn restart_level(
+    level_start: Option<Res<LevelStart>>,
+    mut score: ResMut<Score>,
+    mut lives: ResMut<Lives>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if let Some(level_start) = level_start {
+        **score = level_start.score;
+        **lives = level_start.lives;
+    }
+    next_state.set(GameState::Playing);
+}
+
+/// Adds the screens shown between levels, and the menu input that moves between them
+pub struct ScreensPlugin;
+
+impl Plugin for ScreensPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Title), spawn_title_screen)
+            .add_systems(
+                Update,
+                (
+                    update_title_prompt,
+                    start_game,
+                    open_controls,
+                    change_difficulty,
+                )
+                    .run_if(in_state(GameState::Title)),
+            )
+            // The settings screen uses Esc to go back to the pause menu instead
+            .add_systems(
+                Update,
+                toggle_pause.run_if(in_state(InGame).and(not(in_state(PauseMenu::Settings)))),
+            )
+            // Test plays from the level editor go straight back to the editor
+            .add_systems(
+                OnEnter(GameState::LevelCleared),
+                spawn_level_cleared_screen.run_if(not(is_test_playing)),
+            )
+            .add_systems(Update, next_level.run_if(in_state(GameState::LevelCleared)))
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                spawn_match_over_screen.run_if(is_versus),
+            );
+        // The solo game-over screen is added by `HighScorePlugin`, since it shows the high scores
+    }
+}
+
+#[derive(Component)]
+struct TitlePrompt;
+
+/// A full-screen overlay with a heading and a prompt, despawned when leaving `state`
+pub fn screen<S: States>(state: S, heading: impl Into<String>, prompt: impl Bundle) -> impl Bundle {
+    (
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(20.0),
+            ..default()
+        },
+        BackgroundColor(SCREEN_BACKGROUND_COLOR),
+        StateScoped(state),
+        children![
+            (
+                Text::new(heading),
+                TextFont {
+                    font_size: SCREEN_TITLE_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ),
+            prompt,
+        ],
+    )
+}
+
+pub fn prompt(text: impl Into<String>) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: SCREEN_PROMPT_FONT_SIZE,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    )
+}
+
+fn spawn_title_screen(mut commands: Commands) {
+    commands.spawn(screen(
+        GameState::Title,
+        "Breakout",
+        (prompt("Loading levels..."), TitlePrompt),
+    ));
+}
+
+// Levels load in the background, so tell the player when they can start, or why they can't
+#[allow(clippy::too_many_arguments)]
+fn update_title_prompt(
+    asset_server: Res<AssetServer>,
+    levels: Res<Levels>,
+    menu_input: MenuInput,
+    difficulty: Res<Difficulty>,
+    accessibility: Res<Accessibility>,
+    save_slot: Res<SaveSlot>,
+    custom_level: Option<Res<CustomLevel>>,
+    mut title_prompt: Single<&mut Text, With<TitlePrompt>>,
+) {
+    let mut loading = false;
+
+    for handle in &levels.0 {
+        match asset_server.load_state(handle) {
+            LoadState::Failed(error) => {
+                title_prompt.0 = format!("Could not load a level:\n{error}");
+                return;
+            }
+            LoadState::Loaded => {}
+            _ => loading = true,
+        }
+    }
+
+    // Continued games and the campaign play the bundled levels, not a level from the command line
+    let continue_line = if save_slot.can_continue() && custom_level.is_none() {
+        "\nPress L to continue your saved game"
+    } else {
+        ""
+    };
+    let level_select_line = if custom_level.is_none() {
+        "\nPress S to choose a level"
+    } else {
+        ""
+    };
+
+    title_prompt.0 = if loading {
+        "Loading levels...".to_string()
+    } else {
+        format!(
+            "Press {} to start{continue_line}{level_select_line}\nPress V for a two-player match\nPress E to edit a level\nPress C to change the controls\nPress D to change the difficulty: {}\nPress M to reduce motion: {}",
+            menu_input.confirm_name(),
+            difficulty.name(),
+            if accessibility.reduce_motion {
+                "On"
+            } else {
+                "Off"
+            }
+        )
+    };
+}
+
+// Start from the first level, alone or in a versus match
+#[allow(clippy::too_many_arguments)]
+fn start_game(
+    menu_input: MenuInput,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    levels: Res<Levels>,
+    mut game_mode: ResMut<GameMode>,
+    mut score: ResMut<Score>,
+    mut lives: ResMut<Lives>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let mode = if menu_input.confirm() {
+        GameMode::Solo
+    } else if keyboard_input.just_pressed(KeyCode::KeyV) {
+        GameMode::Versus
+    } else {
+        return;
+    };
+
+    if !levels
+        .0
+        .iter()
+        .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    {
+        return;
+    }
+
+    *game_mode = mode;
+    **score = 0;
+    **lives = STARTING_LIVES;
+    **current_level = 0;
+    next_state.set(GameState::Playing);
+}
+
+fn open_controls(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyC) {
+        next_state.set(GameState::Controls);
+    }
+}
+
+fn change_difficulty(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyD) {
+        *difficulty = difficulty.next();
+    }
+}
+
+pub(crate) fn toggle_pause(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::KeyP])
+        && !gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::Start))
+    {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+pub(crate) fn spawn_level_cleared_screen(
+    mut commands: Commands,
+    menu_input: MenuInput,
+    level_assets: Res<Assets<Level>>,
+    levels: Res<Levels>,
+    current_level: Res<CurrentLevel>,
+    custom_level: Option<Res<CustomLevel>>,
+    level_result: Option<Res<LevelResult>>,
+) {
+    let next_level = levels
+        .0
+        .get(**current_level + 1)
+        .and_then(|handle| level_assets.get(handle));
+
+    // Campaign levels say how well they went, see `campaign::record_level_result`
+    let result = level_result.map_or(String::new(), |result| {
+        let stars = result.stars.map_or(String::new(), |(stars, out_of)| {
+            format!("  {}", star_text(stars, out_of))
+        });
+        let best = if result.new_best { "  New best!" } else { "" };
+        format!(
+            "{} points in {}{stars}{best}\n",
+            result.score,
+            time_text(result.time)
+        )
+    });
+
+    let confirm = menu_input.confirm_name();
+    let text = match next_level {
+        _ if custom_level.is_some() => format!("Well done!\nPress {confirm} to continue"),
+        Some(level) => format!(
+            "{result}Next up: {}\nPress {confirm} to continue",
+            level.name
+        ),
+        None => format!("{result}You cleared every level!\nPress {confirm} to continue"),
+    };
+
+    commands.spawn(screen(
+        GameState::LevelCleared,
+        "Level cleared",
+        prompt(text),
+    ));
+}
+
+// A custom level is the whole game, so clearing it ends the game
+fn next_level(
+    menu_input: MenuInput,
+    levels: Res<Levels>,
+    custom_level: Option<Res<CustomLevel>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !menu_input.confirm() {
+        return;
+    }
+
+    if custom_level.is_none() && **current_level + 1 < levels.0.len() {
+        **current_level += 1;
+        next_state.set(GameState::Playing);
+    } else {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+// Versus matches don't go on the high-score table, so just say who won
+fn spawn_match_over_screen(
+    mut commands: Commands,
+    menu_input: MenuInput,
+    match_result: Option<Res<MatchResult>>,
+) {
+    let Some(match_result) = match_result else {
+        return;
+    };
+
+    let heading = match match_result.winner {
+        Some(player) => format!("{} wins!", player.name()),
+        None => "It's a draw".to_string(),
+    };
+    let scores: Vec<String> = Player::ALL
+        .iter()
+        .map(|player| {
+            format!(
+                "{}: {} points",
+                player.name(),
+                match_result.scores[player.index()]
+            )
+        })
+        .collect();
+
+    commands.spawn(screen(
+        GameState::GameOver,
+        heading,
+        prompt(format!(
+            "{}\nPress {} to return to the title screen",
+            scores.join("\n"),
+            menu_input.confirm_name()
+        )),
+    ));
+}
@@ -33,7 +33,7 @@ impl SteppingPlugin {
 impl Plugin for SteppingPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, build_stepping_hint);
-        if cfg!(not(feature = "bevy_debug_stepping")) {
+        if cfg!(not(feature = "stepping")) {
             return;
         }
 
@@ -178,10 +178,10 @@ fn build_ui(
 }
 
 fn build_stepping_hint(mut commands: Commands) {
-    let hint_text = if cfg!(feature = "bevy_debug_stepping") {
+    let hint_text = if cfg!(feature = "stepping") {
         "Press ` to toggle stepping mode (S: step system, Space: step frame)"
     } else {
-        "Bevy was compiled without stepping support. Run with `--features=bevy_debug_stepping` to enable stepping."
+        "Bevy was compiled without stepping support. Run with `--features=stepping` to enable stepping."
     };
     info!("{}", hint_text);
     // stepping description box
@@ -0,0 +1,211 @@
+//! A tiny synthesizer for the game's blips and music, so they don't need sound files.
+//!
+//! A [`Tune`] is a list of notes played one after another with a single waveform.
+//! Each note starts at full volume and decays, which is enough for both sound effects
+//! and a chiptune-style loop.
+
+use std::{sync::Arc, time::Duration};
+
+use bevy::{
+    audio::{Decodable, Source},
+    prelude::*,
+};
+
+const SAMPLE_RATE: u32 = 44_100;
+// Notes fade in over this long, so they start without a click
+const ATTACK: f32 = 0.004;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+}
+
+impl Waveform {
+    // The waveform's value at `phase`, which is measured in cycles
+    fn sample(self, phase: f32) -> f32 {
+        let phase = phase.fract();
+        match self {
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note {
+    /// In Hz, or `None` for a rest
+    pub frequency: Option<f32>,
+    /// The frequency slides from `frequency` to this by the end of the note
+    pub end_frequency: Option<f32>,
+    pub duration: f32,
+}
+
+impl Note {
+    /// `semitones` above or below A4
+    pub fn semitones(semitones: i32, duration: f32) -> Note {
+        let frequency = 440.0 * 2f32.powf(semitones as f32 / 12.0);
+        Note {
+            frequency: Some(frequency),
+            end_frequency: None,
+            duration,
+        }
+    }
+
+    pub fn rest(duration: f32) -> Note {
+        Note {
+            frequency: None,
+            end_frequency: None,
+            duration,
+        }
+    }
+
+    /// A note whose pitch slides from `from` to `to` Hz
+    pub fn slide(from: f32, to: f32, duration: f32) -> Note {
+        Note {
+            frequency: Some(from),
+            end_frequency: Some(to),
+            duration,
+        }
+    }
+}
+
+/// Notes played in order with one waveform, as an audio source for `AudioPlayer<Tune>`
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Tune {
+    pub waveform: Waveform,
+    /// How quickly each note fades, as the fraction of its volume lost per second
+    pub decay: f32,
+    pub notes: Arc<[Note]>,
+}
+
+impl Decodable for Tune {
+    type DecoderItem = f32;
+    type Decoder = TuneDecoder;
+
+    fn decoder(&self) -> TuneDecoder {
+        TuneDecoder {
+            tune: self.clone(),
+            note: 0,
+            sample: 0,
+            phase: 0.0,
+        }
+    }
+}
+
+/// Plays a [`Tune`], one mono sample at a time
+pub struct TuneDecoder {
+    tune: Tune,
+    note: usize,
+    // Samples played of the current note
+    sample: u32,
+    phase: f32,
+}
+
+impl Iterator for TuneDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            let note = self.tune.notes.get(self.note)?;
+            let length = (note.duration * SAMPLE_RATE as f32) as u32;
+            if self.sample >= length {
+                self.note += 1;
+                self.sample = 0;
+                continue;
+            }
+
+            let time = self.sample as f32 / SAMPLE_RATE as f32;
+            self.sample += 1;
+            let Some(start_frequency) = note.frequency else {
+                return Some(0.0);
+            };
+
+            let progress = time / note.duration;
+            let frequency =
+                start_frequency.lerp(note.end_frequency.unwrap_or(start_frequency), progress);
+            self.phase += frequency / SAMPLE_RATE as f32;
+
+            let attack = (time / ATTACK).min(1.0);
+            let decay = (1.0 - self.tune.decay).max(0.0).powf(time);
+            return Some(self.tune.waveform.sample(self.phase) * attack * decay);
+        }
+    }
+}
+
+impl Source for TuneDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let seconds = self.tune.notes.iter().map(|note| note.duration).sum();
+        Some(Duration::from_secs_f32(seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tune_plays_every_note_then_ends() {
+        let tune = Tune {
+            waveform: Waveform::Square,
+            decay: 0.0,
+            notes: Arc::new([Note::semitones(0, 0.01), Note::rest(0.02)]),
+        };
+
+        let samples: Vec<f32> = tune.decoder().collect();
+        assert_eq!(samples.len(), 441 + 882);
+        assert!(samples[..441].iter().any(|&sample| sample > 0.5));
+        assert!(samples[441..].iter().all(|&sample| sample == 0.0));
+        assert_eq!(
+            tune.decoder().total_duration(),
+            Some(Duration::from_secs_f32(0.03))
+        );
+    }
+
+    #[test]
+    fn notes_decay() {
+        let tune = Tune {
+            waveform: Waveform::Square,
+            decay: 0.99,
+            notes: Arc::new([Note::semitones(0, 1.0)]),
+        };
+
+        let samples: Vec<f32> = tune.decoder().collect();
+        let loudest = |range: std::ops::Range<usize>| {
+            samples[range]
+                .iter()
+                .fold(0.0f32, |loudest, sample| loudest.max(sample.abs()))
+        };
+        assert!(loudest(0..4410) > 4.0 * loudest(40000..44100));
+    }
+
+    #[test]
+    fn waveforms_stay_in_range() {
+        for waveform in [Waveform::Sine, Waveform::Square, Waveform::Triangle] {
+            for step in 0..100 {
+                let sample = waveform.sample(step as f32 / 37.0);
+                assert!((-1.0..=1.0).contains(&sample), "{waveform:?}: {sample}");
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Two players on one keyboard: one paddle at the bottom of the arena, one at the top,
+//! and the bricks in between.
+//!
+//! Each player has their own [`Standing`], kept on their paddle. Points go to whoever last
+//! hit the ball, and a ball that gets past a paddle costs that paddle's player a life.
+//! The match is over when a player runs out of lives, or when the last brick is destroyed,
+//! in which case the player with the most points wins.
+
+use bevy::prelude::*;
+
+use crate::{
+    Ball, BallLost, Brick, STARTING_LIVES, StuckToPaddle, Velocity, arena::ArenaConfig,
+    difficulty::BallSpeedMultiplier, level::BrickKind, scoring::Combo, state::GameState,
+};
+
+/// Whether one player is working through the levels, or two are playing against each other
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Solo,
+    Versus,
+}
+
+/// A run condition for systems that only apply to versus matches
+pub fn is_versus(game_mode: Res<GameMode>) -> bool {
+    *game_mode == GameMode::Versus
+}
+
+/// Who a paddle belongs to, who last hit a ball, or whose kill zone a ball fell into.
+///
+/// Solo games only have player one, at the bottom of the arena.
+#[derive(Component, Reflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub enum Player {
+    #[default]
+    One,
+    Two,
+}
+
+impl Player {
+    pub const ALL: [Player; 2] = [Player::One, Player::Two];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Player::One => "Player One",
+            Player::Two => "Player Two",
+        }
+    }
+
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::One => Player::Two,
+            Player::Two => Player::One,
+        }
+    }
+
+    /// Which way this player sends the ball: 1.0 for up the arena, -1.0 for down it
+    pub fn forward(self) -> f32 {
+        match self {
+            Player::One => 1.0,
+            Player::Two => -1.0,
+        }
+    }
+
+    pub(crate) fn index(self) -> usize {
+        match self {
+            Player::One => 0,
+            Player::Two => 1,
+        }
+    }
+
+    /// Player two's side of the arena is player one's, upside down
+    pub fn mirror_y(self, arena: &ArenaConfig, y: f32) -> f32 {
+        match self {
+            Player::One => y,
+            Player::Two => arena.top + arena.bottom - y,
+        }
+    }
+
+    /// The height of the center of this player's paddle
+    pub fn paddle_y(self, arena: &ArenaConfig) -> f32 {
+        self.mirror_y(arena, arena.paddle_y())
+    }
+
+    /// Where this player's ball waits to be launched, on the arena side of their paddle
+    pub fn ball_starting_position(self, arena: &ArenaConfig) -> Vec3 {
+        let position = arena.ball_starting_position();
+        position.with_y(self.mirror_y(arena, position.y))
+    }
+}
+
+/// A player's points and remaining lives in a versus match
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Standing {
+    pub score: usize,
+    pub lives: u32,
+}
+
+impl Default for Standing {
+    fn default() -> Self {
+        Standing {
+            score: 0,
+            lives: STARTING_LIVES,
+        }
+    }
+}
+
+/// How the last versus match ended
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchResult {
+    /// `None` for a draw
+    pub winner: Option<Player>,
+    /// Each player's points, in the order of [`Player::ALL`]
+    pub scores: [usize; 2],
+}
+
+// Level layouts sit at the top of the arena, which is player two's end in a versus match,
+// so move them to the middle, the same distance from both paddles
+pub(crate) fn center_bricks(bricks: &mut [(Vec2, BrickKind)], arena: &ArenaConfig) {
+    if bricks.is_empty() {
+        return;
+    }
+
+    let (min_y, max_y) = bricks.iter().fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(min_y, max_y), (position, _)| (min_y.min(position.y), max_y.max(position.y)),
+    );
+    let offset = (arena.bottom + arena.top) / 2.0 - (min_y + max_y) / 2.0;
+    for (position, _) in bricks {
+        position.y += offset;
+    }
+}
+
+// Each lost ball costs the player whose end it went out of a life, and they serve it again
+#[allow(clippy::type_complexity)]
+pub(crate) fn lose_lives(
+    mut commands: Commands,
+    mut ball_lost_events: EventReader<BallLost>,
+    mut standings: Query<(&Player, &mut Standing), Without<Ball>>,
+    mut ball_query: Query<(&mut Transform, &mut Velocity, &mut Player), With<Ball>>,
+    mut speed_multiplier: ResMut<BallSpeedMultiplier>,
+    mut combo: ResMut<Combo>,
+    arena: Res<ArenaConfig>,
+) {
+    let mut any_lost = false;
+
+    for &BallLost(ball, player) in ball_lost_events.read() {
+        any_lost = true;
+
+        if let Some((_, mut standing)) = standings.iter_mut().find(|(owner, _)| **owner == player) {
+            standing.lives = standing.lives.saturating_sub(1);
+        }
+
+        if let Ok((mut transform, mut velocity, mut owner)) = ball_query.get_mut(ball) {
+            transform.translation = player.ball_starting_position(&arena);
+            **velocity = Vec2::ZERO;
+            *owner = player;
+            commands.entity(ball).insert(StuckToPaddle);
+        }
+    }
+
+    // Like losing a life in a solo game, the rally starts over
+    if any_lost {
+        speed_multiplier.reset();
+        combo.reset();
+    }
+}
+
+// The match ends when a player has no lives left, or there are no bricks left to score from
+pub(crate) fn check_for_match_over(
+    mut commands: Commands,
+    standings: Query<(&Player, &Standing)>,
+    bricks: Query<&Brick>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let out_of_lives = standings
+        .iter()
+        .find(|(_, standing)| standing.lives == 0)
+        .map(|(player, _)| *player);
+    let bricks_cleared = bricks.iter().all(|brick| !brick.kind.is_breakable());
+    if out_of_lives.is_none() && !bricks_cleared {
+        return;
+    }
+
+    let mut scores = [0; 2];
+    for (player, standing) in &standings {
+        scores[player.index()] = standing.score;
+    }
+
+    let winner = match out_of_lives {
+        Some(loser) => Some(loser.opponent()),
+        None if scores[0] > scores[1] => Some(Player::One),
+        None if scores[1] > scores[0] => Some(Player::Two),
+        None => None,
+    };
+
+    commands.insert_resource(MatchResult { winner, scores });
+    next_state.set(GameState::GameOver);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_two_plays_upside_down() {
+        let arena = ArenaConfig::default();
+
+        assert_eq!(Player::One.paddle_y(&arena), arena.paddle_y());
+        assert_eq!(
+            Player::Two.paddle_y(&arena),
+            arena.top - (arena.paddle_y() - arena.bottom)
+        );
+        assert_eq!(
+            Player::Two.ball_starting_position(&arena).y,
+            -arena.ball_starting_position().y
+        );
+        assert_eq!(Player::One.forward(), -Player::Two.forward());
+    }
+
+    #[test]
+    fn bricks_are_centered_between_the_paddles() {
+        let arena = ArenaConfig::default();
+        let mut bricks = vec![
+            (Vec2::new(0.0, 250.0), BrickKind::Normal),
+            (Vec2::new(100.0, 150.0), BrickKind::Explosive),
+        ];
+
+        center_bricks(&mut bricks, &arena);
+        assert_eq!(bricks[0].0, Vec2::new(0.0, 50.0));
+        assert_eq!(bricks[1].0, Vec2::new(100.0, -50.0));
+    }
+}
@@ -0,0 +1,171 @@
+//! Catches balls that are stuck in a loop and nudges them out of it.
+//!
+//! Bounces off walls and bricks only ever flip one component of a ball's velocity,
+//! so a ball that is moving straight up and down, or straight across, keeps doing so forever,
+//! and a ball can also settle into a longer loop between unbreakable bricks and the walls.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{Ball, StuckToPaddle, Velocity};
+
+// A ball whose direction is within this much of an axis (as the sine of the angle)
+// is moving almost straight up and down, or straight across
+const AXIS_TOLERANCE: f32 = 0.05;
+// A ball is back where it was before if it is within this distance of an earlier position,
+// moving in the same direction. This is more than a ball moves in one timestep,
+// so that positions sampled at different points along the same loop still match.
+const REPEAT_DISTANCE: f32 = 8.0;
+const REPEAT_DIRECTION_DOT: f32 = 0.999;
+// Positions from the last few timesteps are always close by, so they don't count as repeats
+const MIN_LOOP_TICKS: usize = 16;
+// How many timesteps of positions to remember, which is the longest loop that can be caught
+const HISTORY_TICKS: usize = 64 * 5;
+// How many timesteps in a row a ball has to look stuck before it is nudged
+const STUCK_TICKS: u32 = 64 * 3;
+// How far a stuck ball is turned
+const NUDGE_ANGLE: f32 = 5.0 * std::f32::consts::PI / 180.0;
+
+/// Where a ball has been recently, and for how long it has looked stuck
+#[derive(Component, Default)]
+pub(crate) struct Trajectory {
+    history: VecDeque<(Vec2, Vec2)>,
+    stuck_ticks: u32,
+}
+
+impl Trajectory {
+    /// Record the ball's position and direction for this timestep,
+    /// returning `true` once it has looked stuck for long enough to need a nudge
+    fn update(&mut self, position: Vec2, direction: Vec2) -> bool {
+        let near_axis = direction.x.abs() < AXIS_TOLERANCE || direction.y.abs() < AXIS_TOLERANCE;
+        let repeated = self.history.iter().rev().skip(MIN_LOOP_TICKS).any(
+            |&(earlier_position, earlier_direction)| {
+                earlier_position.distance(position) < REPEAT_DISTANCE
+                    && earlier_direction.dot(direction) > REPEAT_DIRECTION_DOT
+            },
+        );
+
+        if near_axis || repeated {
+            self.stuck_ticks += 1;
+        } else {
+            self.stuck_ticks = 0;
+        }
+
+        if self.history.len() == HISTORY_TICKS {
+            self.history.pop_front();
+        }
+        self.history.push_back((position, direction));
+
+        self.stuck_ticks >= STUCK_TICKS
+    }
+
+    fn clear(&mut self) {
+        self.history.clear();
+        self.stuck_ticks = 0;
+    }
+}
+
+/// Turns `velocity` by a small angle towards the nearest diagonal, away from both axes.
+/// The same velocity is always nudged the same way, so that replays stay deterministic.
+pub(crate) fn nudge(velocity: Vec2) -> Vec2 {
+    let towards_diagonal = if velocity.x.abs() < velocity.y.abs() {
+        -1.0
+    } else {
+        1.0
+    };
+    let angle = NUDGE_ANGLE * towards_diagonal * (velocity.x * velocity.y).signum();
+
+    Vec2::from_angle(angle).rotate(velocity)
+}
+
+// Runs right after the balls have moved, so each ball's position is sampled once per timestep
+pub(crate) fn watch_for_stuck_balls(
+    mut ball_query: Query<
+        (
+            &Transform,
+            &mut Velocity,
+            &mut Trajectory,
+            Has<StuckToPaddle>,
+        ),
+        With<Ball>,
+    >,
+) {
+    for (transform, mut velocity, mut trajectory, stuck_to_paddle) in &mut ball_query {
+        if stuck_to_paddle {
+            trajectory.clear();
+            continue;
+        }
+
+        let Some(direction) = velocity.try_normalize() else {
+            continue;
+        };
+
+        if trajectory.update(transform.translation.truncate(), direction) {
+            **velocity = nudge(**velocity);
+            trajectory.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn angle_from_axes(velocity: Vec2) -> f32 {
+        let direction = velocity.normalize();
+        direction.x.abs().min(direction.y.abs()).asin()
+    }
+
+    #[test]
+    fn nudges_away_from_axes_and_keeps_speed() {
+        for velocity in [
+            Vec2::new(0.0, 400.0),
+            Vec2::new(0.0, -400.0),
+            Vec2::new(-400.0, 0.0),
+            Vec2::new(1e-3, 400.0),
+            Vec2::new(-1e-3, -400.0),
+            Vec2::new(400.0, -1e-3),
+        ] {
+            let nudged = nudge(velocity);
+            assert!((nudged.length() - 400.0).abs() < 1e-2, "{velocity}");
+            assert!(
+                angle_from_axes(nudged) > angle_from_axes(velocity) + NUDGE_ANGLE * 0.99,
+                "{velocity} was nudged to {nudged}"
+            );
+        }
+    }
+
+    #[test]
+    fn diagonal_balls_that_keep_moving_are_left_alone() {
+        let mut trajectory = Trajectory::default();
+        let direction = Vec2::new(1.0, 1.0).normalize();
+
+        for tick in 0..STUCK_TICKS * 2 {
+            let position = direction * tick as f32 * 5.0;
+            assert!(!trajectory.update(position, direction));
+        }
+    }
+
+    #[test]
+    fn balls_going_round_in_a_loop_are_caught() {
+        let mut trajectory = Trajectory::default();
+        // Bouncing diagonally around the same diamond, 100 timesteps per lap
+        let corners = [
+            Vec2::new(0.0, -100.0),
+            Vec2::new(100.0, 0.0),
+            Vec2::new(0.0, 100.0),
+            Vec2::new(-100.0, 0.0),
+        ];
+
+        let caught = (0..STUCK_TICKS as usize + 200).position(|tick| {
+            let side = (tick / 25) % 4;
+            let (from, to) = (corners[side], corners[(side + 1) % 4]);
+            let along = (tick % 25) as f32 / 25.0;
+            trajectory.update(from.lerp(to, along), (to - from).normalize())
+        });
+
+        // The first lap is new, after that it is stuck
+        assert_eq!(caught, Some(100 + STUCK_TICKS as usize - 1));
+    }
+}
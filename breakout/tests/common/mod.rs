@@ -0,0 +1,114 @@
+//! A headless harness for driving `BreakoutSimPlugin` one fixed timestep at a time
+
+// Each integration test is its own crate, and not all of them use every helper
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::{
+    ecs::query::QueryFilter, prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy,
+};
+use breakout::{
+    BreakoutSimPlugin, CurrentLevel, LevelSeed, Levels, arena::ArenaConfig, level::Level,
+    state::GameState, versus::GameMode,
+};
+
+/// The simulation without any rendering, audio, windows or input.
+/// Each `update` advances the clock by exactly one fixed timestep, so runs `FixedUpdate` once.
+pub fn sim_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ))
+        .add_plugins(BreakoutSimPlugin);
+    app
+}
+
+/// Levels load in the background, so wait for them before starting one
+pub fn wait_for_levels(app: &mut App) {
+    for _ in 0..1000 {
+        app.update();
+        let levels = app.world().resource::<Levels>();
+        let asset_server = app.world().resource::<AssetServer>();
+        if levels
+            .0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+        {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("levels did not load");
+}
+
+/// A loaded app, with the paddle, ball and bricks of `level` spawned and one timestep run
+pub fn start_level(level: usize, seed: u64) -> App {
+    let mut app = sim_app();
+    wait_for_levels(&mut app);
+
+    app.world_mut().insert_resource(CurrentLevel(level));
+    app.world_mut().insert_resource(LevelSeed(seed));
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app.update();
+    app
+}
+
+/// Like [`start_level`], but as a two-player versus match
+pub fn start_versus(level: usize, seed: u64) -> App {
+    let mut app = sim_app();
+    wait_for_levels(&mut app);
+
+    app.world_mut().insert_resource(GameMode::Versus);
+    app.world_mut().insert_resource(CurrentLevel(level));
+    app.world_mut().insert_resource(LevelSeed(seed));
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app.update();
+    app
+}
+
+/// Like [`start_level`], but playing a level parsed from `ron` instead of a bundled one
+pub fn start_custom_level(ron: &str) -> App {
+    let mut app = sim_app();
+    wait_for_levels(&mut app);
+
+    let arena = app.world().resource::<ArenaConfig>();
+    let level = Level::parse(ron.as_bytes(), arena).unwrap();
+    let handle = app.world_mut().resource_mut::<Assets<Level>>().add(level);
+    app.world_mut().insert_resource(Levels(vec![handle]));
+    app.world_mut().insert_resource(CurrentLevel(0));
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app.update();
+    app
+}
+
+pub fn tick(app: &mut App, ticks: usize) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+pub fn state(app: &App) -> GameState {
+    *app.world().resource::<State<GameState>>().get()
+}
+
+pub fn count<F: QueryFilter>(app: &mut App) -> usize {
+    app.world_mut()
+        .query_filtered::<(), F>()
+        .iter(app.world())
+        .count()
+}
+
+pub fn single<F: QueryFilter>(app: &mut App) -> Entity {
+    app.world_mut()
+        .query_filtered::<Entity, F>()
+        .single(app.world())
+        .unwrap()
+}
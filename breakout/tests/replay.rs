@@ -0,0 +1,223 @@
+//! Records levels and plays them back headless, checking that they end the same way
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use breakout::{
+    Ball, CurrentLevel, LevelSeed, Levels, Lives,
+    arena::ArenaConfig,
+    difficulty::Difficulty,
+    input::PaddleIntent,
+    level::{CustomLevel, Level},
+    replay::{Recording, Replay, ReplayOutcome, ReplayPlugin, ReplayResult},
+    save_game::SavedGame,
+    state::GameState,
+};
+use common::{sim_app, start_level, state, tick, wait_for_levels};
+
+// Set to rewrite the outcome of the recordings in `replays/` after a deliberate gameplay change
+const BLESS_VAR: &str = "BLESS_REPLAYS";
+const MAX_TICKS: usize = 64 * 60 * 5;
+
+fn run_replay(recording: Recording) -> ReplayResult {
+    let mut app = sim_app();
+    app.add_plugins(ReplayPlugin::default())
+        .insert_resource(Replay::new(recording));
+    wait_for_levels(&mut app);
+
+    for _ in 0..MAX_TICKS {
+        app.update();
+        if let Some(result) = app.world_mut().remove_resource::<ReplayResult>() {
+            return result;
+        }
+    }
+    panic!("replay did not finish");
+}
+
+// Play a level with a single life, hitting the ball with different parts of the paddle
+// for a while, then giving up so that the level always ends
+fn record_level(level: usize, seed: u64, directory: &Path) -> Recording {
+    let mut app = sim_app();
+    app.add_plugins(ReplayPlugin {
+        record_to: Some(directory.to_path_buf()),
+        replay: None,
+    });
+    wait_for_levels(&mut app);
+
+    app.world_mut().insert_resource(CurrentLevel(level));
+    app.world_mut().insert_resource(LevelSeed(seed));
+    app.world_mut().insert_resource(Lives(1));
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+
+    for tick in 0..MAX_TICKS {
+        app.update();
+        if state(&app) != GameState::Playing {
+            break;
+        }
+
+        let mut balls = app.world_mut().query_filtered::<&Transform, With<Ball>>();
+        let ball_x = balls
+            .iter(app.world())
+            .next()
+            .map_or(0.0, |transform| transform.translation.x);
+        let offset = ((tick / 200) % 5) as f32 * 20.0 - 40.0;
+        let target_x = if tick < MAX_TICKS / 2 {
+            ball_x + offset
+        } else {
+            -1000.0
+        };
+
+        *app.world_mut().resource_mut::<PaddleIntent>() = PaddleIntent {
+            direction: 0.0,
+            target_x: Some(target_x),
+            launch: true,
+        };
+    }
+
+    let path = directory.join(format!("level-{:02}-{seed}.replay", level + 1));
+    Recording::load(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+fn replays_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("replays")
+}
+
+#[test]
+fn replay_reproduces_recorded_level() {
+    let directory = std::env::temp_dir().join(format!("breakout-replay-{}", std::process::id()));
+    let recording = record_level(1, 42, &directory);
+    let _ = fs::remove_dir_all(&directory);
+
+    assert!(recording.outcome.score > 0, "{:?}", recording.outcome);
+    let result = run_replay(recording);
+    assert!(result.matches(), "{result:?}");
+}
+
+#[test]
+fn bundled_replays_match() {
+    let bless = std::env::var_os(BLESS_VAR).is_some();
+
+    for entry in fs::read_dir(replays_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        let mut recording = Recording::load(&path).unwrap();
+        let result = run_replay(recording.clone());
+
+        if bless {
+            recording.outcome = result.actual;
+            recording.save(&path).unwrap();
+        } else {
+            assert!(
+                result.matches(),
+                "{}: {result:?}\nIf gameplay changed on purpose, rerun with {BLESS_VAR}=1",
+                path.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn replay_of_a_missing_level_exits_with_an_error() {
+    let mut app = sim_app();
+    app.add_plugins(ReplayPlugin::default());
+    wait_for_levels(&mut app);
+
+    let levels = app.world().resource::<Levels>().0.len();
+    app.insert_resource(Replay::new(Recording {
+        level: levels,
+        seed: 0,
+        difficulty: Difficulty::Normal,
+        lives: 3,
+        score: 0,
+        inputs: Vec::new(),
+        outcome: ReplayOutcome {
+            score: 0,
+            bricks: 0,
+        },
+    }));
+    app.update();
+    app.update();
+
+    assert_eq!(state(&app), GameState::Title);
+    assert_eq!(app.should_exit(), Some(AppExit::error()));
+}
+
+#[test]
+fn custom_levels_are_not_recorded() {
+    let directory =
+        std::env::temp_dir().join(format!("breakout-replay-custom-{}", std::process::id()));
+    let mut app = sim_app();
+    app.add_plugins(ReplayPlugin {
+        record_to: Some(directory.clone()),
+        replay: None,
+    });
+    wait_for_levels(&mut app);
+
+    let arena = app.world().resource::<ArenaConfig>();
+    let level = Level::parse(br##"(name: "t", layout: ["#"])"##, arena).unwrap();
+    let handle = app.world_mut().resource_mut::<Assets<Level>>().add(level);
+    app.world_mut().insert_resource(CustomLevel(handle));
+    app.world_mut().insert_resource(Lives(1));
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app.update();
+
+    lose_the_level(&mut app);
+    assert!(!directory.exists());
+}
+
+// Launch the ball and let it fall past the paddle, losing the last life
+fn lose_the_level(app: &mut App) {
+    *app.world_mut().resource_mut::<PaddleIntent>() = PaddleIntent {
+        direction: 0.0,
+        target_x: Some(-1000.0),
+        launch: true,
+    };
+    for _ in 0..MAX_TICKS {
+        app.update();
+        if state(app) != GameState::Playing {
+            break;
+        }
+    }
+    assert_eq!(state(app), GameState::GameOver);
+}
+
+#[test]
+fn continued_levels_are_not_recorded() {
+    let mut app = start_level(0, 1);
+    app.world_mut().insert_resource(Lives(1));
+    tick(&mut app, 10);
+    let saved_game = SavedGame::capture(app.world_mut());
+    let ron = {
+        let type_registry = app.world().resource::<AppTypeRegistry>().read();
+        saved_game.to_ron(&type_registry).unwrap()
+    };
+
+    let directory =
+        std::env::temp_dir().join(format!("breakout-replay-continued-{}", std::process::id()));
+    let mut app = sim_app();
+    app.add_plugins(ReplayPlugin {
+        record_to: Some(directory.clone()),
+        replay: None,
+    });
+    wait_for_levels(&mut app);
+
+    let type_registry = app.world().resource::<AppTypeRegistry>().clone();
+    let saved_game = SavedGame::parse(&ron, &type_registry.read()).unwrap();
+    saved_game.resume(app.world_mut()).unwrap();
+    app.update();
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app.update();
+
+    lose_the_level(&mut app);
+    assert!(!directory.exists());
+}
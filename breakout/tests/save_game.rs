@@ -0,0 +1,221 @@
+//! Saves games part way through a level and continues them in a fresh app
+
+mod common;
+
+use bevy::prelude::*;
+use breakout::{
+    Ball, Brick, CurrentLevel, Lives, Paddle, Score, StuckToPaddle, Velocity,
+    input::PaddleIntent,
+    level::BrickKind,
+    obstacle::Patrol,
+    save_game::{SaveGameError, SavedGame},
+    state::{GameState, LevelClock},
+};
+use common::{count, sim_app, start_level, state, tick, wait_for_levels};
+
+// The bricks, balls and paddles in play, in a stable order
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    bricks: Vec<(Vec2, BrickKind, u32)>,
+    balls: Vec<(Vec2, Vec2, bool)>,
+    paddles: Vec<Vec2>,
+}
+
+fn by_position(a: &Vec2, b: &Vec2) -> std::cmp::Ordering {
+    a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y))
+}
+
+fn snapshot(app: &mut App) -> Snapshot {
+    let world = app.world_mut();
+
+    let mut bricks: Vec<_> = world
+        .query::<(&Transform, &Brick)>()
+        .iter(world)
+        .map(|(transform, brick)| {
+            (
+                transform.translation.truncate(),
+                brick.kind,
+                brick.hit_points,
+            )
+        })
+        .collect();
+    bricks.sort_by(|a, b| by_position(&a.0, &b.0));
+
+    let mut balls: Vec<_> = world
+        .query_filtered::<(&Transform, &Velocity, Has<StuckToPaddle>), With<Ball>>()
+        .iter(world)
+        .map(|(transform, velocity, stuck)| (transform.translation.truncate(), **velocity, stuck))
+        .collect();
+    balls.sort_by(|a, b| by_position(&a.0, &b.0));
+
+    let mut paddles: Vec<_> = world
+        .query_filtered::<&Transform, With<Paddle>>()
+        .iter(world)
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    paddles.sort_by(by_position);
+
+    Snapshot {
+        bricks,
+        balls,
+        paddles,
+    }
+}
+
+fn to_ron(app: &mut App) -> String {
+    let saved_game = SavedGame::capture(app.world_mut());
+    let type_registry = app.world().resource::<AppTypeRegistry>().read();
+    saved_game.to_ron(&type_registry).unwrap()
+}
+
+fn resume(ron: &str) -> App {
+    let mut app = sim_app();
+    wait_for_levels(&mut app);
+
+    let type_registry = app.world().resource::<AppTypeRegistry>().clone();
+    let saved_game = SavedGame::parse(ron, &type_registry.read()).unwrap();
+    saved_game.resume(app.world_mut()).unwrap();
+    app.update();
+    app
+}
+
+#[test]
+fn continues_where_the_game_was_saved() {
+    let mut app = start_level(1, 3);
+
+    // Play for a while, then change things that a fresh level wouldn't have
+    app.world_mut().resource_mut::<PaddleIntent>().direction = -1.0;
+    tick(&mut app, 10);
+    app.world_mut().resource_mut::<PaddleIntent>().launch = true;
+    tick(&mut app, 40);
+
+    let first_brick = app
+        .world_mut()
+        .query_filtered::<Entity, With<Brick>>()
+        .iter(app.world())
+        .next()
+        .unwrap();
+    app.world_mut().despawn(first_brick);
+    app.world_mut().insert_resource(Score(42));
+    app.world_mut().insert_resource(Lives(2));
+
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Paused);
+    app.update();
+    let saved = snapshot(&mut app);
+    let ron = to_ron(&mut app);
+
+    let mut resumed = resume(&ron);
+
+    assert_eq!(state(&resumed), GameState::Paused);
+    assert_eq!(**resumed.world().resource::<Score>(), 42);
+    assert_eq!(**resumed.world().resource::<Lives>(), 2);
+    assert_eq!(**resumed.world().resource::<CurrentLevel>(), 1);
+    assert_eq!(snapshot(&mut resumed), saved);
+
+    // The game carries on from there once it is unpaused
+    resumed
+        .world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    tick(&mut resumed, 10);
+    assert_eq!(state(&resumed), GameState::Playing);
+    assert_ne!(snapshot(&mut resumed).balls, saved.balls);
+
+    // Leaving the level despawns the restored entities along with everything else
+    resumed
+        .world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Title);
+    tick(&mut resumed, 1);
+    assert_eq!(count::<With<Brick>>(&mut resumed), 0);
+    assert_eq!(count::<With<Ball>>(&mut resumed), 0);
+    assert_eq!(count::<With<Paddle>>(&mut resumed), 0);
+}
+
+#[test]
+fn stuck_ball_stays_on_the_paddle() {
+    let mut app = start_level(0, 1);
+    app.world_mut().resource_mut::<PaddleIntent>().direction = 1.0;
+    tick(&mut app, 20);
+    let saved = snapshot(&mut app);
+
+    let mut resumed = resume(&to_ron(&mut app));
+    assert_eq!(snapshot(&mut resumed), saved);
+    assert!(snapshot(&mut resumed).balls[0].2);
+}
+
+#[test]
+fn moving_bricks_keep_moving() {
+    let mut app = start_level(5, 1);
+    tick(&mut app, 30);
+
+    let mut resumed = resume(&to_ron(&mut app));
+    let moving = |app: &mut App| {
+        let world = app.world_mut();
+        let mut positions: Vec<Vec2> = world
+            .query_filtered::<&Transform, (With<Brick>, With<Patrol>)>()
+            .iter(world)
+            .map(|transform| transform.translation.truncate())
+            .collect();
+        positions.sort_by(by_position);
+        positions
+    };
+    let saved = moving(&mut resumed);
+    assert_eq!(saved.len(), 2);
+
+    resumed
+        .world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    tick(&mut resumed, 10);
+    assert_ne!(moving(&mut resumed), saved);
+}
+
+#[test]
+fn continued_levels_remember_how_they_started() {
+    let mut app = start_level(0, 1);
+    app.world_mut().resource_mut::<PaddleIntent>().launch = true;
+    tick(&mut app, 100);
+    app.world_mut().insert_resource(Score(42));
+    let clock = **app.world().resource::<LevelClock>();
+
+    // The clock carries on from where the game was saved
+    let mut resumed = resume(&to_ron(&mut app));
+    assert_eq!(**resumed.world().resource::<LevelClock>(), clock);
+    resumed
+        .world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    tick(&mut resumed, 10);
+    assert!(**resumed.world().resource::<LevelClock>() > clock);
+
+    // Restarting goes back to the start of the level, not to where it was saved
+    resumed
+        .world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Restarting);
+    tick(&mut resumed, 2);
+    assert_eq!(state(&resumed), GameState::Playing);
+    assert_eq!(**resumed.world().resource::<Score>(), 0);
+    assert!(**resumed.world().resource::<LevelClock>() < clock);
+}
+
+#[test]
+fn rejects_saves_it_cannot_continue() {
+    let mut app = start_level(0, 1);
+    app.world_mut().insert_resource(CurrentLevel(99));
+    let ron = to_ron(&mut app);
+
+    let mut fresh = sim_app();
+    wait_for_levels(&mut fresh);
+    let type_registry = fresh.world().resource::<AppTypeRegistry>().clone();
+
+    let saved_game = SavedGame::parse(&ron, &type_registry.read()).unwrap();
+    assert!(matches!(
+        saved_game.resume(fresh.world_mut()),
+        Err(SaveGameError::NoSuchLevel(99))
+    ));
+    assert!(SavedGame::parse("(entities: {", &type_registry.read()).is_err());
+}
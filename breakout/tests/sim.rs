@@ -0,0 +1,588 @@
+//! Drives the game's rules headless, under `MinimalPlugins`, one fixed timestep at a time
+
+mod common;
+
+use bevy::prelude::*;
+use breakout::{
+    Ball, Brick, Collider, CollisionEvent, Levels, Lives, Paddle, Score, StuckToPaddle, Velocity,
+    arena::ArenaConfig,
+    autopilot::Autopilot,
+    difficulty::{BallSpeedMultiplier, Difficulty},
+    input::{PaddleIntent, PlayerTwoIntent},
+    level::{BrickKind, Level, LevelFilePlugin},
+    obstacle::Obstacle,
+    power_up::ActivePowerUps,
+    scoring::ROW_CLEAR_BONUS,
+    state::GameState,
+    versus::{MatchResult, Player, Standing},
+};
+use common::{
+    count, sim_app, single, start_custom_level, start_level, start_versus, state, tick,
+    wait_for_levels,
+};
+
+fn launch(app: &mut App) -> Entity {
+    app.world_mut().resource_mut::<PaddleIntent>().launch = true;
+    tick(app, 1);
+    single::<(With<Ball>, Without<StuckToPaddle>)>(app)
+}
+
+// Put a launched ball somewhere in particular
+fn place_ball(app: &mut App, ball: Entity, position: Vec2, velocity: Vec2) {
+    let mut ball = app.world_mut().entity_mut(ball);
+    ball.get_mut::<Transform>().unwrap().translation = position.extend(1.0);
+    **ball.get_mut::<Velocity>().unwrap() = velocity;
+}
+
+fn position(app: &App, entity: Entity) -> Vec2 {
+    app.world()
+        .get::<Transform>(entity)
+        .unwrap()
+        .translation
+        .truncate()
+}
+
+#[test]
+fn level_spawns_paddle_ball_and_bricks() {
+    let mut app = start_level(0, 1);
+
+    let levels = app.world().resource::<Levels>();
+    let level = app
+        .world()
+        .resource::<Assets<Level>>()
+        .get(&levels.0[0])
+        .unwrap();
+    let brick_count = level
+        .bricks(app.world().resource::<ArenaConfig>())
+        .unwrap()
+        .len();
+
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(count::<With<Paddle>>(&mut app), 1);
+    assert_eq!(count::<(With<Ball>, With<StuckToPaddle>)>(&mut app), 1);
+    assert_eq!(count::<With<Brick>>(&mut app), brick_count);
+}
+
+#[test]
+fn stuck_ball_waits_for_launch_then_moves_every_tick() {
+    let mut app = start_level(0, 1);
+    let ball = single::<With<Ball>>(&mut app);
+    let start = position(&app, ball);
+
+    tick(&mut app, 10);
+    assert_eq!(position(&app, ball), start);
+
+    launch(&mut app);
+    let mut previous = position(&app, ball);
+    for _ in 0..10 {
+        tick(&mut app, 1);
+        let current = position(&app, ball);
+        assert!(
+            current.y > previous.y,
+            "{current} should be above {previous}"
+        );
+        previous = current;
+    }
+}
+
+#[test]
+fn resuming_with_a_launch_binding_leaves_the_ball_on_the_paddle() {
+    let mut app = start_level(0, 1);
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Paused);
+    tick(&mut app, 1);
+
+    // Choosing Resume with Space also reads as a launch
+    app.world_mut().resource_mut::<PaddleIntent>().launch = true;
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    tick(&mut app, 10);
+
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(count::<With<StuckToPaddle>>(&mut app), 1);
+}
+
+#[test]
+fn paddle_follows_intent_and_stays_in_arena() {
+    let mut app = start_level(0, 1);
+    let paddle = single::<With<Paddle>>(&mut app);
+
+    app.world_mut().resource_mut::<PaddleIntent>().direction = 1.0;
+    tick(&mut app, 10);
+    let moved = position(&app, paddle).x;
+    assert!(moved > 0.0);
+
+    // Far more than enough time to cross the arena
+    tick(&mut app, 500);
+    let right_edge = position(&app, paddle).x;
+    assert!(right_edge > moved);
+    tick(&mut app, 10);
+    assert_eq!(position(&app, paddle).x, right_edge);
+
+    // Following a target, such as the mouse, stops on the target
+    *app.world_mut().resource_mut::<PaddleIntent>() = PaddleIntent {
+        target_x: Some(-100.0),
+        ..default()
+    };
+    tick(&mut app, 500);
+    assert_eq!(position(&app, paddle).x, -100.0);
+}
+
+#[test]
+fn losing_the_last_ball_ends_the_game() {
+    let mut app = start_level(0, 1);
+    app.world_mut().insert_resource(Lives(1));
+
+    let ball = launch(&mut app);
+    place_ball(
+        &mut app,
+        ball,
+        Vec2::new(200.0, -280.0),
+        Vec2::new(0.0, -400.0),
+    );
+    tick(&mut app, 10);
+
+    assert_eq!(**app.world().resource::<Lives>(), 0);
+    assert_eq!(state(&app), GameState::GameOver);
+    // Leaving the level despawns everything in it
+    assert_eq!(count::<With<Ball>>(&mut app), 0);
+    assert_eq!(count::<With<Brick>>(&mut app), 0);
+}
+
+#[test]
+fn losing_a_ball_with_lives_left_puts_it_back_on_the_paddle() {
+    let mut app = start_level(0, 1);
+    let lives = **app.world().resource::<Lives>();
+
+    let ball = launch(&mut app);
+    place_ball(
+        &mut app,
+        ball,
+        Vec2::new(200.0, -280.0),
+        Vec2::new(0.0, -400.0),
+    );
+    tick(&mut app, 10);
+
+    assert_eq!(**app.world().resource::<Lives>(), lives - 1);
+    assert_eq!(state(&app), GameState::Playing);
+    assert!(app.world().entity(ball).contains::<StuckToPaddle>());
+}
+
+#[test]
+fn breaking_the_last_brick_clears_the_level() {
+    let mut app = start_level(0, 1);
+
+    let mut bricks = app.world_mut().query_filtered::<Entity, With<Brick>>();
+    let bricks: Vec<Entity> = bricks.iter(app.world()).collect();
+    let (&last_brick, others) = bricks.split_first().unwrap();
+    for &brick in others {
+        app.world_mut().despawn(brick);
+    }
+
+    let ball = launch(&mut app);
+    let below_brick = position(&app, last_brick) - Vec2::new(0.0, 50.0);
+    place_ball(&mut app, ball, below_brick, Vec2::new(0.0, 400.0));
+    tick(&mut app, 10);
+
+    // The last brick is also the last in its row
+    assert_eq!(**app.world().resource::<Score>(), 1 + ROW_CLEAR_BONUS);
+    assert_eq!(state(&app), GameState::LevelCleared);
+}
+
+#[test]
+fn restarting_puts_the_level_back_how_it_started() {
+    let mut app = start_level(0, 1);
+    let bricks = count::<With<Brick>>(&mut app);
+    let score = **app.world().resource::<Score>();
+    let lives = **app.world().resource::<Lives>();
+
+    launch(&mut app);
+    let first_brick = app
+        .world_mut()
+        .query_filtered::<Entity, With<Brick>>()
+        .iter(app.world())
+        .next()
+        .unwrap();
+    app.world_mut().despawn(first_brick);
+    app.world_mut().insert_resource(Score(score + 42));
+    app.world_mut().insert_resource(Lives(lives - 1));
+
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Restarting);
+    tick(&mut app, 2);
+
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(count::<With<Brick>>(&mut app), bricks);
+    assert_eq!(count::<With<StuckToPaddle>>(&mut app), 1);
+    assert_eq!(**app.world().resource::<Score>(), score);
+    assert_eq!(**app.world().resource::<Lives>(), lives);
+}
+
+#[test]
+fn bounces_send_collision_events() {
+    #[derive(Resource, Default)]
+    struct Collisions(usize);
+
+    let mut app = start_level(0, 1);
+    app.init_resource::<Collisions>().add_systems(
+        FixedUpdate,
+        |mut events: EventReader<CollisionEvent>, mut collisions: ResMut<Collisions>| {
+            collisions.0 += events.read().count();
+        },
+    );
+
+    // Straight up into the top wall, with no bricks in the way
+    let ball = launch(&mut app);
+    place_ball(&mut app, ball, Vec2::new(0.0, 200.0), Vec2::new(0.0, 400.0));
+    let mut bricks = app.world_mut().query_filtered::<Entity, With<Brick>>();
+    let bricks: Vec<Entity> = bricks.iter(app.world()).collect();
+    for brick in bricks {
+        app.world_mut().entity_mut(brick).remove::<Collider>();
+    }
+    tick(&mut app, 30);
+
+    assert_eq!(app.world().resource::<Collisions>().0, 1);
+    assert!(app.world().get::<Velocity>(ball).unwrap().y < 0.0);
+}
+
+#[test]
+fn ball_bouncing_straight_up_and_down_is_nudged_out_of_the_loop() {
+    let mut app = start_level(0, 1);
+
+    // The bricks are still there, so the level doesn't end, but the ball passes through them
+    let mut bricks = app.world_mut().query_filtered::<Entity, With<Brick>>();
+    let bricks: Vec<Entity> = bricks.iter(app.world()).collect();
+    for brick in bricks {
+        app.world_mut().entity_mut(brick).remove::<Collider>();
+    }
+
+    // Without any input, the paddle stays in the middle and sends the ball straight back up
+    let ball = launch(&mut app);
+    place_ball(&mut app, ball, Vec2::new(0.0, 0.0), Vec2::new(0.0, 400.0));
+
+    let mut nudged_after = None;
+    for tick_count in 0..64 * 10 {
+        tick(&mut app, 1);
+        let velocity = **app.world().get::<Velocity>(ball).unwrap();
+        if velocity.x.abs() > 1.0 {
+            nudged_after = Some(tick_count);
+            break;
+        }
+    }
+
+    let nudged_after = nudged_after.expect("ball was never nudged");
+    // The first few bounces are just a ball going straight up and down
+    assert!(nudged_after > 64, "{nudged_after}");
+    assert_eq!(state(&app), GameState::Playing);
+}
+
+#[test]
+fn ball_bouncing_straight_across_is_nudged_out_of_the_loop() {
+    let mut app = start_level(0, 1);
+
+    let ball = launch(&mut app);
+    place_ball(&mut app, ball, Vec2::new(0.0, 0.0), Vec2::new(400.0, 0.0));
+    tick(&mut app, 64 * 5);
+
+    let velocity = **app.world().get::<Velocity>(ball).unwrap();
+    assert!(velocity.y.abs() > 1.0, "{velocity}");
+}
+
+#[test]
+fn ball_speeds_up_until_a_life_is_lost() {
+    let mut app = start_level(0, 1);
+    let start = app.world().resource::<BallSpeedMultiplier>().get();
+    assert_eq!(start, Difficulty::Normal.speed_ramp().start);
+
+    // The autopilot keeps the ball in play for a while
+    let ball = launch(&mut app);
+    app.insert_resource(Autopilot);
+    tick(&mut app, 64 * 5);
+    app.world_mut().remove_resource::<Autopilot>();
+    // Without any slow-ball power-up it may have caught
+    app.insert_resource(ActivePowerUps::default());
+    tick(&mut app, 1);
+    let sped_up = app.world().resource::<BallSpeedMultiplier>().get();
+    assert!(sped_up > start, "{sped_up}");
+
+    // The ball's speed follows the multiplier
+    let speed = app.world().get::<Velocity>(ball).unwrap().length();
+    assert!((speed - 400.0 * sped_up).abs() < 1.0, "{speed}");
+
+    place_ball(
+        &mut app,
+        ball,
+        Vec2::new(200.0, -280.0),
+        Vec2::new(0.0, -400.0),
+    );
+    tick(&mut app, 10);
+    assert_eq!(app.world().resource::<BallSpeedMultiplier>().get(), start);
+}
+
+#[test]
+fn harder_difficulties_start_faster() {
+    let mut app = sim_app();
+    app.insert_resource(Difficulty::Hard);
+    wait_for_levels(&mut app);
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app.update();
+
+    let ball = launch(&mut app);
+    let speed = app.world().get::<Velocity>(ball).unwrap().length();
+    let expected = 400.0 * Difficulty::Hard.speed_ramp().start;
+    assert!((speed - expected).abs() < 1.0, "{speed} != {expected}");
+}
+
+#[test]
+fn a_saved_level_file_is_played_instead_of_the_bundled_levels() {
+    let path = std::env::temp_dir().join(format!("breakout-{}.level.ron", std::process::id()));
+    let level = Level {
+        name: "Saved".to_string(),
+        ball_speed: 400.0,
+        gap: 5.0,
+        difficulty: None,
+        stars: Vec::new(),
+        cells: vec![vec![
+            Some(BrickKind::Normal),
+            None,
+            Some(BrickKind::Explosive),
+        ]],
+        obstacles: Vec::new(),
+    };
+    level.save(&path).unwrap();
+
+    let mut app = sim_app();
+    app.add_plugins(LevelFilePlugin {
+        path: Some(path.clone()),
+    });
+    wait_for_levels(&mut app);
+    std::fs::remove_file(&path).unwrap();
+
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app.update();
+    assert_eq!(count::<With<Brick>>(&mut app), 2);
+}
+
+#[test]
+fn level_files_can_override_the_difficulty() {
+    let mut app = start_custom_level(r##"(name: "t", difficulty: Some(Easy), layout: ["#"])"##);
+    assert_eq!(*app.world().resource::<Difficulty>(), Difficulty::Normal);
+    assert_eq!(
+        app.world().resource::<BallSpeedMultiplier>().get(),
+        Difficulty::Easy.speed_ramp().start
+    );
+
+    let ball = launch(&mut app);
+    let speed = app.world().get::<Velocity>(ball).unwrap().length();
+    assert!((speed - 400.0 * Difficulty::Easy.speed_ramp().start).abs() < 1.0);
+}
+
+fn ball_of(app: &mut App, player: Player) -> Entity {
+    let mut balls = app
+        .world_mut()
+        .query_filtered::<(Entity, &Player), With<Ball>>();
+    balls
+        .iter(app.world())
+        .find(|(_, owner)| **owner == player)
+        .map(|(ball, _)| ball)
+        .unwrap()
+}
+
+fn standing(app: &mut App, player: Player) -> Standing {
+    let mut paddles = app.world_mut().query::<(&Player, &Standing)>();
+    paddles
+        .iter(app.world())
+        .find(|(owner, _)| **owner == player)
+        .map(|(_, standing)| *standing)
+        .unwrap()
+}
+
+fn launch_versus(app: &mut App, player: Player) -> Entity {
+    match player {
+        Player::One => app.world_mut().resource_mut::<PaddleIntent>().launch = true,
+        Player::Two => app.world_mut().resource_mut::<PlayerTwoIntent>().launch = true,
+    }
+    tick(app, 1);
+    ball_of(app, player)
+}
+
+#[test]
+fn versus_gives_each_player_a_paddle_and_ball_with_the_bricks_between() {
+    let mut app = start_versus(0, 1);
+
+    assert_eq!(count::<With<Paddle>>(&mut app), 2);
+    assert_eq!(count::<(With<Ball>, With<StuckToPaddle>)>(&mut app), 2);
+
+    let mut paddles = app
+        .world_mut()
+        .query_filtered::<(&Player, &Transform), With<Paddle>>();
+    for (player, transform) in paddles.iter(app.world()) {
+        assert_eq!(transform.translation.y.signum(), -player.forward());
+    }
+
+    let mut bricks = app.world_mut().query_filtered::<&Transform, With<Brick>>();
+    let (min_y, max_y) = bricks.iter(app.world()).fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(min_y, max_y), transform| {
+            (
+                min_y.min(transform.translation.y),
+                max_y.max(transform.translation.y),
+            )
+        },
+    );
+    assert!((min_y + max_y).abs() < 1.0, "{min_y} to {max_y}");
+
+    // Player two launches downwards
+    let ball = launch_versus(&mut app, Player::Two);
+    assert!(app.world().get::<Velocity>(ball).unwrap().y < 0.0);
+}
+
+#[test]
+fn player_two_bounces_the_ball_back_down_and_takes_it_over() {
+    let mut app = start_versus(0, 1);
+
+    let ball = launch_versus(&mut app, Player::One);
+    place_ball(&mut app, ball, Vec2::new(0.0, 200.0), Vec2::new(0.0, 400.0));
+    tick(&mut app, 10);
+
+    assert!(app.world().get::<Velocity>(ball).unwrap().y < 0.0);
+    assert_eq!(app.world().get::<Player>(ball), Some(&Player::Two));
+}
+
+#[test]
+fn a_ball_past_a_paddle_costs_that_player_a_life_and_they_serve() {
+    let mut app = start_versus(0, 1);
+
+    let ball = launch_versus(&mut app, Player::One);
+    place_ball(
+        &mut app,
+        ball,
+        Vec2::new(200.0, 280.0),
+        Vec2::new(0.0, 400.0),
+    );
+    tick(&mut app, 10);
+
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(standing(&mut app, Player::One), Standing::default());
+    assert_eq!(
+        standing(&mut app, Player::Two).lives,
+        Standing::default().lives - 1
+    );
+    assert!(app.world().entity(ball).contains::<StuckToPaddle>());
+    assert_eq!(app.world().get::<Player>(ball), Some(&Player::Two));
+    assert!(position(&app, ball).y > 0.0);
+}
+
+#[test]
+fn running_out_of_lives_loses_the_match() {
+    let mut app = start_versus(0, 1);
+    let mut paddles = app.world_mut().query::<(&Player, &mut Standing)>();
+    for (player, mut standing) in paddles.iter_mut(app.world_mut()) {
+        if *player == Player::One {
+            standing.lives = 1;
+        }
+    }
+
+    let ball = launch_versus(&mut app, Player::One);
+    place_ball(
+        &mut app,
+        ball,
+        Vec2::new(200.0, -280.0),
+        Vec2::new(0.0, -400.0),
+    );
+    tick(&mut app, 10);
+
+    assert_eq!(state(&app), GameState::GameOver);
+    assert_eq!(
+        *app.world().resource::<MatchResult>(),
+        MatchResult {
+            winner: Some(Player::Two),
+            scores: [0, 0],
+        }
+    );
+    assert_eq!(count::<With<Paddle>>(&mut app), 0);
+}
+
+#[test]
+fn breaking_the_last_brick_ends_the_match_with_points_for_whoever_hit_it() {
+    let mut app = start_versus(0, 1);
+
+    let mut bricks = app.world_mut().query_filtered::<Entity, With<Brick>>();
+    let bricks: Vec<Entity> = bricks.iter(app.world()).collect();
+    let (&last_brick, others) = bricks.split_first().unwrap();
+    for &brick in others {
+        app.world_mut().despawn(brick);
+    }
+
+    let ball = launch_versus(&mut app, Player::Two);
+    let above_brick = position(&app, last_brick) + Vec2::new(0.0, 50.0);
+    place_ball(&mut app, ball, above_brick, Vec2::new(0.0, -400.0));
+    tick(&mut app, 10);
+
+    assert_eq!(state(&app), GameState::GameOver);
+    assert_eq!(
+        *app.world().resource::<MatchResult>(),
+        MatchResult {
+            winner: Some(Player::Two),
+            scores: [0, 1 + ROW_CLEAR_BONUS],
+        }
+    );
+    // Versus points don't count towards the solo score
+    assert_eq!(**app.world().resource::<Score>(), 0);
+}
+
+#[test]
+fn ball_bounces_along_the_face_of_a_rotated_bar() {
+    let mut app = start_custom_level(
+        r##"(name: "t", layout: ["#"], obstacles: [
+            (shape: Bar(200.0, 20.0), position: (0.0, 0.0), angle: 45.0),
+        ])"##,
+    );
+    let center = app.world().resource::<ArenaConfig>().center();
+
+    // Dropped onto the bar's upper face, which slopes down to the left
+    let ball = launch(&mut app);
+    place_ball(
+        &mut app,
+        ball,
+        center + Vec2::new(0.0, 100.0),
+        Vec2::new(0.0, -400.0),
+    );
+    tick(&mut app, 20);
+
+    let velocity = **app.world().get::<Velocity>(ball).unwrap();
+    assert!(
+        velocity.normalize().abs_diff_eq(Vec2::NEG_X, 1e-3),
+        "{velocity} should point left"
+    );
+}
+
+#[test]
+fn moving_obstacles_push_the_ball_away() {
+    let mut app = start_custom_level(
+        r##"(name: "t", layout: ["#"], obstacles: [
+            (shape: Disc(40.0), position: (-100.0, 0.0), path: [(300.0, 0.0)], speed: 600.0),
+        ])"##,
+    );
+    let center = app.world().resource::<ArenaConfig>().center();
+    let disc = single::<With<Obstacle>>(&mut app);
+
+    // The disc is faster than the ball, so catches up with it and shoves it along
+    let ball = launch(&mut app);
+    place_ball(&mut app, ball, center, Vec2::new(400.0, 0.0));
+    for _ in 0..40 {
+        tick(&mut app, 1);
+        let gap = position(&app, ball).distance(position(&app, disc));
+        assert!(gap >= 35.0 - 1e-3, "the ball is {gap} from the disc");
+    }
+
+    assert!(position(&app, ball).x > position(&app, disc).x);
+    assert!(app.world().get::<Velocity>(ball).unwrap().x > 0.0);
+}
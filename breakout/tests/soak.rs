@@ -0,0 +1,154 @@
+//! Lets the autopilot play level after level headless, reporting how it went.
+//!
+//! The full soak test is slow, so it is ignored by default. Run it with
+//! `cargo test --release --test soak -- --ignored --nocapture`,
+//! and set `SOAK_LEVELS` to play more or fewer levels than the default.
+
+mod common;
+
+use bevy::prelude::*;
+use breakout::{
+    Ball, Lives, Score, Velocity, autopilot::Autopilot, level::LEVELS, state::GameState,
+};
+use common::{start_level, state, tick};
+
+const SOAK_LEVELS_VAR: &str = "SOAK_LEVELS";
+// A level that takes longer than this is given up on
+const MAX_TICKS: usize = 64 * 60 * 10;
+// A ball that goes this long without scoring or being lost is probably stuck
+const STUCK_TICKS: usize = 64 * 30;
+
+#[derive(Debug)]
+enum Ending {
+    Cleared,
+    GameOver,
+    Stuck {
+        tick: usize,
+        balls: Vec<(Vec2, Vec2)>,
+    },
+    TimedOut,
+}
+
+#[derive(Debug)]
+struct LevelResult {
+    level: usize,
+    seed: u64,
+    score: usize,
+    ending: Ending,
+}
+
+fn balls(app: &mut App) -> Vec<(Vec2, Vec2)> {
+    let mut balls = app
+        .world_mut()
+        .query_filtered::<(&Transform, &Velocity), With<Ball>>();
+    balls
+        .iter(app.world())
+        .map(|(transform, velocity)| (transform.translation.truncate(), **velocity))
+        .collect()
+}
+
+fn play_level(level: usize, seed: u64) -> LevelResult {
+    let mut app = start_level(level, seed);
+    app.insert_resource(Autopilot);
+
+    let mut progress = (0, 0);
+    let mut last_progress_tick = 0;
+    let mut ending = Ending::TimedOut;
+
+    for tick_count in 0..MAX_TICKS {
+        tick(&mut app, 1);
+
+        match state(&app) {
+            GameState::LevelCleared => {
+                ending = Ending::Cleared;
+                break;
+            }
+            GameState::GameOver => {
+                ending = Ending::GameOver;
+                break;
+            }
+            _ => {}
+        }
+
+        let current = (
+            **app.world().resource::<Score>(),
+            **app.world().resource::<Lives>(),
+        );
+        if current != progress {
+            progress = current;
+            last_progress_tick = tick_count;
+        } else if tick_count - last_progress_tick > STUCK_TICKS {
+            ending = Ending::Stuck {
+                tick: tick_count,
+                balls: balls(&mut app),
+            };
+            break;
+        }
+    }
+
+    LevelResult {
+        level,
+        seed,
+        score: **app.world().resource::<Score>(),
+        ending,
+    }
+}
+
+fn soak(levels: usize) -> Vec<LevelResult> {
+    let results: Vec<LevelResult> = (0..levels)
+        .map(|i| play_level(i % LEVELS.len(), i as u64))
+        .collect();
+
+    let average = results.iter().map(|result| result.score).sum::<usize>() as f32
+        / results.len().max(1) as f32;
+    println!(
+        "Played {} levels, average score {average:.1}",
+        results.len()
+    );
+    for result in &results {
+        let ending = match &result.ending {
+            Ending::Stuck { tick, balls } => {
+                format!("stuck after {tick} ticks, balls at (position, velocity) {balls:?}")
+            }
+            ending => format!("{ending:?}"),
+        };
+        println!(
+            "  level {} seed {}: score {}, {ending}",
+            result.level + 1,
+            result.seed,
+            result.score,
+        );
+    }
+
+    results
+}
+
+#[test]
+fn autopilot_clears_the_first_level() {
+    let result = play_level(0, 0);
+    assert!(matches!(result.ending, Ending::Cleared), "{result:?}");
+}
+
+#[test]
+#[ignore = "slow; run with --ignored"]
+fn soak_test() {
+    let levels = std::env::var(SOAK_LEVELS_VAR)
+        .ok()
+        .and_then(|levels| levels.parse().ok())
+        .unwrap_or(LEVELS.len() * 4);
+
+    let results = soak(levels);
+    let stuck: Vec<&LevelResult> = results
+        .iter()
+        .filter(|result| matches!(result.ending, Ending::Stuck { .. } | Ending::TimedOut))
+        .collect();
+    assert!(stuck.is_empty(), "stuck balls: {stuck:#?}");
+}
+
+// Found by the soak test: hitting the middle of the paddle sent the ball straight up
+// into the unbreakable bricks at the bottom of the fortress, and straight back down, forever
+#[test]
+fn autopilot_clears_the_fortress() {
+    let result = play_level(3, 3);
+    assert!(matches!(result.ending, Ending::Cleared), "{result:?}");
+}